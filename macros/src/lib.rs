@@ -0,0 +1,722 @@
+//! Procedural builtins for [`rukt`](https://docs.rs/rukt).
+//!
+//! These builtins need to inspect the characters of string literals or read
+//! the environment of the compiler, which can't be done with declarative
+//! macros. They follow the same [calling
+//! convention](https://docs.rs/rukt/latest/rukt/eval/index.html#calling-convention)
+//! as the other builtins, and are re-exported from `rukt::builtins` when the
+//! corresponding feature is enabled. Utilities that need to create new
+//! identifiers are re-exported from `rukt::utils` in the same way, and the
+//! `proc` feature swaps in procedural versions of utilities that would
+//! otherwise walk their input with deeply recursive declarative macros.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::iter::Peekable;
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn builtin_trim(input: TokenStream) -> TokenStream {
+    string_method(input, "trim", |value| {
+        let literal = Literal::string(value.trim());
+        Ok(TokenTree::Literal(literal))
+    })
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn builtin_lines(input: TokenStream) -> TokenStream {
+    string_method(input, "lines", |value| {
+        let lines = value.lines().map(|line| TokenTree::Literal(Literal::string(line)));
+        Ok(TokenTree::Group(Group::new(Delimiter::Bracket, lines.collect())))
+    })
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn builtin_to_tokens(input: TokenStream) -> TokenStream {
+    string_method(input, "to_tokens", |value| {
+        let tokens: TokenStream = value.parse().map_err(|_| "the contents are not valid Rust tokens")?;
+        let mut iter = tokens.clone().into_iter();
+        match (iter.next(), iter.next()) {
+            (Some(token), None) => Ok(token),
+            _ => Ok(TokenTree::Group(Group::new(Delimiter::Bracket, tokens))),
+        }
+    })
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn builtin_stringify_compact(input: TokenStream) -> TokenStream {
+    let Some(mut state) = State::parse(input) else {
+        return error("rukt: invalid invocation of `stringify_compact`");
+    };
+    let value = match state.tokens.peek() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis && group.stream().is_empty() => {
+            state.tokens.next();
+            let compact = Compact::new(TokenStream::from(state.subject.clone()), false);
+            let text = compact.segments.into_iter().map(|segment| match segment {
+                Segment::Text(text) => text,
+                Segment::Fragment(group) => group.to_string(),
+            });
+            TokenTree::Literal(Literal::string(&text.collect::<String>()))
+        }
+        _ => function_value("stringify_compact", &state.dollar),
+    };
+    state.resume(value)
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn utils_stringify_compact(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(tokens)), Some(TokenTree::Group(next)), None) = (input.next(), input.next(), input.next()) else {
+        return error("rukt: invalid invocation of `stringify_compact`, expected tokens and a continuation");
+    };
+
+    // fragments are left to `stringify!` to produce the same string as the declarative walk
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    for segment in Compact::new(tokens.stream(), true).segments {
+        match segment {
+            Segment::Text(piece) => text.push_str(&piece),
+            Segment::Fragment(group) => {
+                if !text.is_empty() {
+                    parts.push(TokenTree::Literal(Literal::string(&std::mem::take(&mut text))));
+                    parts.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+                }
+                let mut stringify: TokenStream = "::core::stringify!".parse().unwrap();
+                stringify.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, TokenTree::Group(group).into()))]);
+                parts.extend(stringify);
+                parts.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+            }
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TokenTree::Literal(Literal::string(&text)));
+        parts.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+    }
+
+    let mut value: TokenStream = "::core::concat!".parse().unwrap();
+    value.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, parts.into_iter().collect()))]);
+
+    invoke(next.stream(), [TokenTree::Group(Group::new(Delimiter::Bracket, value))], [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn builtin_env(input: TokenStream) -> TokenStream {
+    // forward regular invocations so that importing the builtin doesn't break `env!`
+    let Some(mut state) = State::parse(input.clone()) else {
+        let mut output: TokenStream = "::core::env!".parse().unwrap();
+        output.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, input))]);
+        return output;
+    };
+    let Some(arguments) = state.arguments() else {
+        return error("rukt: expected arguments for `env`, for example `env(\"CARGO_PKG_VERSION\")`");
+    };
+
+    let (name, fallback) = match &arguments[..] {
+        [name] => (string_value(name), None),
+        [name, fallback] => (string_value(name), Some(fallback.clone())),
+        _ => (None, None),
+    };
+    let Some(name) = name else {
+        let arguments: TokenStream = arguments.into_iter().collect();
+        return error(&format!("rukt: invalid arguments `{arguments}` for `env`, expected a string literal and an optional fallback"));
+    };
+
+    match (std::env::var(&name), fallback) {
+        (Ok(value), _) => state.resume(TokenTree::Literal(Literal::string(&value))),
+        (Err(_), Some(fallback)) => state.resume(fallback),
+        (Err(_), None) => error(&format!("rukt: environment variable `{name}` not defined at compile time")),
+    }
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn utils_gensym(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Ident(base)), Some(TokenTree::Group(counter)), Some(TokenTree::Group(next)), None) =
+        (input.next(), input.next(), input.next(), input.next())
+    else {
+        return error("rukt: invalid invocation of `gensym`, expected a base name, a counter and a continuation");
+    };
+    let count = match &counter.stream().into_iter().collect::<Vec<_>>()[..] {
+        [] => Some(0),
+        [token] => integer_value(token),
+        _ => None,
+    };
+    let Some(count) = count else {
+        return error(&format!("rukt: invalid counter `{counter}` for `gensym`, expected an integer literal"));
+    };
+
+    // the identifier keeps the span of the base name so that it resolves like the base name would
+    let name = base.to_string();
+    let ident = Ident::new(&format!("{}_{count}", name.strip_prefix("r#").unwrap_or(&name)), base.span());
+    let next_count = TokenTree::Literal(Literal::u64_unsuffixed(count + 1));
+    let arguments = [TokenTree::Ident(ident), TokenTree::Group(Group::new(Delimiter::Bracket, next_count.into()))];
+    invoke(next.stream(), arguments, [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn utils_hashed_ident(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Ident(base)), Some(TokenTree::Group(tokens)), Some(TokenTree::Group(next)), None) =
+        (input.next(), input.next(), input.next(), input.next())
+    else {
+        return error("rukt: invalid invocation of `hashed_ident`, expected a base name, a list of tokens and a continuation");
+    };
+
+    // identical tokens produce identical definitions, so sharing the name between them is harmless
+    let mut hasher = DefaultHasher::new();
+    tokens.stream().to_string().hash(&mut hasher);
+    let ident = Ident::new(&format!("{base}_{:016x}", hasher.finish()), base.span());
+    invoke(next.stream(), [TokenTree::Ident(ident)], [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn utils_concat_idents(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(parts)), Some(TokenTree::Group(next)), None) = (input.next(), input.next(), input.next()) else {
+        return error("rukt: invalid invocation of `concat_idents`, expected a list of parts and a continuation");
+    };
+
+    let mut name = String::new();
+    let mut span = None;
+    for part in parts.stream() {
+        let Some((piece, part_span)) = ident_part(&part) else {
+            return error(&format!("rukt: can't concatenate `{part}`, expected identifiers or integers"));
+        };
+        name.push_str(&piece);
+        span.get_or_insert(part_span);
+    }
+    let (Some(span), false) = (span, name.starts_with(|c: char| c.is_ascii_digit())) else {
+        return error(&format!("rukt: can't concatenate `{}` into an identifier", parts.stream()));
+    };
+
+    // the identifier keeps the span of the first part so that it resolves like the first part would
+    invoke(next.stream(), [TokenTree::Ident(Ident::new(&name, span))], [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn utils_respan(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(reference)), Some(TokenTree::Group(idents)), Some(TokenTree::Group(next)), None) =
+        (input.next(), input.next(), input.next(), input.next())
+    else {
+        return error("rukt: invalid invocation of `respan`, expected a reference token, a list of identifiers and a continuation");
+    };
+    let mut reference = reference.stream().into_iter();
+    let (Some(reference), None) = (reference.next(), reference.next()) else {
+        return error("rukt: invalid reference for `respan`, expected a single token");
+    };
+
+    let mut respanned = Vec::new();
+    for token in idents.stream() {
+        let TokenTree::Ident(ident) = token else {
+            return error(&format!("rukt: can't respan `{token}`, expected identifiers"));
+        };
+        // the identifiers resolve like the reference token would
+        respanned.push(TokenTree::Ident(Ident::new(&ident.to_string(), reference.span())));
+    }
+    invoke(next.stream(), [TokenTree::Group(Group::new(Delimiter::Bracket, respanned.into_iter().collect()))], [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn eval_expand_escape(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(tokens)), Some(TokenTree::Group(output)), Some(TokenTree::Group(escape)), Some(TokenTree::Group(next)), None) =
+        (input.next(), input.next(), input.next(), input.next(), input.next())
+    else {
+        return error("rukt: invalid invocation of `expand_escape`, expected tokens, initial output, escape tokens and a continuation");
+    };
+
+    let mut escaped = output.stream();
+    escaped.extend(escape_dollars(tokens.stream(), &escape.stream()));
+    invoke(next.stream(), [TokenTree::Group(Group::new(Delimiter::Bracket, escaped))], [])
+}
+
+/// Replace each pair of dollar signs `$$` with the escape tokens, at all group levels.
+fn escape_dollars(tokens: TokenStream, escape: &TokenStream) -> TokenStream {
+    let mut output = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Group(group) => {
+                let mut escaped = Group::new(group.delimiter(), escape_dollars(group.stream(), escape));
+                escaped.set_span(group.span());
+                output.push(TokenTree::Group(escaped));
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '$' && matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() == '$') => {
+                tokens.next();
+                output.extend(escape.clone());
+            }
+            token => output.push(token),
+        }
+    }
+    output.into_iter().collect()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn eval_error_message(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(message), Some(TokenTree::Group(variables)), None) = (input.next(), input.next(), input.next()) else {
+        return error("rukt: invalid invocation of `error_message`, expected a message and a list of variables");
+    };
+    let Some(text) = string_value(&message) else {
+        return error(&format!("rukt: invalid error message `{message}`, expected a string literal"));
+    };
+
+    let mut names = Vec::new();
+    let mut variables = variables.stream().into_iter();
+    while let (Some(TokenTree::Ident(name)), Some(TokenTree::Group(value))) = (variables.next(), variables.next()) {
+        names.push((name.to_string(), value.stream()));
+    }
+
+    // the message is split around the variables, which are stringified like everywhere else
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                literal.push(c);
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return error("rukt: unmatched `{` in error message, use `{{` for a literal brace"),
+                    }
+                }
+                let Some((_, value)) = names.iter().find(|(candidate, _)| *candidate == name) else {
+                    return error(&format!("rukt: undefined variable `{name}` in error message"));
+                };
+                if !literal.is_empty() {
+                    parts.push(TokenTree::Literal(Literal::string(&std::mem::take(&mut literal))));
+                    parts.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+                }
+                let mut stringify: TokenStream = "::core::stringify!".parse().unwrap();
+                stringify.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, value.clone()))]);
+                parts.extend(stringify);
+                parts.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+            }
+            '}' => return error("rukt: unmatched `}` in error message, use `}}` for a literal brace"),
+            c => literal.push(c),
+        }
+    }
+    parts.push(TokenTree::Literal(Literal::string(&literal)));
+
+    let mut concat: TokenStream = "::core::concat!".parse().unwrap();
+    concat.extend([TokenTree::Group(Group::new(Delimiter::Parenthesis, parts.into_iter().collect()))]);
+    let mut arguments = Group::new(Delimiter::Parenthesis, concat);
+    arguments.set_span(message.span());
+    let mut output: TokenStream = "::core::compile_error!".parse().unwrap();
+    output.extend([TokenTree::Group(arguments), TokenTree::Punct(Punct::new(';', Spacing::Alone))]);
+    output
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn eval_strip_suffixes(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(operands)), Some(TokenTree::Group(next)), None) = (input.next(), input.next(), input.next()) else {
+        return error("rukt: invalid invocation of `strip_suffixes`, expected two operands and a continuation");
+    };
+    let operands: Vec<_> = operands.stream().into_iter().collect();
+    let [left, right] = &operands[..] else {
+        return error("rukt: invalid operands for `strip_suffixes`, expected two tokens");
+    };
+
+    // empty brackets tell the continuation that there's nothing to strip
+    let empty = || TokenTree::Group(Group::new(Delimiter::Bracket, TokenStream::new()));
+    let nothing = || invoke(next.stream(), [empty(), empty()], []);
+    let (Some((left_digits, left_suffix)), Some((right_digits, right_suffix))) = (integer_parts(left), integer_parts(right)) else {
+        return nothing();
+    };
+    let suffix = match (left_suffix, right_suffix) {
+        ("", "") => return nothing(),
+        (suffix, "") | ("", suffix) => suffix,
+        (a, b) if a == b => a,
+        _ => return error(&format!("rukt: mismatched integer suffixes in `{left}` and `{right}`")),
+    };
+
+    let digits = [left_digits, right_digits].map(|digits| TokenTree::Literal(digits.parse().unwrap()));
+    let suffix = TokenTree::Ident(Ident::new(suffix, Span::call_site()));
+    let arguments = [
+        TokenTree::Group(Group::new(Delimiter::Bracket, digits.into_iter().collect())),
+        TokenTree::Group(Group::new(Delimiter::Bracket, suffix.into())),
+    ];
+    invoke(next.stream(), arguments, [])
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn eval_attach_suffix(input: TokenStream) -> TokenStream {
+    let mut input = input.into_iter();
+    let (Some(TokenTree::Group(integers)), Some(TokenTree::Group(suffix)), Some(TokenTree::Group(next)), None) =
+        (input.next(), input.next(), input.next(), input.next())
+    else {
+        return error("rukt: invalid invocation of `attach_suffix`, expected a list of integers, a suffix and a continuation");
+    };
+    let mut suffix = suffix.stream().into_iter();
+    let (Some(TokenTree::Ident(suffix)), None) = (suffix.next(), suffix.next()) else {
+        return error("rukt: invalid suffix for `attach_suffix`, expected an identifier");
+    };
+
+    let mut suffixed = Vec::new();
+    for integer in integers.stream() {
+        let Some((digits, "")) = integer_parts(&integer) else {
+            return error(&format!("rukt: can't attach a suffix to `{integer}`, expected an unsuffixed integer"));
+        };
+        let mut literal: Literal = format!("{digits}{suffix}").parse().unwrap();
+        literal.set_span(integer.span());
+        suffixed.push(TokenTree::Literal(literal));
+    }
+    invoke(next.stream(), [TokenTree::Group(Group::new(Delimiter::Bracket, suffixed.into_iter().collect()))], [])
+}
+
+/// Evaluator state destructured according to the calling convention.
+struct State {
+    tokens: Peekable<proc_macro::token_stream::IntoIter>,
+    subject: TokenTree,
+    next: TokenStream,
+    patterns: TokenTree,
+    values: TokenTree,
+    dollar: TokenTree,
+}
+
+impl State {
+    fn parse(input: TokenStream) -> Option<State> {
+        let mut input = input.into_iter();
+        let (Some(TokenTree::Group(tokens)), Some(subject), Some(TokenTree::Group(next)), Some(patterns), Some(values), Some(dollar)) = (
+            input.next(),
+            input.next(),
+            input.next(),
+            input.next(),
+            input.next(),
+            input.next(),
+        ) else {
+            return None;
+        };
+        Some(State {
+            tokens: tokens.stream().into_iter().peekable(),
+            subject,
+            next: next.stream(),
+            patterns,
+            values,
+            dollar,
+        })
+    }
+
+    /// Consume the parenthesized arguments at the start of the remaining tokens.
+    fn arguments(&mut self) -> Option<Vec<TokenTree>> {
+        match self.tokens.peek() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                let arguments = group.stream().into_iter().collect();
+                self.tokens.next();
+                Some(arguments)
+            }
+            _ => None,
+        }
+    }
+
+    /// Pass the value to the next continuation `($F:path; $($C:tt)*)`.
+    fn resume(self, value: TokenTree) -> TokenStream {
+        let tokens = TokenTree::Group(Group::new(Delimiter::Brace, self.tokens.collect()));
+        invoke(self.next, [tokens, value], [self.patterns, self.values, self.dollar])
+    }
+}
+
+/// Invoke the continuation `($F:path; $($C:tt)*)` with the arguments prepended
+/// to the context tokens, and the trailing arguments appended after them.
+fn invoke<const A: usize, const B: usize>(next: TokenStream, arguments: [TokenTree; A], trailing: [TokenTree; B]) -> TokenStream {
+    let mut path = Vec::new();
+    let mut continuation = next.into_iter();
+    for token in continuation.by_ref() {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == ';' => break,
+            _ => path.push(token),
+        }
+    }
+
+    let mut arguments = Vec::from(arguments);
+    arguments.extend(continuation);
+    arguments.extend(trailing);
+
+    let mut output: TokenStream = path.into_iter().collect();
+    output.extend([
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(Group::new(Delimiter::Parenthesis, arguments.into_iter().collect())),
+        TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+    ]);
+    output
+}
+
+/// Compute the result of a method call on a string literal.
+fn string_method(input: TokenStream, name: &str, f: impl Fn(&str) -> Result<TokenTree, String>) -> TokenStream {
+    let Some(mut state) = State::parse(input) else {
+        return error(&format!("rukt: invalid invocation of `{name}`"));
+    };
+    let value = match state.tokens.peek() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis && group.stream().is_empty() => {
+            state.tokens.next();
+            let subject = &state.subject;
+            let Some(value) = string_value(subject) else {
+                return error(&format!("rukt: can't {name} `{subject}`, expected a string literal"));
+            };
+            match f(&value) {
+                Ok(value) => value,
+                Err(err) => return error(&format!("rukt: can't {name} `{subject}`, {err}")),
+            }
+        }
+        _ => function_value(name, &state.dollar),
+    };
+    state.resume(value)
+}
+
+/// Wrap the builtin in a function so that it can be passed around as a value.
+fn function_value(name: &str, dollar: &TokenTree) -> TokenTree {
+    let source = format!("{{ fn {name}(D value:tt) {{ value.rukt::builtins::{name}() }} }}");
+    let tokens = source.parse::<TokenStream>().unwrap().into_iter().map(|token| replace_dollar(token, dollar));
+    tokens.collect::<TokenStream>().into_iter().next().unwrap()
+}
+
+fn replace_dollar(token: TokenTree, dollar: &TokenTree) -> TokenTree {
+    match token {
+        TokenTree::Group(group) => {
+            let stream = group.stream().into_iter().map(|token| replace_dollar(token, dollar));
+            TokenTree::Group(Group::new(group.delimiter(), stream.collect()))
+        }
+        TokenTree::Ident(ident) if ident.to_string() == "D" => dollar.clone(),
+        token => token,
+    }
+}
+
+/// Punctuation that the lexer turns into a single token, longest first.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "%=", "^=", "&=",
+    "|=", "<<", ">>", "..",
+];
+
+/// Part of a compact string, either text or a fragment stringified by the
+/// compiler.
+enum Segment {
+    Text(String),
+    Fragment(Group),
+}
+
+/// Stringify tokens with the same rules as `utils::stringify_compact`.
+struct Compact {
+    segments: Vec<Segment>,
+    separate: bool,
+    fragments: bool,
+}
+
+impl Compact {
+    /// Fragments parsed with specifiers other than `tt` are either kept whole,
+    /// or stringified token by token.
+    fn new(tokens: TokenStream, fragments: bool) -> Compact {
+        let mut compact = Compact { segments: Vec::new(), separate: false, fragments };
+        compact.tokens(tokens);
+        compact
+    }
+
+    fn tokens(&mut self, tokens: TokenStream) {
+        let mut tokens = tokens.into_iter().peekable();
+        let mut punctuation = String::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::None && self.fragments => {
+                    self.separator();
+                    self.segments.push(Segment::Fragment(group));
+                }
+                TokenTree::Group(group) if group.delimiter() == Delimiter::None => self.tokens(group.stream()),
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        _ => ("{", "}"),
+                    };
+                    self.piece(open.to_string());
+                    self.separate = false;
+                    self.tokens(group.stream());
+                    self.segments.push(Segment::Text(close.to_string()));
+                    self.separate = true;
+                }
+                TokenTree::Punct(punct) if punct.as_char() == '\'' && matches!(tokens.peek(), Some(TokenTree::Ident(_))) => {
+                    self.piece(format!("'{}", tokens.next().unwrap()));
+                }
+                TokenTree::Punct(punct) => {
+                    punctuation.push(punct.as_char());
+                    let joint = punct.spacing() == Spacing::Joint && matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() != '\'');
+                    if !joint {
+                        for operator in split_operators(&punctuation) {
+                            self.piece(operator.to_string());
+                        }
+                        punctuation.clear();
+                    }
+                }
+                token => self.piece(token.to_string()),
+            }
+        }
+    }
+
+    fn piece(&mut self, text: String) {
+        self.separator();
+        self.segments.push(Segment::Text(text));
+    }
+
+    fn separator(&mut self) {
+        if self.separate {
+            self.segments.push(Segment::Text(" ".to_string()));
+        }
+        self.separate = true;
+    }
+}
+
+/// Split joint punctuation into the tokens that the lexer would produce.
+fn split_operators(mut punctuation: &str) -> Vec<&str> {
+    let mut operators = Vec::new();
+    while !punctuation.is_empty() {
+        let length = OPERATORS.iter().find(|operator| punctuation.starts_with(*operator)).map_or(1, |operator| operator.len());
+        operators.push(&punctuation[..length]);
+        punctuation = &punctuation[length..];
+    }
+    operators
+}
+
+/// Extract the value of a string literal, looking through opaque fragments.
+fn string_value(token: &TokenTree) -> Option<String> {
+    match token {
+        TokenTree::Literal(literal) => unescape(&literal.to_string()),
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+            let mut tokens = group.stream().into_iter();
+            match (tokens.next(), tokens.next()) {
+                (Some(token), None) => string_value(&token),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Extract the text of an identifier or an integer literal, looking through
+/// opaque fragments.
+fn ident_part(token: &TokenTree) -> Option<(String, Span)> {
+    match token {
+        TokenTree::Ident(ident) => {
+            let name = ident.to_string();
+            Some((name.strip_prefix("r#").unwrap_or(&name).to_string(), ident.span()))
+        }
+        TokenTree::Literal(literal) => {
+            let value = literal.to_string();
+            value.bytes().all(|c| c.is_ascii_digit()).then(|| (value, literal.span()))
+        }
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+            let mut tokens = group.stream().into_iter();
+            match (tokens.next(), tokens.next()) {
+                (Some(token), None) => ident_part(&token),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Extract the value of an integer literal, looking through opaque fragments.
+fn integer_value(token: &TokenTree) -> Option<u64> {
+    match token {
+        TokenTree::Literal(literal) => literal.to_string().parse().ok(),
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+            let mut tokens = group.stream().into_iter();
+            match (tokens.next(), tokens.next()) {
+                (Some(token), None) => integer_value(&token),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Split an integer literal into its digits and its suffix, looking through
+/// opaque fragments.
+fn integer_parts(token: &TokenTree) -> Option<(String, &'static str)> {
+    const SUFFIXES: [&str; 12] = ["u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize"];
+    match token {
+        TokenTree::Literal(literal) => {
+            let text = literal.to_string();
+            let suffix = SUFFIXES.into_iter().find(|suffix| text.ends_with(suffix)).unwrap_or("");
+            let digits = &text[..text.len() - suffix.len()];
+            let valid = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+            valid.then(|| (digits.to_string(), suffix))
+        }
+        TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+            let mut tokens = group.stream().into_iter();
+            match (tokens.next(), tokens.next()) {
+                (Some(token), None) => integer_parts(&token),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn unescape(source: &str) -> Option<String> {
+    if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = &raw[hashes..raw.len() - hashes];
+        return Some(raw.strip_prefix('"')?.strip_suffix('"')?.to_string());
+    }
+
+    let mut chars = source.strip_prefix('"')?.strip_suffix('"')?.chars().peekable();
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'x' => {
+                let digits: String = [chars.next()?, chars.next()?].iter().collect();
+                value.push(u8::from_str_radix(&digits, 16).ok()? as char);
+            }
+            'u' => {
+                chars.next().filter(|&c| c == '{')?;
+                let digits: String = chars.by_ref().take_while(|&c| c != '}').filter(|&c| c != '_').collect();
+                value.push(char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?);
+            }
+            '\n' => {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            }
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+fn error(message: &str) -> TokenStream {
+    let mut output: TokenStream = "compile_error!".parse().unwrap();
+    let literal = TokenTree::Literal(Literal::string(message));
+    let mut group = Group::new(Delimiter::Parenthesis, literal.into());
+    group.set_span(Span::call_site());
+    output.extend([TokenTree::Group(group), TokenTree::Punct(Punct::new(';', Spacing::Alone))]);
+    output
+}
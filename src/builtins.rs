@@ -9,39 +9,178 @@
 //! This module provides some common utilities but you can create your own
 //! builtins anywhere.
 //!
+//! The [`builtin`](crate::builtin) macro defines a builtin from rules that
+//! match the subject and the arguments of the call, and produce the resulting
+//! value.
+//!
 //! ```
-//! macro_rules! example {
-//!     ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-//!         $F!($T "hello world" $($C)* $P $V $);
-//!     };
+//! # use rukt::{builtin, rukt};
+//! builtin! {
+//!     greet {
+//!         ((), ()) => { "hello world" };
+//!         ((), ($name:literal)) => { (concat!("hello ", $name)) };
+//!     }
+//! }
+//!
+//! rukt! {
+//!     let name = "rukt";
+//!     let a = greet();
+//!     let b = greet($name);
+//!     expand {
+//!         assert_eq!([$a, $b], ["hello world", "hello rukt"]);
+//!     }
 //! }
 //! ```
 //!
-//! This is an example of a simple builtin that resolves to the token `"hello
-//! world"`. The macro follows the [calling
+//! The generated macro follows the [calling
 //! convention](crate::eval#calling-convention) of the Rukt evaluator. As long
 //! as the macro is accessible from the surrounding Rust code, the evaluator
 //! will know how to invoke it during the expansion of [`rukt`](crate::rukt)
-//! blocks.
+//! blocks. Builtins that need more control over the evaluation can implement
+//! the calling convention by hand.
+//!
+//! Builtins that operate on a value can also be used as function values. When
+//! referenced without being called, [`identity`], [`not`], [`starts_with`],
+//! [`deep_map`], [`reduce`], [`fold_while`], [`apply`], [`dispatch`],
+//! [`unique_by`], [`count_tokens_deep`], [`zip_longest`], [`struct_fields`],
+//! [`chunk_by`], [`map_indexed`], [`intersperse`], [`cartesian`], [`swap`],
+//! [`assert_len`], [`assert_len_at_least`] and [`assert_len_at_most`], as well
+//! as the builtins defined with [`builtin`](crate::builtin), resolve to a
+//! function that takes the value as first argument, followed by the arguments
+//! of the builtin.
 //!
 //! ```
-//! # macro_rules! example {
-//! #     ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-//! #         $F!($T "hello world" $($C)* $P $V $);
-//! #     };
-//! # }
 //! # use rukt::rukt;
+//! use rukt::builtins::{deep_map, not};
 //! rukt! {
-//!     let message = example;
+//!     let f = not;
+//!     let value = [true (false)].deep_map($f);
 //!     expand {
-//!         assert_eq!($message, "hello world");
+//!         assert_eq!(stringify!($value), "[false (true)]");
 //!     }
 //! }
 //! ```
+//!
+//! Counting and indexing tokens with declarative macros takes a lot of
+//! recursive calls. On a nightly compiler, the `nightly-metavar-expr` feature
+//! lets [`utils::count`](crate::utils::count) and [`map_indexed`] use the
+//! unstable [metavariable
+//! expressions](https://github.com/rust-lang/rust/issues/83527) `${count()}`
+//! and `${index()}` instead, which also benefits the builtins built on top of
+//! them like [`assert_len`] and [`expand_count`]. The results and the errors
+//! are the same as with the portable implementation, including the limit of 255
+//! tokens when counting.
+//!
+//! ```toml
+//! [dependencies]
+//! rukt = { version = "*", features = ["nightly-metavar-expr"] }
+//! ```
+//!
+//! Metavariable expressions are checked where the macros expand, so enabling
+//! the feature isn't enough. Every crate that invokes Rukt macros while the
+//! feature is enabled also needs the attribute at its root, including crates
+//! that only use `$$` in `expand` statements, which then get passed to the
+//! compiler as-is.
+//!
+//! ```ignore
+//! #![feature(macro_metavar_expr)]
+//! ```
+//!
+//! The feature doesn't build on stable compilers, and since the syntax isn't
+//! stable yet, a newer nightly can break it at any point. Leave it off for
+//! anything that needs to build with a stable toolchain. For the same reason,
+//! the documentation on docs.rs is built without it.
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_macro_path {
+    ($R:tt $A:tt $I:ident [] $D:tt) => {
+        $crate::builtin_macro_define!($R $A $I $I [$I] $);
+    };
+    ($R:tt $A:tt $I:ident [$K:ident $(:: $X:ident)*] $D:tt) => {
+        $crate::builtin_macro_path!(@crate $K [$K $($X)*] $R $A $I [$K $(:: $X)*] $);
+    };
+    // `crate::` paths would resolve to the crate of the caller, `$crate::` keeps pointing to the defining crate
+    (@crate crate $L:tt $R:tt $A:tt $I:ident [$K:ident $($X:tt)*] $D:tt) => {
+        $crate::builtin_macro_path!(@last $L $R $A $I [$D $K $($X)*] $);
+    };
+    (@crate $_:ident $L:tt $R:tt $A:tt $I:ident $X:tt $D:tt) => {
+        $crate::builtin_macro_path!(@last $L $R $A $I $X $);
+    };
+    (@last [$L:ident] $R:tt $A:tt $I:ident $X:tt $D:tt) => {
+        $crate::builtin_macro_define!($R $A $I $L $X $);
+    };
+    (@last [$H:ident $($L:ident)+] $R:tt $A:tt $I:ident $X:tt $D:tt) => {
+        $crate::builtin_macro_path!(@last [$($L)+] $R $A $I $X $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_macro_define {
+    ([$([$L:tt $B:tt])*] [$(#[$A:meta])*] $I:ident $J:ident [$($X:tt)*] $D:tt) => {
+        $(#[$A])*
+        macro_rules! $I {
+            $(
+                // the names of the metavariables can't collide with the captures of the rules
+                (@rules $L $D __rukt_tokens:tt $D __rukt_next:tt $D __rukt_patterns:tt $D __rukt_values:tt) => {
+                    $crate::builtin_macro_result!($B $D __rukt_tokens $D __rukt_next $D __rukt_patterns $D __rukt_values $I);
+                };
+            )*
+            (@rules $D __rukt_other:tt $D($D __rukt_context:tt)*) => {
+                compile_error!(concat!(
+                    "rukt: invalid arguments for `",
+                    stringify!($I),
+                    "`, no rule matches `",
+                    stringify!($D __rukt_other),
+                    "`"
+                ));
+            };
+            ({ ($D($D R:tt)*) $D($D T:tt)* } $D S:tt $D N:tt $D P:tt $D V:tt $D D:tt) => {
+                $crate::eval_substitute!([$D($D R)*] ($crate::builtin_macro_substituted; [$($X)*] $D S { $D($D T)* } $D N $D P $D V) $D P $D V $D D);
+            };
+            ($D T:tt $D S:tt ($D F:path; $D($D C:tt)*) $D P:tt $D V:tt $D D:tt) => {
+                $D F!($D T { fn $J($D D value:tt $D D($D D args:tt)*) { value.$($X)*($D D($D D args)*) } } $D($D C)* $D P $D V $D D);
+            };
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_macro_substituted {
+    ([$($R:tt)*] [$($X:tt)*] $S:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $($X)*!(@rules ($S, ($($R)*)) $T $N $P $V);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_macro_result {
+    ({ compile_error!($($M:tt)*) } $T:tt $N:tt $P:tt $V:tt $I:ident) => {
+        compile_error!($($M)*);
+    };
+    ({ $R:tt } $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $I:ident) => {
+        $F!($T $R $($C)* $P $V $);
+    };
+    ({ $($R:tt)* } $T:tt $N:tt $P:tt $V:tt $I:ident) => {
+        compile_error!(concat!(
+            "rukt: the rules of `",
+            stringify!($I),
+            "` must produce a single token tree, got `",
+            stringify!($($R)*),
+            "`"
+        ));
+    };
+}
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! builtin_breakpoint {
+    // the step counter of checked evaluation isn't a variable
+    ($T:tt $S:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*] $D:tt) => {
+        $crate::builtin_breakpoint!($T $S $N [$($P)*] [$($V)*] $);
+    };
     ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         compile_error!(concat!(
             "rukt: breakpoint\n",
@@ -89,6 +228,235 @@ macro_rules! builtin_breakpoint {
 #[doc(inline)]
 pub use builtin_breakpoint as breakpoint;
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_block {
+    // the step counter of checked evaluation stays in front of the nested environment
+    ({ { $($B:tt)* } $($T:tt)* } $S:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*] $D:tt) => {
+        $crate::eval::block!({ $($B)* } () ($crate::builtin_block_captured; { $($T)* } $N) [$DD __rukt_steps:tt [$($P)*]] [$L [$($V)*]] $);
+    };
+    ({ { $($B:tt)* } $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($B)* } () ($crate::builtin_block_captured; { $($T)* } $N) [$P] [$V] $);
+    };
+    ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: expected a block after `block`, got `", stringify!($T), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_block_captured {
+    ({} $S:tt $T:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*] $D:tt) => {
+        $crate::builtin_block_env!([$($P)*] [$($V)*] [$T $N [$DD __rukt_steps:tt $($P)*] [$L $($V)*]] $);
+    };
+    ({} $S:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_block_env!($P $V [$T $N $P $V] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_block_env {
+    // the first entry is the environment of the parent block
+    ([$_:tt $($P:tt)*] [$__:tt $($V:tt)*] $C:tt $D:tt) => {
+        $crate::builtin_block_walk!([$($P)*] [$($V)*] [] $C $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_block_walk {
+    ([$H:tt $($P:tt)*] [$X:tt $($V:tt)*] $A:tt $C:tt $D:tt) => {
+        $crate::builtin_block_detect!([=$H=] [$($P)*] $X [$($V)*] $A $C $);
+    };
+    ([] [] $A:tt [$T:tt $N:tt $P:tt $V:tt] $D:tt) => {
+        $crate::eval::parent!({} $A $T $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_block_detect {
+    ([$(=)$+] [$I:ident : tt $($P:tt)*] $X:tt $V:tt [$($A:tt)*] $C:tt $D:tt) => {
+        $crate::builtin_block_walk!([$($P)*] $V [$($A)* ($I $X)] $C $);
+    };
+    // destructuring patterns bind their variables through the hidden entries that follow them
+    ([=$H:tt=] $P:tt $X:tt $V:tt $A:tt $C:tt $D:tt) => {
+        $crate::builtin_block_walk!($P $V $A $C $);
+    };
+}
+
+/// Evaluate a nested block and collect the variables it defines.
+///
+/// The result is a list of parenthesized pairs, one for each variable defined
+/// in the block, in the order of definition. Each pair contains the name of
+/// the variable followed by its value.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::block;
+/// rukt! {
+///     let defs = block {
+///         let a = 1;
+///         let b = [x y];
+///     };
+///     let [$(($name:ident $value:tt))*] = defs;
+///     expand {
+///         assert_eq!(stringify!($defs).replace(" ", ""), "[(a1)(b[xy])]");
+///         assert_eq!([$(stringify!($name)),*], ["a", "b"]);
+///     }
+/// }
+/// ```
+///
+/// The block can reference the variables of the surrounding scope, but the
+/// variables it defines don't leak out of it, and only show up in the
+/// resulting list. The metavariables of destructuring patterns aren't
+/// included, only the names bound with `let (a, b) = ...;` and the rest of
+/// lists with `..$rest`. Functions defined in the block are included as
+/// function values.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::block;
+/// rukt! {
+///     let prefix = "hello";
+///     let defs = block {
+///         let (a, b) = ($prefix world);
+///         let [$first:tt ..$rest] = [1 2 3];
+///     };
+///     expand {
+///         assert_eq!(stringify!($defs).replace(" ", ""), "[(a\"hello\")(bworld)(rest[23])]");
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_block as block;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_expand_count {
+    ({ { $($B:tt)* } $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expand_escape!([$($B)*] [] [$DD] ($crate::builtin_expand_count_escaped; [$DD:tt] { $($T)* } $N $P $V $));
+    };
+    ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: expected a block after `expand_count`, got `", stringify!($T), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_expand_count_escaped {
+    // the code is transcribed into an argument, so the items can be counted after substitution
+    ([$($B:tt)*] [$($M:tt)+] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $($M)* $D($D CC:tt)*) => {
+                $crate::builtin_expand_count_transcribed!([$($B)*] $D($D CC)*);
+            };
+        }
+        __rukt_transcribe!($V $D $T $N $P $V);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_expand_count_transcribed {
+    ([$($X:tt)*] $T:tt $N:tt $P:tt $V:tt) => {
+        $($X)*
+        $crate::builtin_expand_count_walk!([$($X)*] [] [] ($T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_expand_count_walk {
+    ([; $($X:tt)*] [] $U:tt $C:tt) => {
+        $crate::builtin_expand_count_walk!([$($X)*] [] $U $C);
+    };
+    ([; $($X:tt)*] $W:tt [$($U:tt)*] $C:tt) => {
+        $crate::builtin_expand_count_walk!([$($X)*] [] [$($U)* _] $C);
+    };
+    // a braced group ends the item, unless it's followed by a semicolon like in `let x = S { a: 1 };`
+    ([{ $($G:tt)* } ; $($X:tt)*] $W:tt [$($U:tt)*] $C:tt) => {
+        $crate::builtin_expand_count_walk!([$($X)*] [] [$($U)* _] $C);
+    };
+    ([{ $($G:tt)* } $($X:tt)*] $W:tt [$($U:tt)*] $C:tt) => {
+        $crate::builtin_expand_count_walk!([$($X)*] [] [$($U)* _] $C);
+    };
+    ([$H:tt $($X:tt)*] $W:tt $U:tt $C:tt) => {
+        $crate::builtin_expand_count_walk!([$($X)*] [_] $U $C);
+    };
+    // trailing tokens without a terminator count as a last item
+    ([] [] $U:tt ($T:tt $N:tt $P:tt $V:tt)) => {
+        $crate::utils::count!($U ($crate::builtin_expand_count_done; $T $N $P $V));
+    };
+    ([] $W:tt [$($U:tt)*] ($T:tt $N:tt $P:tt $V:tt)) => {
+        $crate::utils::count!([$($U)* _] ($crate::builtin_expand_count_done; $T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_expand_count_done {
+    ($L:tt $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt) => {
+        $F!($T $L $($C)* $P $V $);
+    };
+}
+
+/// Expand code and count the top-level items it produced.
+///
+/// This works like an [`expand`](crate::eval::block#expand-statements)
+/// statement, but as an expression that results in the number of top-level
+/// items in the expanded code. The variables are substituted before counting,
+/// so repetitions count once per generated item, and `$$` escapes a literal
+/// `$` sign like in `expand` statements. This is mostly useful for testing
+/// code generators.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::expand_count;
+/// rukt! {
+///     let names = [first second third];
+///     let [$($name:ident)*] = names;
+///     let count = expand_count {
+///         $(
+///             #[allow(dead_code)]
+///             fn $name() {}
+///         )*
+///         const TOTAL: usize = 3;
+///     };
+///     expand {
+///         assert_eq!($count, 4);
+///     }
+/// }
+/// ```
+///
+/// The expanded code is split into items by looking at the top-level tokens.
+/// An item ends with a semicolon `;`, or with a braced group `{}` that isn't
+/// followed by a semicolon, so `struct A;`, `struct B { x: u32 }` and
+/// `const C: S = S { x: 1 };` each count as a single item. Tokens after the
+/// last item count as one more item. Everything else, like attributes,
+/// visibility and bracketed or parenthesized groups, is part of the current
+/// item. The heuristic doesn't parse Rust, so a statement like `if a {} else
+/// {}` counts as two items.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::expand_count;
+/// rukt! {
+///     let count = expand_count {
+///         #[derive(Debug)]
+///         struct A;
+///         struct B { x: u32 }
+///         const C: B = B { x: 1 };
+///     };
+///     expand {
+///         assert_eq!($count, 3);
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_expand_count as expand_count;
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! builtin_parse {
@@ -168,29 +536,128 @@ pub use builtin_parse as parse;
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! builtin_starts_with {
-    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+macro_rules! builtin_parse_many {
+    ({ ::<$F:tt>($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_parse_many!({ ::<$F, ,>($($R)*) $($T)* } $S $N $P $V $);
+    };
+    ({ ::<$F:tt, $X:tt>($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_parse {
+            ($TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $D($D SS:$F)$X* $D($X)?) => {
+                $FF!($TT [$D($D SS)*] $D($CC)* $PP $VV $);
+            };
+        }
         macro_rules! __rukt_transcribe {
-            ($P $TT:tt $SS:tt $NN:tt $PP:tt $VV:tt) => {
-                $crate::utils::escape!([[$SS] [$($R)*]] [] [__rukt_dollar] ($crate::builtin_starts_with_escaped; $TT $NN $PP $VV $));
+            ($P $TT:tt $NN:tt $PP:tt $VV:tt) => {
+                __rukt_parse!($TT $NN $PP $VV $($R)*);
             };
         }
-        __rukt_transcribe!($V { $($T)* } $S $N $P $V);
-    }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+}
+
+/// Parse a separated sequence of tokens into a list of syntax fragments.
+///
+/// The fragments are separated by commas by default, and a trailing separator
+/// is allowed. The resulting list contains one token per fragment.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::parse_many;
+/// rukt! {
+///     let types = parse_many::<ty>(u8, u16, u32);
+///     let [$a:tt $b:tt $c:tt] = types;
+///     expand {
+///         const SIZES: [usize; 3] = [size_of::<$a>(), size_of::<$b>(), size_of::<$c>()];
+///     }
+/// }
+/// assert_eq!(SIZES, [1, 2, 4]);
+/// ```
+///
+/// A different separator can be specified after the fragment specifier. Keep in
+/// mind that `macro_rules` only allows some tokens to follow each kind of
+/// fragment. For example, an `expr` can only be followed by `,`, `;` or `=>`.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::parse_many;
+/// rukt! {
+///     let values = parse_many::<expr, ;>(1 + 2; 3 * 4;);
+///     let [$a:tt $b:tt] = values;
+///     expand {
+///         assert_eq!([$a, $b], [3, 12]);
+///     }
+/// }
+/// ```
+///
+/// Like with [`parse`], each fragment is opaque. Other builtins can still move
+/// the fragments around, for example with [`deep_map`], but you won't be able
+/// to inspect their tokens or compare them with `==`.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::parse_many;
+/// rukt! {
+///     let [$first:tt $($rest:tt)*] = parse_many::<ty>(u8, u16);
+///     let same = [$first] == [u8]; // error: rukt: can't compare `[u8]`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_parse_many as parse_many;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_starts_with {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_starts_with_substituted; { $($T)* } $S $N $P $V) $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn starts_with($D value:tt $D($D args:tt)*) { value.$crate::builtins::starts_with($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_starts_with_substituted {
+    ([$($R:tt)*] $T:tt $S:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::escape!([[$S] [$($R)*]] [] [__rukt_dollar] ($crate::builtin_starts_with_escaped; $T $N $P $V $));
+    };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! builtin_starts_with_escaped {
     ([[$S1:tt] [$($S2:tt)*]] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::utils::select!([$S1] [
-            [[($($S2)* $D($_:tt)*)] [true]]
-            [[[$($S2)* $D($_:tt)*]] [true]]
-            [[{$($S2)* $D($_:tt)*}] [true]]
-            [[($D($_:tt)*)] [false]]
-            [[[$D($_:tt)*]] [false]]
-            [[{$D($_:tt)*}] [false]]
-        ] ($crate::eval_unwrap; $T $N $P $V) $);
+        // the prefix check shares the probe so that no other macro needs to be defined
+        macro_rules! __rukt_probe {
+            ([($($S2)* $D($D _:tt)*)] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([true] $D($D CC)*);
+            };
+            ([[$($S2)* $D($D _:tt)*]] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([true] $D($D CC)*);
+            };
+            ([{$($S2)* $D($D _:tt)*}] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([true] $D($D CC)*);
+            };
+            ([($D($D _:tt)*)] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([false] $D($D CC)*);
+            };
+            ([[$D($D _:tt)*]] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([false] $D($D CC)*);
+            };
+            ([{$D($D _:tt)*}] [$($S2)*] $D($D CC:tt)*) => {
+                $crate::eval::unwrap!([false] $D($D CC)*);
+            };
+            ([$S1] [$($S2)*] $D($D CC:tt)*) => {
+                compile_error!("rukt: starts_with can only be applied to a delimiter-enclosed token tree");
+            };
+            ([$S1] $D Y:tt $D($D CC:tt)*) => {
+                $crate::eval_opaque!($D Y "inspect");
+            };
+            ([$D X:tt] $D Y:tt $D($D CC:tt)*) => {
+                $crate::eval_opaque!([$D X] "inspect");
+            };
+        }
+        __rukt_probe!([$S1] [$($S2)*] $T $N $P $V);
     };
 }
 
@@ -222,5 +689,2917 @@ macro_rules! builtin_starts_with_escaped {
 ///     }
 /// }
 /// ```
+///
+/// Opaque values parsed with fragment specifiers like `expr` can't be
+/// inspected either.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::starts_with;
+/// rukt! {
+///     let ($value:expr) = ([1, 2, 3]);
+///     let condition = value.starts_with(1); // error: rukt: can't inspect `[1, 2, 3]`
+/// }
+/// ```
 #[doc(inline)]
 pub use builtin_starts_with as starts_with;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_identity {
+    ({ () $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } $S $($C)* $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn identity($D value:tt) { value } } $($C)* $P $V $);
+    };
+}
+
+/// Function returning its argument unchanged.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::identity;
+/// rukt! {
+///     let f = identity;
+///     let a = f(42);
+///     let b = identity([1, 2, 3]);
+///     expand {
+///         assert_eq!($a, 42);
+///         assert_eq!($b, [1, 2, 3]);
+///     }
+/// }
+/// ```
+///
+/// When applied to a value with the method syntax, it simply passes the value
+/// through.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::identity;
+/// rukt! {
+///     let value = "hello".identity();
+///     expand {
+///         assert_eq!($value, "hello");
+///     }
+/// }
+/// ```
+///
+/// This is mostly useful as a default when passing functions around.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::identity;
+/// rukt! {
+///     fn apply_twice($f:tt $arg:tt) {
+///         let once = f($arg);
+///         f($once)
+///     }
+///     let f = identity;
+///     let value = apply_twice($f 7);
+///     expand {
+///         assert_eq!($value, 7);
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_identity as identity;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_const_fn {
+    ({ () $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } { fn const_fn($D($D _:tt)*) [$D value:tt] [$S] { value } } $($C)* $P $V $);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $crate::builtin_const_fn!($TT $($R)* ($FF; $D($CC)*) $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { () $($T)* } $N $P $V);
+    };
+}
+
+/// Create a function that ignores its arguments and always returns the given
+/// value.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::const_fn;
+/// rukt! {
+///     let f = const_fn(42);
+///     let a = f();
+///     let b = f(anything [goes] here);
+///     expand {
+///         assert_eq!([$a, $b], [42, 42]);
+///     }
+/// }
+/// ```
+///
+/// With the method syntax, the function returns the value it was applied to.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::const_fn;
+/// rukt! {
+///     let message = "hello";
+///     let f = message.const_fn();
+///     let result = f(1 2 3);
+///     expand {
+///         assert_eq!($result, "hello");
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_const_fn as const_fn;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_deep_map {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt $SS:tt $NN:tt $PP:tt $VV:tt) => {
+                $crate::builtin_deep_map_walk!($TT $($R)* [(root [$SS] [])] $NN $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $S $N $P $V);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn deep_map($D value:tt $D($D args:tt)*) { value.$crate::builtins::deep_map($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_deep_map_walk {
+    ($T:tt $F:tt [($K:tt [($($G:tt)*) $($X:tt)*] $R:tt) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [(() [$($G)*] []) ($K [$($X)*] $R) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [($K:tt [[$($G:tt)*] $($X:tt)*] $R:tt) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [([] [$($G)*] []) ($K [$($X)*] $R) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [($K:tt [{$($G:tt)*} $($X:tt)*] $R:tt) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [({} [$($G)*] []) ($K [$($X)*] $R) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [($K:tt [$L:tt $($X:tt)*] $R:tt) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($L) ($crate::builtin_deep_map_leaf; $F [($K [$($X)*] $R) $($Q)*] $N) $P $V $);
+    };
+    ($T:tt $F:tt [(() [] [$($R:tt)*]) ($K:tt $X:tt [$($RR:tt)*]) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [($K $X [$($RR)* ($($R)*)]) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [([] [] [$($R:tt)*]) ($K:tt $X:tt [$($RR:tt)*]) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [($K $X [$($RR)* [$($R)*]]) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [({} [] [$($R:tt)*]) ($K:tt $X:tt [$($RR:tt)*]) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [($K $X [$($RR)* {$($R)*}]) $($Q)*] $N $P $V $);
+    };
+    ($T:tt $F:tt [(root [] [$S:tt])] ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T $S $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_deep_map_leaf {
+    ($T:tt $S:tt $F:tt [($K:tt $X:tt [$($R:tt)*]) $($Q:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_deep_map_walk!($T $F [($K $X [$($R)* $S]) $($Q)*] $N $P $V $);
+    };
+}
+
+/// Apply a function to every token nested in this token tree, preserving the
+/// delimiters at every level.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::deep_map;
+/// rukt! {
+///     fn double($x:tt) {
+///         [$x $x]
+///     }
+///     let value = [1 (2 {3})].deep_map($double);
+///     expand {
+///         assert_eq!(stringify!($value), "[[1 1] ([2 2] {[3 3]})]");
+///     }
+/// }
+/// ```
+///
+/// Every token that isn't a delimiter-enclosed group is mapped one-to-one. The
+/// token tree returned by the function replaces the original token as a whole,
+/// so returning a group doesn't splice its content into the surrounding group.
+///
+/// When applied to a single token, the function is simply called with it.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::deep_map;
+/// rukt! {
+///     fn wrap($x:tt) {
+///         ($x)
+///     }
+///     let value = 42.deep_map($wrap);
+///     expand {
+///         assert_eq!(stringify!($value), "(42)");
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_deep_map as deep_map;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_reduce {
+    ({ ($($R:tt)*) $($T:tt)* } [] $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: can't reduce an empty list, there's no initial value");
+    };
+    ({ ($($R:tt)*) $($T:tt)* } [$X:tt $($Y:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt $NN:tt $PP:tt $VV:tt) => {
+                $crate::builtin_reduce_walk!($TT $($R)* [$($Y)*] $X $NN $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't reduce `", stringify!($S), "`, expected a list"));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn reduce($D value:tt $D($D args:tt)*) { value.$crate::builtins::reduce($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_reduce_walk {
+    ($T:tt $F:tt [$X:tt $($Y:tt)*] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($S $X) ($crate::builtin_reduce_step; $F [$($Y)*] $N) $P $V $);
+    };
+    ($T:tt $F:tt [] $S:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T $S $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_reduce_step {
+    ($T:tt $S:tt $F:tt $X:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_reduce_walk!($T $F $X $S $N $P $V $);
+    };
+}
+
+/// Combine the elements of a list with a function, using the first element as
+/// the initial accumulator.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::reduce;
+/// rukt! {
+///     fn add($a:tt $b:tt) {
+///         ($a + $b)
+///     }
+///     let total = [1 2 3 4].reduce($add);
+///     expand {
+///         assert_eq!($total, 10);
+///     }
+/// }
+/// ```
+///
+/// The function receives the accumulator and the next element, and its result
+/// becomes the new accumulator. Elements can be arbitrary token trees, so
+/// groups can be used as accumulator values.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::reduce;
+/// rukt! {
+///     fn nest($a:tt $b:tt) {
+///         ($a $b)
+///     }
+///     let value = [(a) b c].reduce($nest);
+///     expand {
+///         assert_eq!(stringify!($value), "(((a) b) c)");
+///     }
+/// }
+/// ```
+///
+/// Like [`Iterator::reduce`], there's no result for an empty list, so this is
+/// an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::reduce;
+/// rukt! {
+///     fn add($a:tt $b:tt) {
+///         ($a + $b)
+///     }
+///     let total = [].reduce($add); // error: can't reduce an empty list
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_reduce as reduce;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_fold_while_stripped; [$($R)*] { $($T)* } $N $P $V $)
+            ($crate::builtin_fold_while_invalid;)
+        );
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn fold_while($D value:tt $D($D args:tt)*) { value.$crate::builtins::fold_while($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't fold `", stringify!($S), "`, expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!($R ($crate::builtin_fold_while_substituted; $T $X $N $P $V) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while_substituted {
+    ([$A:tt $F:tt] $T:tt $X:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_fold_while_walk!($T $F $X $A $N $P $V $);
+    };
+    ([$($R:tt)*] $T:tt $X:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for fold_while, ",
+            "expected an initial value and a function",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while_walk {
+    ($T:tt $F:tt [$X:tt $($Y:tt)*] $A:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($A $X) ($crate::builtin_fold_while_step; $F [$($Y)*] $N) $P $V $);
+    };
+    ($T:tt $F:tt [] $A:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T $A $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_fold_while_step {
+    ($T:tt (continue $A:tt) $F:tt $X:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_fold_while_walk!($T $F $X $A $N $P $V $);
+    };
+    // the remaining elements are dropped without calling the function
+    ($T:tt (done $R:tt) $F:tt $X:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T $R $($C)* $P $V $);
+    };
+    ($T:tt $S:tt $F:tt $X:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid result `", stringify!($S), "` for fold_while, ",
+            "expected `(continue value)` or `(done value)`",
+        ));
+    };
+}
+
+/// Combine the elements of a group with a function that can stop early.
+///
+/// The first argument is the initial accumulator, and the second one is the
+/// function. The function receives the accumulator and the next element, and
+/// wraps its result to tell the builtin what to do next. Returning `(continue
+/// value)` makes `value` the new accumulator and moves on to the next element.
+/// Returning `(done value)` stops the fold right away, and `value` becomes the
+/// result without looking at the remaining elements. When the elements run
+/// out, the result is the last accumulator.
+///
+/// ```
+/// # #![recursion_limit = "512"]
+/// # use rukt::rukt;
+/// use rukt::builtins::fold_while;
+/// rukt! {
+///     fn above($found:tt $x:tt) {
+///         if x > 10 { (done $x) } else { (continue $found) }
+///     }
+///     let a = [3 8 12 5 20].fold_while(none $above);
+///     let b = [1 2 3].fold_while(none $above);
+///     expand {
+///         assert_eq!(stringify!([$a $b]), "[12 none]");
+///     }
+/// }
+/// ```
+///
+/// The accumulator can be any token tree, so the fold can collect elements
+/// until it finds the one it's looking for.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::fold_while;
+/// rukt! {
+///     fn until_zero([$($a:tt)*] $x:tt) {
+///         if x == 0 { (done [$($a)*]) } else { (continue [$($a)* $x]) }
+///     }
+///     let value = [3 1 0 2 4].fold_while([] $until_zero);
+///     expand {
+///         assert_eq!(stringify!($value), "[3 1]");
+///     }
+/// }
+/// ```
+///
+/// Any other result is an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::fold_while;
+/// rukt! {
+///     fn first($a:tt $x:tt) {
+///         x
+///     }
+///     let value = [1 2 3].fold_while(0 $first); // error: rukt: invalid result `1` for fold_while, expected `(continue value)` or `(done value)`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_fold_while as fold_while;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_apply {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt $SS:tt $NN:tt $PP:tt $VV:tt) => {
+                $crate::builtin_apply_call!($TT [$($R)*] $SS $NN $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $S $N $P $V);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn apply($D value:tt $D($D args:tt)*) { value.$crate::builtins::apply($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_apply_call {
+    ($T:tt [{ fn $($F:tt)* } [$($A:tt)*]] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T { fn $($F)* } ($($A)*) $N $P $V $);
+    };
+    ($T:tt [{ fn $($F:tt)* } ($($A:tt)*)] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T { fn $($F)* } ($($A)*) $N $P $V $);
+    };
+    ($T:tt [[$($A:tt)*]] { fn $($F:tt)* } $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T { fn $($F)* } ($($A)*) $N $P $V $);
+    };
+    ($T:tt [($($A:tt)*)] { fn $($F:tt)* } $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T { fn $($F)* } ($($A)*) $N $P $V $);
+    };
+    ($T:tt [$F:tt $A:tt] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't apply `", stringify!($F), "`, expected a function and an argument group"));
+    };
+    ($T:tt [$A:tt] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't apply `", stringify!($S), "`, expected a function"));
+    };
+    ($T:tt $R:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: invalid arguments for apply `", stringify!($R), "`, expected a function and an argument group"));
+    };
+}
+
+/// Call a function with the content of an argument group.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::apply;
+/// rukt! {
+///     fn sum($($x:tt)*) {
+///         (0 $(+ $x)*)
+///     }
+///     let f = sum;
+///     let a = apply($f [1 2 3]);
+///     let b = apply($f []);
+///     expand {
+///         assert_eq!([$a, $b], [6, 0]);
+///     }
+/// }
+/// ```
+///
+/// With the method syntax, the function is applied to the given group.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::apply;
+/// rukt! {
+///     fn pair($a:tt $b:tt) {
+///         ($a, $b)
+///     }
+///     let args = [1 2];
+///     let result = pair.apply($args);
+///     expand {
+///         assert_eq!($result, (1, 2));
+///     }
+/// }
+/// ```
+///
+/// Applying something that isn't a function is an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::apply;
+/// rukt! {
+///     let result = apply(42 [1 2 3]); // error: can't apply `42`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_apply as apply;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_dispatch {
+    ({ ($($R:tt)*) $($T:tt)* } [$($E:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_dispatch_lookup; [$($E)*] { $($T)* } $N $P $V $) $P $V $);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't dispatch on `", stringify!($S), "`, expected a list of `(name function)` pairs"));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn dispatch($D value:tt $D($D args:tt)*) { value.$crate::builtins::dispatch($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_dispatch_lookup {
+    ([$I:ident $($A:tt)*] $E:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_lookup {
+            ([($I $D F:tt) $D($D Y:tt)*] $D AA:tt $D TT:tt $D($D CC:tt)*) => {
+                $crate::eval_call!($D TT $D F $D AA $D($D CC)*);
+            };
+            ([$D X:tt $D($D Y:tt)*] $D($D CC:tt)*) => {
+                __rukt_lookup!([$D($D Y)*] $D($D CC)*);
+            };
+            ([] $D($D CC:tt)*) => {
+                compile_error!(concat!("rukt: no function named `", stringify!($I), "` to dispatch to"));
+            };
+        }
+        __rukt_lookup!($E ($($A)*) $T $N $P $V $);
+    };
+    ([$($A:tt)*] $E:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: invalid arguments for dispatch `(", stringify!($($A)*), ")`, expected a function name followed by arguments"));
+    };
+}
+
+/// Look up a function by name in a list of `(name function)` pairs and call
+/// it with the remaining arguments.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::dispatch;
+/// rukt! {
+///     fn double($x:tt) {
+///         ($x * 2)
+///     }
+///     fn triple($x:tt) {
+///         ($x * 3)
+///     }
+///     let helpers = [(double $double) (triple $triple)];
+///     let value = helpers.dispatch(triple 7);
+///     expand {
+///         assert_eq!($value, 21);
+///     }
+/// }
+/// ```
+///
+/// This makes it possible to bundle related functions into a single value
+/// that can be exported with `pub let` and imported as a whole with `use`.
+///
+/// ```
+/// # use rukt::rukt;
+/// mod helpers {
+///     use rukt::rukt;
+///     rukt! {
+///         fn double($x:tt) {
+///             ($x * 2)
+///         }
+///         pub(crate) let helpers = [(double $double)];
+///     }
+/// }
+/// use rukt::builtins::dispatch;
+/// rukt! {
+///     use helpers::helpers;
+///     let value = helpers.dispatch(double 7);
+///     expand {
+///         assert_eq!($value, 14);
+///     }
+/// }
+/// ```
+///
+/// Dispatching to a name that isn't in the list is an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::dispatch;
+/// rukt! {
+///     fn double($x:tt) {
+///         ($x * 2)
+///     }
+///     let helpers = [(double $double)];
+///     let value = helpers.dispatch(triple 7); // error: rukt: no function named `triple` to dispatch to
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_dispatch as dispatch;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_unique_by {
+    ({ ($($R:tt)*) $($T:tt)* } [$($X:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt $NN:tt $PP:tt $VV:tt) => {
+                $crate::builtin_unique_by_walk!($TT $($R)* [$($X)*] [] [] $NN $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't deduplicate `", stringify!($S), "`, expected a list"));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn unique_by($D value:tt $D($D args:tt)*) { value.$crate::builtins::unique_by($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_unique_by_walk {
+    ($T:tt $F:tt [$X:tt $($Y:tt)*] $K:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($X) ($crate::builtin_unique_by_step; $F $X [$($Y)*] $K $R $N) $P $V $);
+    };
+    ($T:tt $F:tt [] $K:tt $R:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T $R $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_unique_by_step {
+    ($T:tt $S:tt $F:tt $X:tt $Y:tt $K:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::escape!([$S] [] [__rukt_dollar] ($crate::builtin_unique_by_escaped; $X $T $F $Y $K $R $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_unique_by_escaped {
+    // keys that don't match themselves come from opaque fragments
+    ([$S:tt] $X:tt $T:tt $F:tt $Y:tt [$($K:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_compare {
+            $(
+                ($K $D XX:tt $D($D CC:tt)*) => {
+                    $crate::builtin_unique_by_walk!($D($D CC)*);
+                };
+            )*
+            ($S $D XX:tt $D TT:tt $D FF:tt $D YY:tt [$D($D KK:tt)*] [$D($D RR:tt)*] $D($D CC:tt)*) => {
+                $crate::builtin_unique_by_walk!($D TT $D FF $D YY [$D($D KK)* $S] [$D($D RR)* $D XX] $D($D CC)*);
+            };
+            ($D SS:tt $D($D CC:tt)*) => {
+                $crate::eval_opaque!([$S] "compare");
+            };
+        }
+        __rukt_compare!($S $X $T $F $Y [$($K)*] $R $N $P $V $);
+    };
+}
+
+/// Remove the elements of a list that have the same key as a previous element.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::unique_by;
+/// rukt! {
+///     fn field_name([$name:ident: $type:tt]) {
+///         name
+///     }
+///     let fields = [[x: u8] [y: u16] [x: u32]].unique_by($field_name);
+///     expand {
+///         assert_eq!(stringify!($fields).replace(" ", ""), "[[x:u8][y:u16]]");
+///     }
+/// }
+/// ```
+///
+/// The function is called with each element and returns the key used for
+/// comparing elements. The first element for each key is kept and the order of
+/// the list is preserved. Keys are compared like with the `==` operator, so
+/// keys from opaque fragments can't be compared.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::unique_by;
+/// rukt! {
+///     fn opaque($key:expr) {
+///         key
+///     }
+///     let values = [1 2].unique_by($opaque); // error: rukt: can't compare `1`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_unique_by as unique_by;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_zip_longest {
+    ({ ($($R:tt)*) $($T:tt)* } [$($X:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_zip_longest_substituted; { $($T)* } [$($X)*] $N $P $V) $P $V $);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't zip `", stringify!($S), "`, expected a list"));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn zip_longest($D value:tt $D($D args:tt)*) { value.$crate::builtins::zip_longest($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_zip_longest_substituted {
+    ([[$($Y:tt)*] $F:tt] $T:tt $X:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_zip_longest_walk!($X [$($Y)*] $F [] $T $N $P $V $);
+    };
+    ([$($R:tt)*] $T:tt $X:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for zip_longest, ",
+            "expected a list and a default value",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_zip_longest_walk {
+    (
+        [$A1:tt $A2:tt $A3:tt $A4:tt $($X:tt)*]
+        [$B1:tt $B2:tt $B3:tt $B4:tt $($Y:tt)*]
+        $F:tt
+        [$($Z:tt)*]
+        $T:tt $N:tt $P:tt $V:tt $D:tt
+    ) => {
+        $crate::builtin_zip_longest_walk!([$($X)*] [$($Y)*] $F [$($Z)* ($A1 $B1) ($A2 $B2) ($A3 $B3) ($A4 $B4)] $T $N $P $V $);
+    };
+    ([$A:tt $($X:tt)*] [$B:tt $($Y:tt)*] $F:tt [$($Z:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_zip_longest_walk!([$($X)*] [$($Y)*] $F [$($Z)* ($A $B)] $T $N $P $V $);
+    };
+    // once one of the lists runs out, the rest of the other one is paired with the default
+    ([$($A:tt)*] [] $F:tt [$($Z:tt)*] $T:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T [$($Z)* $(($A $F))*] $($C)* $P $V $);
+    };
+    ([] [$($B:tt)*] $F:tt [$($Z:tt)*] $T:tt ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T [$($Z)* $(($F $B))*] $($C)* $P $V $);
+    };
+}
+
+/// Pair up the elements of two lists, filling the missing elements of the
+/// shorter one with a default value.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::zip_longest;
+/// rukt! {
+///     let pairs = [a b c].zip_longest([1] X);
+///     expand {
+///         assert_eq!(stringify!($pairs).replace(" ", ""), "[(a1)(bX)(cX)]");
+///     }
+/// }
+/// ```
+///
+/// Either side can be the shorter one. The arguments can reference variables
+/// in scope.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::zip_longest;
+/// rukt! {
+///     let names = [x y z];
+///     let pairs = [1].zip_longest($names _);
+///     expand {
+///         assert_eq!(stringify!($pairs).replace(" ", ""), "[(1x)(_y)(_z)]");
+///     }
+/// }
+/// ```
+///
+/// The other list must be a bracketed `[]` group followed by a single token for
+/// the default value.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::zip_longest;
+/// rukt! {
+///     let pairs = [a b].zip_longest([1 2]); // error: rukt: invalid arguments `[1 2]` for zip_longest
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_zip_longest as zip_longest;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_chunk_by {
+    ({ ($($R:tt)*) $($T:tt)* } [$($X:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt $NN:tt $PP:tt $VV:tt) => {
+                $crate::builtin_chunk_by_walk!($TT $($R)* [$($X)*] [] [] $NN $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't chunk `", stringify!($S), "`, expected a list"));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn chunk_by($D value:tt $D($D args:tt)*) { value.$crate::builtins::chunk_by($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_chunk_by_walk {
+    ($T:tt $F:tt [$X:tt $($Y:tt)*] $Z:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($X) ($crate::builtin_chunk_by_step; $F $X [$($Y)*] $Z $R $N) $P $V $);
+    };
+    ($T:tt $F:tt [] [$($Z:tt)*] [$($R:tt)*] ($FF:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $FF!($T [$($R)* [$($Z)*]] $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_chunk_by_step {
+    // the separator closes the current chunk and is dropped
+    ($T:tt true $F:tt $X:tt $Y:tt [$($Z:tt)*] [$($R:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_chunk_by_walk!($T $F $Y [] [$($R)* [$($Z)*]] $N $P $V $);
+    };
+    ($T:tt false $F:tt $X:tt $Y:tt [$($Z:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_chunk_by_walk!($T $F $Y [$($Z)* $X] $R $N $P $V $);
+    };
+    ($T:tt $S:tt $F:tt $X:tt $($C:tt)*) => {
+        compile_error!(concat!(
+            "rukt: the predicate of chunk_by returned `", stringify!($S), "` for `", stringify!($X), "`, ",
+            "expected a boolean",
+        ));
+    };
+}
+
+/// Split a list into chunks separated by the elements matching a predicate.
+///
+/// ```
+/// # #![recursion_limit = "512"]
+/// # use rukt::rukt;
+/// use rukt::builtins::chunk_by;
+/// rukt! {
+///     fn is_sep($x:tt) {
+///         [$x] == [sep]
+///     }
+///     let chunks = [1 2 sep 3 sep 4].chunk_by($is_sep);
+///     expand {
+///         assert_eq!(stringify!($chunks).replace(" ", ""), "[[12][3][4]]");
+///     }
+/// }
+/// ```
+///
+/// The function is called with each element and returns `true` if the element
+/// is a separator. The separators are dropped, and each of them starts a new
+/// chunk. Like with [`slice::split`], a list with `n` separators always results
+/// in `n + 1` chunks, so leading, trailing or consecutive separators produce
+/// empty chunks.
+///
+/// ```
+/// # #![recursion_limit = "512"]
+/// # use rukt::rukt;
+/// use rukt::builtins::chunk_by;
+/// rukt! {
+///     fn is_comma($x:tt) {
+///         x == ,
+///     }
+///     let a = [, a,, b,].chunk_by($is_comma);
+///     let b = [].chunk_by($is_comma);
+///     expand {
+///         assert_eq!(stringify!($a).replace(" ", ""), "[[][a][][b][]]");
+///         assert_eq!(stringify!($b).replace(" ", ""), "[[]]");
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_chunk_by as chunk_by;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_map_indexed_stripped; [$($R)*] { $($T)* } $N $P $V $)
+            ($crate::builtin_map_indexed_invalid;)
+        );
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn map_indexed($D value:tt $D($D args:tt)*) { value.$crate::builtins::map_indexed($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't map `", stringify!($S), "` with indices, expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!($R ($crate::builtin_map_indexed_substituted; $T $X $K $N $P $V) $P $V $);
+    };
+}
+
+#[cfg(not(feature = "nightly-metavar-expr"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_substituted {
+    ([$F:tt] $T:tt $X:tt $K:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::integers!(($crate::builtin_map_indexed_walk; $T $F $X [] $K $N $P $V $));
+    };
+    ([$($R:tt)*] $T:tt $X:tt $K:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for map_indexed, ",
+            "expected a function",
+        ));
+    };
+}
+
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_substituted {
+    // all the indices fit in a single group when they come from the repetition
+    ([$F:tt] $T:tt [$($E:tt)*] $K:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_map_indexed_limit!(
+            [_ _ _ _ _ _ _ _ _ _ _ _ _ _ _ _] [$($E)*]
+            ([[$(${ignore($E)} ${index()})*]] $T $F [$($E)*] [] $K $N $P $V $)
+        );
+    };
+    ([$($R:tt)*] $T:tt $X:tt $K:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for map_indexed, ",
+            "expected a function",
+        ));
+    };
+}
+
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_limit {
+    ([$K:tt $($G:tt)*] [$X0:tt $X1:tt $X2:tt $X3:tt $X4:tt $X5:tt $X6:tt $X7:tt $X8:tt $X9:tt $X10:tt $X11:tt $X12:tt $X13:tt $X14:tt $X15:tt $($X:tt)*] $C:tt) => {
+        $crate::builtin_map_indexed_limit!([$($G)*] [$($X)*] $C);
+    };
+    ([] [$($X:tt)+] $C:tt) => {
+        compile_error!("rukt: can't map more than 256 elements with indices");
+    };
+    ($G:tt $X:tt ($($C:tt)*)) => {
+        $crate::builtin_map_indexed_walk!($($C)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_walk {
+    // the result gets the delimiter of the subject
+    ($G:tt $T:tt $F:tt [] [$($Z:tt)*] $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::with_delimiter!([$($Z)*] $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+    // the indices are taken from the table of integers, one group of sixteen at a time
+    ([[$I:tt $($J:tt)*] $($G:tt)*] $T:tt $F:tt [$E:tt $($X:tt)*] $Z:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_call!($T $F ($I $E) ($crate::builtin_map_indexed_step; [[$($J)*] $($G)*] $F [$($X)*] $Z $K $N) $P $V $);
+    };
+    ([[] $($G:tt)*] $T:tt $F:tt $X:tt $Z:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_map_indexed_walk!([$($G)*] $T $F $X $Z $K $N $P $V $);
+    };
+    ([] $T:tt $F:tt $X:tt $Z:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: can't map more than 256 elements with indices");
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_map_indexed_step {
+    ($T:tt $S:tt $G:tt $F:tt $X:tt [$($Z:tt)*] $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_map_indexed_walk!($G $T $F $X [$($Z)* $S] $K $N $P $V $);
+    };
+}
+
+/// Apply a function to every element of a group along with its index.
+///
+/// The function is called with two arguments, the index of the element as a
+/// decimal integer starting at `0`, followed by the element itself. The results
+/// are collected in a group with the same delimiter as the subject.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::map_indexed;
+/// rukt! {
+///     fn variant($i:tt $name:ident) {
+///         ($name = $i)
+///     }
+///     let variants = [Red Green Blue].map_indexed($variant);
+///     let [$(($name:ident = $value:tt))*] = variants;
+///     expand {
+///         #[derive(Debug, PartialEq)]
+///         enum Color {
+///             $($name = $value),*
+///         }
+///         assert_eq!(Color::Blue as u8, 2);
+///     }
+/// }
+/// ```
+///
+/// Groups count as a single element and are passed to the function as a
+/// whole. The subject can have at most 256 elements.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::map_indexed;
+/// rukt! {
+///     fn pair($i:tt $x:tt) {
+///         [$i $x]
+///     }
+///     let value = ((a b) {c}).map_indexed($pair);
+///     expand {
+///         assert_eq!(stringify!($value).replace(" ", ""), "([0(ab)][1{c}])");
+///     }
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_map_indexed as map_indexed;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_intersperse {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_intersperse_stripped; [$($R)*] { $($T)* } $N $P $V $)
+            ($crate::builtin_intersperse_invalid;)
+        );
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn intersperse($D value:tt $D($D args:tt)*) { value.$crate::builtins::intersperse($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_intersperse_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!($R ($crate::builtin_intersperse_substituted; $T $K $X $N $P $V) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_intersperse_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't intersperse `", stringify!($S), "`, expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_intersperse_substituted {
+    ([$E:tt] $T:tt $K:tt $X:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_intersperse_walk!($X $E $K [] $T $N $P $V $);
+    };
+    ([$($R:tt)*] $T:tt $K:tt $X:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for intersperse, ",
+            "expected a single separator token",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_intersperse_walk {
+    ([$A1:tt $A2:tt $A3:tt $A4:tt $A5:tt $($X:tt)*] $E:tt $K:tt [$($Z:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_intersperse_walk!([$A5 $($X)*] $E $K [$($Z)* $A1 $E $A2 $E $A3 $E $A4 $E] $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $($X:tt)*] $E:tt $K:tt [$($Z:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_intersperse_walk!([$B $($X)*] $E $K [$($Z)* $A $E] $T $N $P $V $);
+    };
+    // the last element isn't followed by a separator, and the result gets the delimiter of the subject
+    ([$($A:tt)?] $E:tt $K:tt [$($Z:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::with_delimiter!([$($Z)* $($A)?] $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+}
+
+/// Insert a separator between the elements of a group.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::intersperse;
+/// rukt! {
+///     let values = [a b c].intersperse(,);
+///     let [$first:ident, $($rest:tt)*] = values;
+///     expand {
+///         assert_eq!(stringify!($values).replace(" ", ""), "[a,b,c]");
+///         assert_eq!(stringify!($first), "a");
+///     }
+/// }
+/// ```
+///
+/// The result is still a single group with the same delimiter as the subject,
+/// as opposed to a flat stream of tokens, so it can be passed around and
+/// destructured like any other value. Empty groups and groups with a single
+/// element are returned unchanged. The separator can reference a variable in
+/// scope.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::intersperse;
+/// rukt! {
+///     let sep = +;
+///     let a = (1 2 3).intersperse($sep);
+///     let b = [x].intersperse($sep);
+///     expand {
+///         assert_eq!($a, 6);
+///         assert_eq!(stringify!($b), "[x]");
+///     }
+/// }
+/// ```
+///
+/// The separator must be a single token.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::intersperse;
+/// rukt! {
+///     let values = [a b].intersperse(, ;); // error: rukt: invalid arguments `, ;` for intersperse
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_intersperse as intersperse;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_cartesian_stripped; [$($R)*] { $($T)* } $N $P $V $)
+            ($crate::builtin_cartesian_invalid;)
+        );
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn cartesian($D value:tt $D($D args:tt)*) { value.$crate::builtins::cartesian($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't compute the cartesian product of `", stringify!($S), "`, expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!($R ($crate::builtin_cartesian_substituted; $T $K $X $N $P $V) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_substituted {
+    ([$G:tt] $T:tt $K:tt $X:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$G]
+            ($crate::builtin_cartesian_other; $X $K $T $N $P $V)
+            ($crate::builtin_cartesian_arguments;)
+        );
+    };
+    ($R:tt $T:tt $K:tt $X:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_cartesian_arguments!($R);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_arguments {
+    ([$($R:tt)*]) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for cartesian, ",
+            "expected a group",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_other {
+    ($Y:tt $L:tt $X:tt $K:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_cartesian_walk!($X $Y [] $K $T $N $P $V);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_cartesian_walk {
+    // each element of the subject gets paired with all the elements of the other group at once
+    ([$A:tt $($X:tt)*] [$($B:tt)*] [$($Z:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_cartesian_walk!([$($X)*] [$($B)*] [$($Z)* $(($A $B))*] $K $T $N $P $V);
+    };
+    // the result gets the delimiter of the subject
+    ([] $Y:tt [$($Z:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::with_delimiter!([$($Z)*] $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+}
+
+/// Pair every element of a group with every element of another group.
+///
+/// The pairs are enclosed in parentheses `()`, and ordered by the elements of
+/// the subject first, like nested `for` loops. The result is a group with the
+/// same delimiter as the subject, and the delimiter of the other group doesn't
+/// matter.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::cartesian;
+/// rukt! {
+///     let pairs = [a b].cartesian([1 2]);
+///     expand {
+///         assert_eq!(stringify!($pairs).replace(" ", ""), "[(a1)(a2)(b1)(b2)]");
+///     }
+/// }
+/// ```
+///
+/// This is convenient for generating an implementation for every combination
+/// of types. Groups count as a single element, and when either group is
+/// empty, the result is empty.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::cartesian;
+/// trait Convert<T> {}
+/// rukt! {
+///     let types = [u8 u16 (Vec<u8>)];
+///     let [$(($from:tt $to:tt))*] = types.cartesian($types);
+///     let none = types.cartesian([]);
+///     expand {
+///         $(impl Convert<$to> for $from {})*
+///         assert_eq!(stringify!($none), "[]");
+///     }
+/// }
+/// ```
+///
+/// The argument must be a single group.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::cartesian;
+/// rukt! {
+///     let pairs = [a b].cartesian(1 2); // error: rukt: invalid arguments `1 2` for cartesian, expected a group
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_cartesian as cartesian;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_swap_stripped; [$($R)*] { $($T)* } $S $N $P $V $)
+            ($crate::builtin_swap_invalid;)
+        );
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn swap($D value:tt $D($D args:tt)*) { value.$crate::builtins::swap($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't swap elements of `", stringify!($S), "`, expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!($R ($crate::builtin_swap_substituted; $T $X $K $S $N $P $V) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_substituted {
+    // the indices are forwarded as tts, captured literals would no longer match the integer table
+    ([$A:tt $B:tt] $T:tt $X:tt $K:tt $S:tt $N:tt $P:tt $V:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$A $B] [$A $B] [[$A $B] [$A $B] [$B $A]] $T ($crate::builtin_swap_ordered; $X $K $S $N) $P $V $));
+    };
+    ([$($R:tt)*] $T:tt $X:tt $K:tt $S:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for swap, ",
+            "expected two indices",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_ordered {
+    ($T:tt [$I:tt $J:tt] [$($X:tt)*] $K:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::split_at!([$($X)*] [$J] ($crate::builtin_swap_split_last; [$I $J] $K $T $S $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_split_last {
+    // the first index is the smallest, so the tokens before the second one contain the first element
+    ([$($L:tt)*] [$EJ:tt $($R:tt)*] [$I:tt $J:tt] $K:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::split_at!([$($L)*] [$I] ($crate::builtin_swap_split_first; $EJ [$($R)*] $K $T $S $N $P $V $));
+    };
+    ($L:tt [] [$I:tt $J:tt] $K:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: can't swap elements of `", stringify!($S), "`, index ", stringify!($J), " is out of range"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_swap_split_first {
+    ([$($L:tt)*] [$EI:tt $($M:tt)*] $EJ:tt [$($R:tt)*] $K:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::with_delimiter!([$($L)* $EJ $($M)* $EI $($R)*] $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+    // equal indices leave the subject unchanged
+    ($L:tt [] $EJ:tt $R:tt $K:tt $T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $S $($C)* $P $V $);
+    };
+}
+
+/// Exchange the elements at two indices of a group.
+///
+/// The indices start at `0` and can be given in either order. Groups count as
+/// a single element, and the result has the same delimiter as the subject.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::swap;
+/// rukt! {
+///     let fields = [a (b c) d {e}];
+///     let first = 1;
+///     let a = fields.swap($first 3);
+///     let b = (x y z).swap(2 0);
+///     let c = fields.swap(2 2);
+///     expand {
+///         assert_eq!(stringify!($a).replace(" ", ""), "[a{e}d(bc)]");
+///         assert_eq!(stringify!($b).replace(" ", ""), "(zyx)");
+///         assert_eq!(stringify!($c), stringify!($fields));
+///     }
+/// }
+/// ```
+///
+/// Indices past the end of the group are an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::swap;
+/// rukt! {
+///     let values = [a b c].swap(0 3); // error: rukt: can't swap elements of `[a b c]`, index 3 is out of range
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_swap as swap;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_filter_matching {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_filter_matching_stripped; [$($R)*] { $($T)* } $N $P $V $)
+            ($crate::builtin_filter_matching_invalid;)
+        );
+    };
+    // patterns would get mixed up with the parameters of a function value
+    ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: filter_matching can't be used as a function value, call it directly with a pattern");
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_filter_matching_stripped {
+    ($X:tt $K:tt $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::partition!($X $R ($crate::builtin_filter_matching_partitioned; $K $T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_filter_matching_partitioned {
+    ($M:tt $O:tt $K:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::with_delimiter!($M $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_filter_matching_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't filter `", stringify!($S), "`, expected a group"));
+    };
+}
+
+/// Keep the elements of a group that match a pattern.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::filter_matching;
+/// rukt! {
+///     let pairs = [(a 1) foo (b 2) (c)].filter_matching(($name:ident $value:tt));
+///     expand {
+///         assert_eq!(stringify!($pairs).replace(" ", ""), "[(a1)(b2)]");
+///     }
+/// }
+/// ```
+///
+/// The pattern is an arbitrary `macro_rules!` matcher, tried against each
+/// element on its own, so groups are always single candidates. The elements
+/// are kept as-is, and the metavariables of the pattern don't bind anything
+/// once the builtin returns. This makes it possible to filter elements by
+/// shape without defining a predicate function. The result has the same
+/// delimiter as the subject.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::filter_matching;
+/// rukt! {
+///     let idents = (a 1 "b" c [d]).filter_matching($x:ident);
+///     expand {
+///         assert_eq!(stringify!($idents), "(a c)");
+///     }
+/// }
+/// ```
+///
+/// Fragment specifiers other than `tt`, `ident` and `lifetime` are parsed
+/// eagerly by `macro_rules!`, so elements that only partially match them, like
+/// a lone `-` for `$x:literal`, result in an error instead of being dropped.
+/// Unlike most builtins that take arguments, `filter_matching` can't be used as
+/// a function value.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::filter_matching;
+/// rukt! {
+///     let f = filter_matching; // error: rukt: filter_matching can't be used as a function value
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_filter_matching as filter_matching;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_replace_pattern {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_replace_pattern_split!([] [$($R)*] [$S { $($T)* } $N $P $V] $);
+    };
+    // patterns would get mixed up with the parameters of a function value
+    ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: replace_pattern can't be used as a function value, call it directly with a pattern and a template");
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_replace_pattern_split {
+    ([] [=> $($B:tt)*] $A:tt $D:tt) => {
+        compile_error!("rukt: replace_pattern expects a non-empty pattern before `=>`");
+    };
+    ([$($M:tt)*] [=> $($B:tt)*] [$S:tt $T:tt $N:tt $P:tt $V:tt] $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_replace_pattern_stripped; [$($M)*] [$($B)*] $T $N $P $V $)
+            ($crate::builtin_replace_pattern_invalid;)
+        );
+    };
+    ([$($M:tt)*] [$X:tt $($R:tt)*] $A:tt $D:tt) => {
+        $crate::builtin_replace_pattern_split!([$($M)* $X] [$($R)*] $A $);
+    };
+    ([$($M:tt)*] [] $A:tt $D:tt) => {
+        compile_error!(concat!("rukt: invalid arguments `", stringify!($($M)*), "` for replace_pattern, expected `pattern => template`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_replace_pattern_stripped {
+    ([$($X:tt)*] $K:tt [$($M:tt)*] [$($B:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_replace_pattern {
+            // the names of the metavariables can't collide with the ones bound by the pattern
+            ([$($M)* $D($D __rukt_rest:tt)*] [$D($D __rukt_output:tt)*] $D __rukt_next:tt) => {
+                __rukt_replace_pattern!([$D($D __rukt_rest)*] [$D($D __rukt_output)* $($B)*] $D __rukt_next);
+            };
+            ([$D __rukt_first:tt $D($D __rukt_rest:tt)*] [$D($D __rukt_output:tt)*] $D __rukt_next:tt) => {
+                __rukt_replace_pattern!([$D($D __rukt_rest)*] [$D($D __rukt_output)* $D __rukt_first] $D __rukt_next);
+            };
+            ([] $D __rukt_output:tt ($D __rukt_next:path; $D($D __rukt_context:tt)*)) => {
+                $D __rukt_next!($D __rukt_output $D($D __rukt_context)*);
+            };
+        }
+        __rukt_replace_pattern!([$($X)*] [] ($crate::builtin_replace_pattern_replaced; $K $T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_replace_pattern_replaced {
+    ($O:tt $K:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::with_delimiter!($O $K ($crate::eval::unwrap; $T $N $P $V));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_replace_pattern_invalid {
+    ([$S:tt]) => {
+        compile_error!(concat!("rukt: can't replace patterns in `", stringify!($S), "`, expected a group"));
+    };
+}
+
+/// Rewrite the sequences of elements that match a pattern.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::replace_pattern;
+/// rukt! {
+///     let calls = [foo(1) bar foo(2)].replace_pattern(foo($n:tt) => call($n));
+///     expand {
+///         assert_eq!(stringify!($calls).replace(" ", ""), "[call(1)barcall(2)]");
+///     }
+/// }
+/// ```
+///
+/// The arguments are an arbitrary `macro_rules!` matcher, followed by `=>` and
+/// the template that replaces each match. The template is transcribed with the
+/// metavariables bound by the pattern, including repetitions, and can be empty
+/// to remove the matches. The result has the same delimiter as the subject.
+///
+/// The matching scans the top-level elements from left to right. At each
+/// position, the pattern is tried against the remaining elements, and when it
+/// matches a prefix of them, the prefix is replaced and the scan resumes right
+/// after it, so matches never overlap and the replaced tokens aren't scanned
+/// again. Otherwise the element is kept as-is and the scan moves on to the next
+/// one. Groups are single elements, so the contents of nested groups are only
+/// rewritten when the pattern itself descends into them.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::replace_pattern;
+/// rukt! {
+///     let a = (x x x).replace_pattern(x x => y);
+///     let b = [a = 1, [b = 2]].replace_pattern($k:ident = $v:tt => ($k $v));
+///     expand {
+///         assert_eq!(stringify!($a), "(y x)");
+///         assert_eq!(stringify!($b).replace(" ", ""), "[(a1),[b=2]]");
+///     }
+/// }
+/// ```
+///
+/// The pattern is followed by a repetition of the remaining elements in the
+/// generated `macro_rules!` definition, so it can't end with a fragment
+/// specifier like `expr` or `ty` that `macro_rules!` doesn't allow before
+/// arbitrary tokens, and a trailing repetition in the pattern is ambiguous.
+/// Stick to `tt`, `ident`, `lifetime` and `literal` at the end of the pattern.
+/// A pattern that matches an empty sequence never moves forward and runs into
+/// the recursion limit. Like [`filter_matching`], `replace_pattern` can't be
+/// used as a function value.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::replace_pattern;
+/// rukt! {
+///     let a = [1 2].replace_pattern(1 2); // error: rukt: invalid arguments `1 2` for replace_pattern, expected `pattern => template`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_replace_pattern as replace_pattern;
+
+crate::builtin! {
+    #[doc(hidden)]
+    #[macro_export]
+    builtin_not as crate::builtins::not {
+        (true, ()) => { false };
+        (false, ()) => { true };
+        ((), (true)) => { false };
+        ((), (false)) => { true };
+        ($value:tt, ()) => {
+            compile_error!(concat!("rukt: can't negate `", stringify!($value), "`, expected a boolean"))
+        };
+    }
+}
+
+/// Negate a boolean.
+///
+/// This is equivalent to the prefix `!` operator, but reads better at the end
+/// of a chain of method calls.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::{not, starts_with};
+/// rukt! {
+///     let a = [1 2 3].starts_with(2).not();
+///     let b = not(true);
+///     expand {
+///         assert_eq!([$a, $b], [true, false]);
+///     }
+/// }
+/// ```
+///
+/// Negating anything other than a boolean is an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::not;
+/// rukt! {
+///     let value = 42.not(); // error: can't negate `42`
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_not as not;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_count_tokens_deep {
+    ({ () $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::integers!(($crate::builtin_count_tokens_deep_walk; @start [$S] { $($T)* } $N $P $V $));
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn count_tokens_deep($D value:tt) { value.$crate::builtins::count_tokens_deep() } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_count_tokens_deep_walk {
+    ([$([$($K:tt)*])*] @start $X:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!($X [$($($K)*)*] $T $N $P $V $);
+    };
+    // splice the groups among the next tokens so that runs of leaves can be counted at once
+    ([($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([[$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([{$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt $H:tt ($($R:tt)*) $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $H $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt $H:tt [$($R:tt)*] $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $H $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt $H:tt {$($R:tt)*} $($X:tt)*] $K:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$A $B $C $E $F $G $H $($R)* $($X)*] $K $T $N $P $V $);
+    };
+    ([$A:tt $B:tt $C:tt $E:tt $F:tt $G:tt $H:tt $I:tt $($X:tt)*] [$K0:tt $K1:tt $K2:tt $K3:tt $K4:tt $K5:tt $K6:tt $K7:tt $($KR:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$($X)*] [$($KR)*] $T $N $P $V $);
+    };
+    ([$A:tt $($X:tt)*] [$K:tt $($KR:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_count_tokens_deep_walk!([$($X)*] [$($KR)*] $T $N $P $V $);
+    };
+    ([] [$K:tt $($KR:tt)*] $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $K $($C)* $P $V $);
+    };
+    ($X:tt [] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: can't count more than 255 tokens");
+    };
+}
+
+/// Count the tokens of a token tree, including the tokens nested in groups.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::count_tokens_deep;
+/// rukt! {
+///     let a = [a (b c) d].count_tokens_deep();
+///     let b = [[] () {}].count_tokens_deep();
+///     let c = 42.count_tokens_deep();
+///     expand {
+///         assert_eq!([$a, $b, $c], [4, 0, 1]);
+///     }
+/// }
+/// ```
+///
+/// Only the leaves are counted, the delimiters of groups don't count as tokens,
+/// including the ones of the token tree itself. Punctuation spanning multiple
+/// characters like `::` or `=>` counts as a single token, the same way it
+/// matches a single `tt` fragment.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::count_tokens_deep;
+/// rukt! {
+///     let count = [std::mem::take(&mut x)].count_tokens_deep();
+///     expand {
+///         assert_eq!($count, 8);
+///     }
+/// }
+/// ```
+///
+/// The result is an integer literal, which means that only up to `255` tokens
+/// can be counted, like with [range operators](crate::eval::operator#range-operators).
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::count_tokens_deep;
+/// rukt! {
+///     let values = 0..255;
+///     let count = [$values x].count_tokens_deep(); // error: rukt: can't count more than 255 tokens
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_count_tokens_deep as count_tokens_deep;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_struct_fields {
+    ({ () $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_struct_fields_parse!([$S] $S { $($T)* } $N $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn struct_fields($D value:tt) { value.$crate::builtins::struct_fields() } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_struct_fields_parse {
+    (
+        [{ $(#[$M:meta])* $SV:vis struct $I:ident { $($(#[$FM:meta])* $FV:vis $X:ident: $Y:ty),* $(,)? } }]
+        $S:tt $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt
+    ) => {
+        $F!($T [$(($X $Y))*] $($C)* $P $V $);
+    };
+    ([($($R:tt)*)] $S:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_struct_fields_parse!([{ $($R)* }] $S $T $N $P $V $);
+    };
+    ([[$($R:tt)*]] $S:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_struct_fields_parse!([{ $($R)* }] $S $T $N $P $V $);
+    };
+    ($R:tt $S:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: can't extract fields from `", stringify!($S), "`, ",
+            "expected a group containing a struct definition with named fields",
+        ));
+    };
+}
+
+/// Extract the fields of a struct definition.
+///
+/// The value must be a group containing a struct definition with named fields.
+/// The result is a list with one pair of tokens `(name type)` per field.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::struct_fields;
+/// rukt! {
+///     let fields = {
+///         struct Point {
+///             x: i32,
+///             y: i32,
+///         }
+///     }.struct_fields();
+///     expand {
+///         assert_eq!(stringify!($fields).replace(" ", ""), "[(xi32)(yi32)]");
+///     }
+/// }
+/// ```
+///
+/// Destructuring the result with a repetition makes it possible to generate
+/// code for each field of a struct stored as a value, like a `Default` impl.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::struct_fields;
+/// rukt! {
+///     let definition = {
+///         #[derive(Debug, PartialEq)]
+///         pub struct Config {
+///             pub name: String,
+///             retries: Option<u8>,
+///         }
+///     };
+///     let [$(($field:ident $ty:ty))*] = definition.struct_fields();
+///     let {$item:item} = definition;
+///     expand {
+///         $item
+///         impl Default for Config {
+///             fn default() -> Self {
+///                 Self {
+///                     $($field: <$ty>::default(),)*
+///                 }
+///             }
+///         }
+///     }
+/// }
+/// assert_eq!(Config::default(), Config { name: String::new(), retries: None });
+/// ```
+///
+/// Attributes and visibility modifiers are skipped. The types are parsed as
+/// `ty` fragments, so like with [`parse`] they are opaque and can only be
+/// expanded as-is. Tuple structs, unit structs and generics are not supported.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::struct_fields;
+/// rukt! {
+///     let fields = { struct Pair(u8, u8); }.struct_fields(); // error: rukt: can't extract fields
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_struct_fields as struct_fields;
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_builder {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_generate_builder_substituted; { $($T)* } $N $P $V) $P $V $);
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_builder_substituted {
+    ([$I:ident, { $($F:ident: $Y:ty),* $(,)? }] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::concat_idents!([$I Builder] ($crate::builtin_generate_builder_setters; $I [$(($F [$Y]))*] [] $T $N $P $V));
+    };
+    ([$($R:tt)*] $T:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for generate_builder, ",
+            "expected a struct name and a braced list of fields like `Name, { field: Type }`",
+        ));
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_builder_setters {
+    // the types are enclosed in brackets, since the fragments don't survive the procedural macro
+    ($B:ident $I:ident [($F:ident $Y:tt) $($X:tt)*] $Z:tt $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::utils::concat_idents!([set_ $F] ($crate::builtin_generate_builder_setter; $B $I $F $Y [$($X)*] $Z $T $N $P $V));
+    };
+    ($B:ident $I:ident [] [$(($F:ident [$($Y:tt)*] $M:ident))*] $T:tt ($FF:path; $($C:tt)*) $P:tt $V:tt) => {
+        #[derive(Default)]
+        struct $B {
+            $($F: ::core::option::Option<$($Y)*>,)*
+        }
+
+        #[allow(dead_code)]
+        impl $B {
+            $(
+                fn $M(mut self, value: $($Y)*) -> Self {
+                    self.$F = ::core::option::Option::Some(value);
+                    self
+                }
+            )*
+
+            fn build(self) -> ::core::result::Result<$I, &'static str> {
+                ::core::result::Result::Ok($I {
+                    $($F: match self.$F {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => {
+                            return ::core::result::Result::Err(concat!("missing field `", stringify!($F), "`"));
+                        }
+                    },)*
+                })
+            }
+        }
+
+        #[allow(dead_code)]
+        impl $I {
+            fn builder() -> $B {
+                ::core::default::Default::default()
+            }
+        }
+
+        $FF!($T $B $($C)* $P $V $);
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_builder_setter {
+    ($M:ident $B:ident $I:ident $F:ident $Y:tt $X:tt [$($Z:tt)*] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_generate_builder_setters!($B $I $X [$($Z)* ($F $Y $M)] $T $N $P $V);
+    };
+}
+
+/// Generate a builder for a struct.
+///
+/// The builtin takes the name of the struct and its fields, with the same
+/// syntax as the body of the struct, and expands to a builder next to the
+/// struct. The builder has the name of the struct followed by `Builder`, and
+/// a setter prefixed with `set_` for every field. Its `build` method returns
+/// the struct, or an error naming the first field that wasn't set. The struct
+/// also gets a `builder` associated function that returns an empty builder.
+/// The result is the name of the builder.
+///
+/// Creating the names of the builder and of the setters requires the `gensym`
+/// feature, like [`utils::concat_idents`](crate::utils::concat_idents).
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["gensym"] }
+/// ```
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::generate_builder;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Config {
+///     retries: u32,
+///     host: String,
+/// }
+///
+/// rukt! {
+///     generate_builder(Config, { retries: u32, host: String, });
+/// }
+///
+/// let config = Config::builder().set_retries(3).set_host("localhost".into()).build();
+/// assert_eq!(config, Ok(Config { retries: 3, host: "localhost".into() }));
+/// assert_eq!(ConfigBuilder::default().set_retries(3).build(), Err("missing field `host`"));
+/// ```
+///
+/// The builder and its methods are private, so they're only visible in the
+/// module of the struct. Since the fields are listed separately, the builtin
+/// can be combined with the other builtins to generate builders from tokens
+/// computed in Rukt code. The fields must match the fields of the struct.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::{generate_builder, struct_fields};
+/// rukt! {
+///     let definition = {
+///         struct Point {
+///             x: i32,
+///             y: i32,
+///         }
+///     };
+///     let [$(($field:ident $ty:ty))*] = definition.struct_fields();
+///     let {$item:item} = definition;
+///     let builder = generate_builder(Point, { $($field: $ty),* });
+///     expand {
+///         $item
+///         let point = $builder::default().set_x(1).set_y(2).build().unwrap();
+///         assert_eq!([point.x, point.y], [1, 2]);
+///     }
+/// }
+/// ```
+#[cfg(feature = "gensym")]
+#[doc(inline)]
+pub use builtin_generate_builder as generate_builder;
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_builder {
+    ($($T:tt)*) => {
+        $crate::builtin_requires_feature!(generate_builder "gensym");
+    };
+}
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+pub use builtin_generate_builder as generate_builder;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_dispatch {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_generate_dispatch_substituted; { $($T)* } $N $P $V) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_dispatch_substituted {
+    ([$I:ident, $E:ident, { $($A:ident: $Y:ty),* $(,)? }, [$($M:tt)*] $(,)?] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_generate_dispatch_methods!([$($M)*] [] $I $E [$(($A $Y))*] $T $N $P $V);
+    };
+    ([$($R:tt)*] $T:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for generate_dispatch, ",
+            "expected a trait, an enum, a braced list of variants like `{ Variant: Type }` and a list of methods",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_generate_dispatch_methods {
+    // the variants are a single token tree here, so that they can be repeated inside of each method
+    ([$M:ident $($X:tt)*] [$($Z:tt)*] $I:ident $E:ident [$(($A:ident $Y:tt))*] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_generate_dispatch_methods!([$($X)*] [$($Z)*
+            fn $M(&self) {
+                match self {
+                    $($E::$A(inner) => $I::$M(inner),)*
+                }
+            }
+        ] $I $E [$(($A $Y))*] $T $N $P $V);
+    };
+    ([($M:ident -> $R:ty) $($X:tt)*] [$($Z:tt)*] $I:ident $E:ident [$(($A:ident $Y:tt))*] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_generate_dispatch_methods!([$($X)*] [$($Z)*
+            fn $M(&self) -> $R {
+                match self {
+                    $($E::$A(inner) => $I::$M(inner),)*
+                }
+            }
+        ] $I $E [$(($A $Y))*] $T $N $P $V);
+    };
+    ([$O:tt $($X:tt)*] $Z:tt $I:ident $E:ident $W:tt $T:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: can't forward `", stringify!($O), "` in generate_dispatch, ",
+            "only methods taking `&self` without other arguments are supported, ",
+            "expected `name` or `(name -> Type)`",
+        ));
+    };
+    ([] [$($Z:tt)*] $I:ident $E:ident [$(($A:ident $Y:tt))*] $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt) => {
+        enum $E {
+            $($A($Y),)*
+        }
+
+        impl $I for $E {
+            $($Z)*
+        }
+
+        $F!($T $E $($C)* $P $V $);
+    };
+}
+
+/// Generate an enum that dispatches the methods of a trait to its variants.
+///
+/// The builtin takes the name of the trait, the name of the enum, the variants
+/// of the enum with the type they wrap, and the methods of the trait. It
+/// expands to the enum, with one variant per entry, and an implementation of
+/// the trait that matches on `self` and forwards each method to the value of
+/// the variant. The result is the name of the enum.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::generate_dispatch;
+///
+/// trait Shape {
+///     fn area(&self) -> f64;
+///     fn name(&self) -> &'static str;
+/// }
+///
+/// struct Square(f64);
+/// struct Circle(f64);
+///
+/// impl Shape for Square {
+///     fn area(&self) -> f64 { self.0 * self.0 }
+///     fn name(&self) -> &'static str { "square" }
+/// }
+///
+/// impl Shape for Circle {
+///     fn area(&self) -> f64 { 3.0 * self.0 * self.0 }
+///     fn name(&self) -> &'static str { "circle" }
+/// }
+///
+/// rukt! {
+///     generate_dispatch(Shape, AnyShape, {
+///         Square: Square,
+///         Circle: Circle,
+///     }, [(area -> f64) (name -> &'static str)]);
+/// }
+///
+/// let shapes = [AnyShape::Square(Square(2.0)), AnyShape::Circle(Circle(1.0))];
+/// assert_eq!(shapes.iter().map(Shape::area).sum::<f64>(), 7.0);
+/// assert_eq!(shapes[1].name(), "circle");
+/// ```
+///
+/// A method written as a plain identifier returns `()`, and a method written
+/// as `(name -> Type)` returns `Type`. For now, the methods can't take
+/// arguments other than `&self`, and other signatures are rejected.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::generate_dispatch;
+/// trait Greet {
+///     fn greet(&self, name: &str);
+/// }
+/// rukt! {
+///     generate_dispatch(Greet, AnyGreeter, { A: u8 }, [(greet(name: &str))]); // error: rukt: can't forward `(greet(name: &str))` in generate_dispatch
+/// }
+/// ```
+///
+/// The enum is private, and the variants can hold any type that implements the
+/// trait. The trait must be in scope.
+#[doc(inline)]
+pub use builtin_generate_dispatch as generate_dispatch;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_matches {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ([$($R)*] $D RR:tt $D SS:tt $D TT:tt $D NN:tt $D PP:tt $D VV:tt) => {
+                $crate::eval::unwrap!([$D SS] $D TT $D NN $D PP $D VV);
+            };
+            ($D X:tt [$D($D RR:tt)*] $D SS:tt $D($D CC:tt)*) => {
+                compile_error!(concat!(
+                    "rukt: assertion failed\n",
+                    "pattern = ", stringify!($D($D RR)*), "\n",
+                    "value = ", stringify!($D SS),
+                ));
+            };
+        }
+        __rukt_transcribe!([$S] [$($R)*] $S { $($T)* } $N $P $V);
+    };
+}
+
+/// Assert that the value matches the given `macro_rules` pattern.
+///
+/// The value is returned unchanged, so the assertion can be inserted in the
+/// middle of an expression. This is handy for checking that a transformation
+/// produced the expected shape.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_matches;
+/// rukt! {
+///     let fields = [a: u32, b: u8].assert_matches([$($name:ident: $type:ty),*]);
+///     expand {
+///         assert_eq!(stringify!($fields).replace(" ", ""), "[a:u32,b:u8]");
+///     }
+/// }
+/// ```
+///
+/// The error message shows both the pattern and the actual tokens when the
+/// value doesn't match.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_matches;
+/// rukt! {
+///     let _ = [a b].assert_matches([$name:ident]); // error: rukt: assertion failed
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_assert_matches as assert_matches;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_assert_len_substituted; [assert_len [exactly] [fail pass fail]] { $($T)* } $S $N $P $V) $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn assert_len($D value:tt $D($D args:tt)*) { value.$crate::builtins::assert_len($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_substituted {
+    ([$R:tt] $M:tt $T:tt $S:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_assert_len_length!($R $R $M $T $S $N $P $V $);
+    };
+    ([$($R:tt)*] [$I:ident $($M:tt)*] $T:tt $S:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for ", stringify!($I), ", ",
+            "expected a length",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_length {
+    // the length is forwarded as a tt, a captured literal would no longer match the integer table
+    ($L:literal $R:tt $M:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::strip_delimiters!(
+            [$S]
+            ($crate::builtin_assert_len_stripped; $R $M $T $S $N $P $V $)
+            ($crate::builtin_assert_len_invalid; $M)
+        );
+    };
+    ($L:tt $R:tt [$I:ident $($M:tt)*] $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid length `", stringify!($R), "` for ", stringify!($I), ", ",
+            "expected an integer between 0 and 255",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_invalid {
+    ([$S:tt] [$I:ident $($M:tt)*]) => {
+        compile_error!(concat!("rukt: can't check the length of `", stringify!($S), "` with ", stringify!($I), ", expected a group"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_stripped {
+    ($X:tt $K:tt $R:tt $M:tt $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::count!($X ($crate::builtin_assert_len_counted; $R $M $T $S $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_counted {
+    ($C:tt $R:tt [$I:ident $W:tt $O:tt] $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$C $R] [$C $R] $O $T ($crate::builtin_assert_len_checked; $C $R $W $S $N) $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_checked {
+    ($T:tt pass $C:tt $R:tt $W:tt $S:tt ($F:path; $($CC:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $S $($CC)* $P $V $);
+    };
+    ($T:tt fail $C:tt $R:tt [$($W:tt)*] $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: `", stringify!($S), "` has ", stringify!($C), " elements, ",
+            "expected ", stringify!($($W)* $R),
+        ));
+    };
+}
+
+/// Assert that a group has exactly the given number of elements.
+///
+/// The value is returned unchanged, so the assertion can be used as a
+/// precondition right before destructuring the value. The error message shows
+/// both the expected and the actual number of top-level elements, which reads
+/// better than a pattern mismatch.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len;
+/// rukt! {
+///     let args = [a (b c) d];
+///     let [$a:tt $b:tt $c:tt] = args.assert_len(3);
+///     expand {
+///         assert_eq!(stringify!($a $b $c).replace(" ", ""), "a(bc)d");
+///     }
+/// }
+/// ```
+///
+/// Groups count as a single element, and the length must be an integer
+/// between `0` and `255`. Use [`assert_len_at_least`] and
+/// [`assert_len_at_most`] to only check one bound.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len;
+/// rukt! {
+///     let args = [a b].assert_len(3); // error: rukt: `[a b]` has 2 elements, expected exactly 3
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_assert_len as assert_len;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_at_least {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_assert_len_substituted; [assert_len_at_least [at least] [fail pass pass]] { $($T)* } $S $N $P $V) $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn assert_len_at_least($D value:tt $D($D args:tt)*) { value.$crate::builtins::assert_len_at_least($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+/// Assert that a group has at least the given number of elements.
+///
+/// This is like [`assert_len`], but longer groups are returned unchanged too.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len_at_least;
+/// rukt! {
+///     let [$first:tt $($rest:tt)*] = [a b c].assert_len_at_least(1);
+///     expand {
+///         assert_eq!(stringify!($first), "a");
+///     }
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len_at_least;
+/// rukt! {
+///     let args = [].assert_len_at_least(1); // error: rukt: `[]` has 0 elements, expected at least 1
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_assert_len_at_least as assert_len_at_least;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_assert_len_at_most {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_assert_len_substituted; [assert_len_at_most [at most] [pass pass fail]] { $($T)* } $S $N $P $V) $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn assert_len_at_most($D value:tt $D($D args:tt)*) { value.$crate::builtins::assert_len_at_most($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+/// Assert that a group has at most the given number of elements.
+///
+/// This is like [`assert_len`], but shorter groups are returned unchanged too.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len_at_most;
+/// rukt! {
+///     let [$($flag:ident)*] = [a b].assert_len_at_most(2);
+///     expand {
+///         assert_eq!([$(stringify!($flag)),*], ["a", "b"]);
+///     }
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::assert_len_at_most;
+/// rukt! {
+///     let args = (a b c).assert_len_at_most(2); // error: rukt: `(a b c)` has 3 elements, expected at most 2
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_assert_len_at_most as assert_len_at_most;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_default_for {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute!([$($R)*] ($crate::builtin_default_for_substituted; { $($T)* } $N $P $V) $P $V $);
+    };
+    ($T:tt $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T { fn default_for($D($D args:tt)*) { $crate::builtins::default_for($D($D args)*) } } $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_default_for_substituted {
+    ([$Y:tt] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_default_for_lookup!([$Y] [(::core::default::Default::default())] $T $N $P $V $);
+    };
+    ([$Y:tt $Z:tt] $T:tt $N:tt $P:tt $V:tt) => {
+        $crate::builtin_default_for_lookup!([$Y] [$Z] $T $N $P $V $);
+    };
+    ([$($R:tt)*] $T:tt $N:tt $P:tt $V:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid arguments `", stringify!($($R)*), "` for default_for, ",
+            "expected a type and an optional fallback",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_default_for_lookup {
+    // types made of several tokens are passed in parentheses
+    ([($($Y:tt)+)] $Z:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_default_for_lookup!([$($Y)+] $Z $T $N $P $V $);
+    };
+    ([$($Y:tt)*] [$Z:tt] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::select!([$($Y)*] [
+            [[u8] [0]]
+            [[u16] [0]]
+            [[u32] [0]]
+            [[u64] [0]]
+            [[u128] [0]]
+            [[usize] [0]]
+            [[i8] [0]]
+            [[i16] [0]]
+            [[i32] [0]]
+            [[i64] [0]]
+            [[i128] [0]]
+            [[isize] [0]]
+            [[f32] [0.0]]
+            [[f64] [0.0]]
+            [[bool] [false]]
+            [[char] ['\0']]
+            [[()] [()]]
+            [[&str] [""]]
+            [[&'static str] [""]]
+            [[String] [(String::new())]]
+            [[Vec<$D __rukt_item:ty>] [(Vec::new())]]
+            [[Option<$D __rukt_item:ty>] [None]]
+            [[$D($D __rukt_type:tt)*] [$Z]]
+        ] ($crate::builtin_default_for_selected; $T $N $P $V) $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_default_for_selected {
+    ([$X:tt] $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt) => {
+        $F!($T $X $($C)* $P $V $);
+    };
+}
+
+/// Function returning the zero value of a type.
+///
+/// The result is an expression that can be used to initialize a value of the
+/// given type, which is convenient for generating initializers from the types
+/// of struct fields. Expressions made of several tokens are wrapped in
+/// parentheses, so that they remain a single token tree.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt;
+/// use rukt::builtins::default_for;
+/// rukt! {
+///     let count = default_for(u32);
+///     let enabled = default_for(bool);
+///     let name = default_for(String);
+///     expand {
+///         assert_eq!($count, 0);
+///         assert_eq!($enabled, false);
+///         assert_eq!($name, "");
+///         assert_eq!(stringify!($name).replace(" ", ""), "(String::new())");
+///     }
+/// }
+/// ```
+///
+/// The builtin knows about the integer and floating-point types, `bool`,
+/// `char`, `()`, `&str`, `String`, `Vec<T>` and `Option<T>`. Types made of
+/// several tokens need to be enclosed in parentheses, like `(Vec<u8>)`. Other
+/// types fall back to `Default::default()`, or to the second argument when
+/// there is one.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::default_for;
+/// #[derive(Debug, Default, PartialEq)]
+/// struct Point(i32, i32);
+/// rukt! {
+///     let fields = [(retries u8) (tags (Vec<u16>)) (origin Point)];
+///     for ($name:ident $ty:tt) in fields {
+///         let value = default_for($ty);
+///         expand {
+///             fn $name() -> $ty {
+///                 $value
+///             }
+///         }
+///     }
+///     let custom = default_for(Point (Point(1, 1)));
+///     expand {
+///         assert_eq!($custom, Point(1, 1));
+///     }
+/// }
+/// assert_eq!((retries(), tags(), origin()), (0, Vec::new(), Point(0, 0)));
+/// ```
+///
+/// The types are matched by their tokens, so aliases and paths like
+/// `std::string::String` use the fallback.
+#[doc(inline)]
+pub use builtin_default_for as default_for;
+
+#[cfg(feature = "debug")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_debug_assert {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($R)* } () ($crate::eval::operator; [] ($crate::builtin_debug_assert_check; [$($R)*] { $($T)* } $N)) $P $V $);
+    };
+}
+
+#[cfg(not(feature = "debug"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_debug_assert {
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } () $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_debug_assert_check {
+    ({} true $R:tt $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T () $($C)* $P $V $);
+    };
+    ({} false [$($R:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: debug assertion failed: ", stringify!($($R)*)));
+    };
+    ({} $S:tt [$($R:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: debug assertion `", stringify!($($R)*), "` evaluated to `", stringify!($S), "`, expected a boolean"));
+    };
+}
+
+/// Check an invariant when the `debug` feature is enabled.
+///
+/// The condition is a Rukt [`expression`](crate::eval::expression). With the
+/// `debug` feature of the `rukt` crate, a condition that evaluates to `false`
+/// aborts compilation. Without the feature, the condition isn't evaluated at
+/// all, so the check doesn't slow down regular builds.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["debug"] }
+/// ```
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::debug_assert;
+/// rukt! {
+///     let opcodes = [add sub];
+///     debug_assert(opcodes != []);
+/// }
+/// ```
+#[doc(inline)]
+pub use builtin_debug_assert as debug_assert;
+
+/// Trim leading and trailing whitespace from a string literal.
+///
+/// This builtin needs to look at the characters of the literal, so it's
+/// implemented as a procedural macro and requires the `strings` feature of the
+/// `rukt` crate.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["strings"] }
+/// ```
+///
+/// Without the feature, the string builtins can still be imported, but calling
+/// them is an error that tells you which feature to enable, so enabling it
+/// doesn't require changing any Rukt code.
+///
+/// The result is a regular string literal that can be used anywhere.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::trim;
+/// rukt! {
+///     let name = "  hello  ".trim();
+///     expand {
+///         assert_eq!($name, "hello");
+///     }
+/// }
+/// ```
+///
+/// Other tokens are rejected, including byte strings.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::trim;
+/// rukt! {
+///     let name = hello.trim(); // error: rukt: can't trim `hello`, expected a string literal
+/// }
+/// ```
+#[cfg(feature = "strings")]
+#[doc(inline)]
+pub use rukt_macros::builtin_trim as trim;
+
+/// Split a string literal into a list of lines.
+///
+/// This builtin requires the `strings` feature, like [`trim`]. Lines are split
+/// the same way as [`str::lines`]: both `\n` and `\r\n` end a line, and the
+/// final line ending is optional. A trailing newline doesn't produce an empty
+/// line at the end of the list, but blank lines in the middle of the literal
+/// are preserved.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::lines;
+/// rukt! {
+///     let [$($opcode:tt)*] = "
+/// add
+/// sub
+/// ".lines();
+///     expand {
+///         assert_eq!([$($opcode),*], ["", "add", "sub"]);
+///     }
+/// }
+/// ```
+///
+/// The lines are string literals, so the list can be used with `for` loops
+/// and in `expand` blocks. Combine it with [`trim`] to get rid of indentation.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::{deep_map, lines, trim};
+/// rukt! {
+///     let f = trim;
+///     let names = "
+///         foo
+///         bar
+///     ".trim().lines().deep_map($f);
+///     for name in names {
+///         expand {
+///             assert!(["foo", "bar"].contains(&$name));
+///         }
+///     }
+/// }
+/// ```
+#[cfg(feature = "strings")]
+#[doc(inline)]
+pub use rukt_macros::builtin_lines as lines;
+
+/// Parse the contents of a string literal into tokens.
+///
+/// This is the inverse of [`stringify!`], and it requires the `strings`
+/// feature, like [`trim`] and [`lines`]. When the contents are a single token
+/// tree, the result is that token tree. Otherwise, the tokens are wrapped in a
+/// list.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::to_tokens;
+/// rukt! {
+///     let numbers = "[1 2 3]".to_tokens();
+///     let sum = "1 + 2".to_tokens();
+///     expand {
+///         assert_eq!(stringify!($numbers), "[1 2 3]");
+///         assert_eq!(stringify!($sum), "[1 + 2]");
+///     }
+/// }
+/// ```
+///
+/// Combined with the other string builtins, this makes it possible to write
+/// tokens in a string, transform the string, and then turn it back into
+/// tokens that can be used in `expand` blocks.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::{deep_map, lines, to_tokens, trim};
+/// rukt! {
+///     let f = to_tokens;
+///     let constants = "
+///         const A: u32 = 1;
+///         const B: u32 = 2;
+///     ".trim().lines().deep_map($f);
+///     for [$($tokens:tt)*] in constants {
+///         expand {
+///             $($tokens)*
+///         }
+///     }
+/// }
+/// assert_eq!(A + B, 3);
+/// ```
+///
+/// Contents that can't be tokenized, like unbalanced delimiters or
+/// unterminated literals, are rejected.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// use rukt::builtins::to_tokens;
+/// rukt! {
+///     let tokens = "(1 2".to_tokens(); // error: rukt: can't to_tokens `"(1 2"`, the contents are not valid Rust tokens
+/// }
+/// ```
+#[cfg(feature = "strings")]
+#[doc(inline)]
+pub use rukt_macros::builtin_to_tokens as to_tokens;
+
+/// Build a whitespace-normalized string literal from a value.
+///
+/// The string is the same as the one built by
+/// [`utils::stringify_compact`](crate::utils::stringify_compact): token trees
+/// are separated by a single space, delimiters are tight against their
+/// contents, and punctuation like `::` or `=>` stays in one piece. This
+/// builtin requires the `strings` feature, like [`trim`], but the result is a
+/// string literal, so it can be compared with `==` in Rukt code.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::stringify_compact;
+/// rukt! {
+///     let tokens = [Vec::<u8>::new() => { f(x, [1]) }].stringify_compact();
+///     let same = tokens == "[Vec :: < u8 > :: new () => {f (x , [1])}]";
+///     expand {
+///         assert!($same);
+///     }
+/// }
+/// ```
+///
+/// Unlike [`stringify!`], the output doesn't depend on how the tokens were
+/// written, so comparing the strings of generated tokens is reliable. The
+/// value is stringified with its delimiters, and values parsed with fragment
+/// specifiers other than `tt` are stringified token by token.
+#[cfg(feature = "strings")]
+#[doc(inline)]
+pub use rukt_macros::builtin_stringify_compact as stringify_compact;
+
+// without the procedural backend, the string builtins still resolve so that the error explains what's missing
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_requires_feature {
+    ($I:ident $F:literal) => {
+        compile_error!(concat!("rukt: `", stringify!($I), "` requires the `", $F, "` feature of the `rukt` crate"));
+    };
+}
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_trim {
+    ($($T:tt)*) => {
+        $crate::builtin_requires_feature!(trim "strings");
+    };
+}
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+pub use builtin_trim as trim;
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_lines {
+    ($($T:tt)*) => {
+        $crate::builtin_requires_feature!(lines "strings");
+    };
+}
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+pub use builtin_lines as lines;
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_to_tokens {
+    ($($T:tt)*) => {
+        $crate::builtin_requires_feature!(to_tokens "strings");
+    };
+}
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+pub use builtin_to_tokens as to_tokens;
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_stringify_compact {
+    ($($T:tt)*) => {
+        $crate::builtin_requires_feature!(stringify_compact "strings");
+    };
+}
+
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+pub use builtin_stringify_compact as stringify_compact;
+
+/// Read an environment variable at compile time.
+///
+/// The result is a string literal, like with [`env!`]. Reading the environment
+/// can't be done with declarative macros, so this builtin is implemented as a
+/// procedural macro and requires the `env` feature of the `rukt` crate.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["env"] }
+/// ```
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::env;
+/// rukt! {
+///     let version = env("CARGO_PKG_VERSION");
+///     expand {
+///         assert_eq!($version, env!("CARGO_PKG_VERSION"));
+///     }
+/// }
+/// ```
+///
+/// The second argument is used when the variable isn't defined, like with
+/// [`option_env!`] and [`Option::unwrap_or`]. Without a fallback, an undefined
+/// variable is a compile-time error.
+///
+/// ```
+/// # use rukt::rukt;
+/// use rukt::builtins::env;
+/// rukt! {
+///     let profile = env("RUKT_UNDEFINED_VARIABLE" "release");
+///     expand {
+///         assert_eq!($profile, "release");
+///     }
+/// }
+/// ```
+///
+/// Importing the builtin shadows the standard [`env!`] macro, so invocations
+/// outside of Rukt code are forwarded to it, and `env!` keeps working in the
+/// rest of the module.
+///
+/// Unlike [`env!`], the compiler doesn't keep track of the variables read by
+/// this builtin. Variables set by Cargo are fine, but for other variables,
+/// make sure that the crate gets recompiled when they change by adding
+/// `cargo::rerun-if-env-changed=NAME` to the build script.
+#[cfg(feature = "env")]
+#[doc(inline)]
+pub use rukt_macros::builtin_env as env;
+
+#[cfg(not(feature = "env"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_env {
+    ({ $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::builtin_requires_feature!(env "env");
+    };
+    ($($T:tt)*) => {
+        ::core::env!($($T)*)
+    };
+}
+
+#[cfg(not(feature = "env"))]
+#[doc(hidden)]
+pub use builtin_env as env;
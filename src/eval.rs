@@ -26,6 +26,15 @@
 //! state. It's always bound to the dollar-sign token `$`, which can be useful
 //! for generating intermediate `macro_rules` definitions.
 //!
+//! Intermediate `macro_rules` definitions can reuse the same name, like
+//! `__rukt_transcribe`, as long as they're invoked right after being defined.
+//! The invocation resolves to the most recent definition with that name, so
+//! the definitions don't interfere with each other. They're not hygienic
+//! though: like any `macro_rules` definition produced by a macro, they remain
+//! visible after the block until the end of the enclosing module or function,
+//! and they shadow user macros with the same name. All of them are prefixed
+//! with `__rukt_`, which user code shouldn't use for its own macros.
+//!
 //! All macros expand to a call to a continuation. This can be a predetermined
 //! continuation or the [next dynamic continuation](#next-continuation).
 //!
@@ -140,6 +149,45 @@
 //! }
 //! ```
 //!
+//! The environment isn't always a flat list of variables. The branches of `if`
+//! statements and expressions, `do` expressions and the [`block`] builtin are
+//! evaluated as nested blocks, which keep the environment of the parent block
+//! as their first entry: the first pattern is a bracketed group with the
+//! patterns of the parent block, and the first value is a bracketed group with
+//! its values. This lets [`parent`] restore the environment of the parent block
+//! in a single step when the nested block ends. The entry can itself start
+//! with the environment of an enclosing block.
+//!
+//! ```
+//! macro_rules! example {
+//!     ($T:tt $S:tt $N:tt [[$($PP:tt)*] $($P:tt)*] [[$($PV:tt)*] $($V:tt)*] $D:tt) => {
+//!         // the parent block is in `$PP` and `$PV`, the nested bindings in `$P` and `$V`
+//!     };
+//! }
+//! ```
+//!
+//! Variable patterns always start with a dollar sign `$`, so macros that walk
+//! the environment can tell the entry of the parent block apart and descend
+//! into it, or skip it to only look at the variables of the nested block.
+//!
+//! Blocks evaluated with [`rukt_checked`](crate::rukt_checked) also carry the
+//! step counter as the very first entry, in front of the entry of the parent
+//! block. Its pattern is `$__rukt_steps:tt`, and its value is a bracketed group
+//! with the step limit, the help text of the error, and one token for each
+//! remaining step. It transcribes like any other variable, but macros that walk
+//! the environment should skip it, and leave it in front when they rebuild the
+//! environment.
+//!
+//! ```
+//! macro_rules! example {
+//!     ($T:tt $S:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*] $D:tt) => {
+//!         // the variables are in `$P` and `$V`, after the step counter
+//!     };
+//! }
+//! ```
+//!
+//! [`block`]: crate::builtins::block
+//!
 //! To substitute variables defined in the current scope, you can generate and
 //! expand an intermediate macro.
 //!
@@ -171,35 +219,161 @@ macro_rules! eval_block {
     ({} $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!({} () $($C)* $P $V $);
     };
+    // statements of checked blocks are counted by a macro that only exists with the `checked` feature,
+    // the subject marks the statement as counted
+    ($T:tt () $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] $V:tt $D:tt) => {
+        $crate::eval_block_checked_step!($T () $N [$DD __rukt_steps:tt $($P)*] $V $);
+    };
+    ({ ; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    // simple bindings skip the expression evaluator to save recursion depth
+    ({ let $I:ident = $X:ident; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $D($D CC:tt)*) => {
+                $crate::eval_let_variable!([$D$X] $D($D CC)*);
+            };
+        }
+        __rukt_transcribe!($V $I $X { $($T)* } $N $P $V $);
+    };
+    ({ let $L:tt = $X:ident; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $X; $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding; $L $N)) $P $V $);
+    };
+    ({ let _ = $X:tt; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    // the space after the group keeps `stringify!` output the same as with the expression evaluator
+    ({ let $I:ident = ($($R:tt)*); $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_define!($I $P $V $);
+        macro_rules! __rukt_transcribe {
+            ($P $D II:ident $D TT:tt $D NN:tt [$D($D PP:tt)*] [$D($D VV:tt)*] $D DD:tt) => {
+                $crate::eval::block!($D TT () $D NN [$D($D PP)* $D DD $D II:tt] [$D($D VV)* ($($R)*) ] $);
+            };
+        }
+        __rukt_transcribe!($V $I { $($T)* } $N $P $V $);
+    };
+    ({ let $I:ident = [$($R:tt)*]; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_define!($I $P $V $);
+        macro_rules! __rukt_transcribe {
+            ($P $D II:ident $D TT:tt $D NN:tt [$D($D PP:tt)*] [$D($D VV:tt)*] $D DD:tt) => {
+                $crate::eval::block!($D TT () $D NN [$D($D PP)* $D DD $D II:tt] [$D($D VV)* [$($R)*] ] $);
+            };
+        }
+        __rukt_transcribe!($V $I { $($T)* } $N $P $V $);
+    };
+    ({ let $I:ident = {$($R:tt)*}; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_define!($I $P $V $);
+        macro_rules! __rukt_transcribe {
+            ($P $D II:ident $D TT:tt $D NN:tt [$D($D PP:tt)*] [$D($D VV:tt)*] $D DD:tt) => {
+                $crate::eval::block!($D TT () $D NN [$D($D PP)* $D DD $D II:tt] [$D($D VV)* {$($R)*} ] $);
+            };
+        }
+        __rukt_transcribe!($V $I { $($T)* } $N $P $V $);
+    };
+    ({ let $I:ident = $X:tt; $($T:tt)* } $S:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $X] $);
+    };
     ({ let $L:tt = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding; $L $N)) $P $V $);
     };
     ({ $(#[$A:meta])* pub $(($($E:tt)*))? let $L:ident = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub; $L [$(#[$A])*] [pub $(($($E)*))*] $N)) $P $V $);
     };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? let $L:ident as $X:ident = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub; [$L as $X] [$(#[$A])*] [pub $(($($E)*))*] $N)) $P $V $);
+    };
+    ({ $(#[$($A:tt)*])* pub $(($($E:tt)*))? let $L:ident in $G:ident = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub; $L [$(#[$($A)*])*] [in $G pub $(($($E)*))*] $N)) $P $V $);
+    };
+    ({ $(#[$($A:tt)*])* pub $(($($E:tt)*))? let $L:ident as $X:ident in $G:ident = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub; [$L as $X] [$(#[$($A)*])*] [in $G pub $(($($E)*))*] $N)) $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? let _ $(in $G:ident)? = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: can't export `_`, `pub let` requires a variable name or a pattern");
+    };
+    ({ $(#[$($A:tt)*])* pub $(($($E:tt)*))? let $L:tt in $G:ident = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub_pattern; $L [$(#[$($A)*])*] [in $G pub $(($($E)*))*] $N)) $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? let $L:tt = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding_pub_pattern; $L [$(#[$A])*] [pub $(($($E)*))*] $N)) $P $V $);
+    };
     ({ use $($I:ident)::+; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $($I)::*!({ $($T)* } () ($crate::eval_use_import; [$($I)::*] $N) $P $V $);
     };
     ({ use $($I:ident)::+ as $A:ident; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $($I)::*!({ $($T)* } () ($crate::eval_use_import; [$A] $N) $P $V $);
     };
+    ({ use $($I:ident)::+ ::*; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $($I)::*!({ $($T)* } () ($crate::eval_use_glob; [] [$($I)*] $N) $P $V $);
+    };
+    ({ use $($I:ident::)* { $($G:tt)* }; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_group!([$($I::)*] { $($G)* } [] { $($T)* } $N $P $V $);
+    };
+    ({ $(#[$A:meta])* $(pub $(($($E:tt)*))?)? static mut $I:ident: $Y:ty = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_static_binding; [$(#[$A])* $(pub $(($($E)*))*)* static mut] $I [$Y] $N)) $P $V $);
+    };
+    ({ $(#[$A:meta])* $(pub $(($($E:tt)*))?)? static $I:ident: $Y:ty = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_static_binding; [$(#[$A])* $(pub $(($($E)*))*)* static] $I [$Y] $N)) $P $V $);
+    };
+    // a `meta` fragment would be opaque, and `#[macro_export]` needs to be recognized to export the constant
+    ({ $(#[$($A:tt)*])* pub $(($($E:tt)*))? const $I:ident: $Y:ty = $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_const_binding; [$(#[$($A)*])*] [] [] $I [$Y] [pub $(($($E)*))*] $N)) $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? use $($I:ident)::+; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $($I)::*!({ ; $($T)* } () ($crate::eval_use_import_pub; [$($I)::*] [$(#[$A])*] [pub $(($($E)*))*] $N) $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? use $($I:ident)::+ as $L:ident; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $($I)::*!({ ; $($T)* } () ($crate::eval_use_import_pub; [$L] [$(#[$A])*] [pub $(($($E)*))*] $N) $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? registry $I:ident; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::escape_repetitions!([{}] [] [$DD] ($crate::export_variable; $I [$(#[$A])*] [pub $(($($E)*))*] [$DD:tt] $));
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    ({ $(#[$A:meta])* $(pub $(($($E:tt)*))?)? append $($I:ident)::+ { $($G:tt)* }; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_append_name!([$($I)::*] [$($I)*] [$(#[$A])*] [$([pub $(($($E)*))*])*] { { $($G)* }; $($T)* } $N $P $V $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? group $I:ident { $($G:tt)* } $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_binding_pub!({ ; $($T)* } { $($G)* } $I [$(#[$A])*] [pub $(($($E)*))*] $N $P $V $);
+    };
+    ({ $(#[$A:meta])* $(pub $(($($E:tt)*))?)? fn $I:ident $(as $X:ident)? ($($R:tt)*) where $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_fn_guard!({ $($T)* } [] [$(#[$A])* $(pub $(($($E)*))*)* fn $I $(as $X)* ($($R)*)] $I $S $N $P $V $);
+    };
     ({ fn $I:ident($($R:tt)*) { $($B:tt)* } $($T:tt)* } $S:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
-        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* { fn $I($($R)*) [$($P)*] [$($V)*] { $($B)* } }] $);
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval_fn_capture!([$($P)*] [$($B)*] ([] $I ($($R)*) { $($B)* } { $($T)* } $N [$($P)*] [$($V)*]) $);
     };
     ({ $(#[$A:meta])* pub $(($($E:tt)*))? fn $I:ident($($R:tt)*) { $($B:tt)* } $($T:tt)* } $S:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
-        $crate::utils::escape_repetitions!([{ fn $I($($R)*) [$($P)*] [$($V)*] { $($B)* } }] [] [$DD] ($crate::export_function; $I [$(#[$A])*] [pub $(($($E)*))*] [$DD:tt] $));
-        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* { fn $I($($A)*) [$($P)*] [$($V)*] { $($B)* } }] $);
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval_fn_capture!([$($P)*] [$($B)*] ([$I [$(#[$A])*] [pub $(($($E)*))*]] $I ($($R)*) { $($B)* } { $($T)* } $N [$($P)*] [$($V)*]) $);
+    };
+    ({ $(#[$A:meta])* pub $(($($E:tt)*))? fn $I:ident as $X:ident($($R:tt)*) { $($B:tt)* } $($T:tt)* } $S:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval_fn_capture!([$($P)*] [$($B)*] ([$X [$(#[$A])*] [pub $(($($E)*))*]] $I ($($R)*) { $($B)* } { $($T)* } $N [$($P)*] [$($V)*]) $);
     };
     ({ if $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_statement; [] $N)) $P $V $);
     };
+    ({ for ($Q1:tt $I:ident, $Q2:tt $X:ident) in $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_for_index_detect!([=$Q1=] [=$Q2=] [$I $X] ($Q1 $I, $Q2 $X) { $($T)* } $N $P $V $);
+    };
+    ({ for $L:tt in $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_for_loop; $L $N)) $P $V $);
+    };
+    ({ error $M:literal; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::error!($M $P $V $);
+    };
+    ({ warn $M:literal; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        const _: () = {
+            #[deprecated = $M]
+            #[allow(non_camel_case_types)]
+            struct rukt_warning;
+            let _ = rukt_warning;
+        };
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
     ({ expand { $($B:tt)* } $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
-        macro_rules! __rukt_transcribe {
-            ($P) => {
-                $($B)*
-            };
-        }
-        __rukt_transcribe!($V);
+        $crate::eval::expand_escape!([$($B)*] [] [$DD] ($crate::eval_expand_escaped; $P $V [$DD:tt]));
         $crate::eval::block!({ $($T)* } () $N $P $V $);
     };
     ($T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
@@ -210,30 +384,294 @@ macro_rules! eval_block {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_let_binding {
+    ({ assert $($T:tt)* } $S:tt $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_assert_split!({ $($T)* } [] $S $L $N $P $V $);
+    };
     ({ ; $($T:tt)* } $S:tt _ $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::block!({ $($T)* } () $N $P $V $);
     };
     ({ ; $($T:tt)* } $S:tt $I:ident $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
         $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
     };
-    ({ ; $($T:tt)* } $S:tt $L:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
-        $crate::eval::block!({ $($T)* } () $N [$($P)* $L] [$($V)* $S] $);
+    ({ ; $($T:tt)* } $S:tt [$($L:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_rest!([$($L)*] [] [] ({ $($T)* } $S [$($L)*] $N $P $V) $);
+    };
+    ({ ; $($T:tt)* } $S:tt ($($I:ident),+ $(,)?) $N:tt $P:tt $V:tt $D:tt) => {
+        $($crate::eval_define!($I $P $V $);)*
+        $crate::eval_let_tuple!($S [$($I)*] { $($T)* } $N $P $V $);
+    };
+    ({ ; $($T:tt)* } $S:tt ($($L:tt)*) $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_rest!([$($L)*] [] () ({ $($T)* } $S ($($L)*) $N $P $V) $);
+    };
+    ({ ; $($T:tt)* } $S:tt {$($L:tt)*} $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_rest!([$($L)*] [] {} ({ $($T)* } $S {$($L)*} $N $P $V) $);
+    };
+    // a single literal token doesn't bind anything, the value only needs to match it
+    ({ ; $($T:tt)* } $S:tt $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($L $D($D CC:tt)*) => {
+                $crate::eval::block!($D($D CC)*);
+            };
+            ($D SS:tt $D($D CC:tt)*) => {
+                $crate::eval_let_mismatch!($D SS [$L]);
+            };
+        }
+        __rukt_transcribe!($S { $($T)* } () $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_variable {
+    ([$X:tt] $I:ident $Y:ident $T:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!($T () $N [$($P)* $D$I:tt] [$($V)* $X] $);
+    };
+    // the identifier isn't a variable, evaluate it as a builtin
+    ([$($X:tt)*] $I:ident $Y:ident { $($T:tt)* } $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $Y; $($T)* } () ($crate::eval::operator; [] ($crate::eval_let_binding; $I $N)) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_assert_split {
+    // the condition is checked by the continuation once the value is bound
+    ({ ; $($T:tt)* } [$($C:tt)*] $S:tt $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_binding!({ ; } $S $L ($crate::eval_let_assert; [$S $L ($($C)*)] { $($C)* } { $($T)* } $N) $P $V $);
+    };
+    ({ $X:tt $($T:tt)* } [$($C:tt)*] $S:tt $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_assert_split!({ $($T)* } [$($C)* $X] $S $L $N $P $V $);
+    };
+    ({} [$($C:tt)*] $S:tt $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: expected `;` after `assert ", stringify!($($C)*), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_assert {
+    ({} () $A:tt { $($C:tt)* } $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($C)* } () ($crate::eval::operator; [] ($crate::eval_let_assert_result; $A $T $N)) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_assert_result {
+    ({} true $A:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($T () $N $P $V $);
+    };
+    ({} false [$S:tt $L:tt ($($C:tt)*)] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: assertion `", stringify!($($C)*), "` failed for `let ", stringify!($L), "`\n",
+            "value = ", stringify!($S),
+        ));
+    };
+    ({} $X:tt [$S:tt $L:tt ($($C:tt)*)] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: assertion `", stringify!($($C)*), "` for `let ", stringify!($L), "` ",
+            "must evaluate to `true` or `false`, got `", stringify!($X), "`",
+        ));
+    };
+    ({ $($R:tt)+ } $X:tt $A:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: unexpected tokens `", stringify!($($R)*), "` in the assertion"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_tuple {
+    (($($X:tt)*) [$($I:ident)*] $T:tt $N:tt [$($P:tt)*] $V:tt $D:tt) => {
+        // the patterns of the new variables are passed as arguments so that they don't get transcribed
+        macro_rules! __rukt_transcribe {
+            ([$($D$I:tt)*] $D TT:tt $D NN:tt $D PP:tt [$D($D VV:tt)*]) => {
+                $crate::eval::block!($D TT () $D NN $D PP [$D($D VV)* $($D$I)*] $);
+            };
+            ([$D($D XX:tt)*] $D($D CC:tt)*) => {
+                $crate::eval_let_tuple!([$D($D XX)*] [$($I)*]);
+            };
+        }
+        __rukt_transcribe!([$($X)*] $T $N [$($P)* $($D$I:tt)*] $V);
+    };
+    ([$($X:tt)*] [$($I:ident)*]) => {
+        compile_error!(concat!(
+            "rukt: value `", stringify!(($($X)*)), "` doesn't have the same number of elements as `(",
+            stringify!($($I),*), ")`",
+        ));
+    };
+    ($S:tt [$($I:ident)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: can't destructure `", stringify!($S), "` into `(", stringify!($($I),*), ")`, ",
+            "expected a parenthesized group",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_define {
+    // an unbound variable is transcribed as-is, so a bound one shows up as a single token
+    ($I:ident $P:tt $V:tt $D:tt) => {
+        const _: () = {
+            macro_rules! __rukt_transcribe {
+                ($P $D II:ident) => {
+                    $crate::eval_define_check!([= $D$I =] $D II);
+                };
+            }
+            __rukt_transcribe!($V $I);
+        };
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_define_check {
+    ([= $X:tt $Y:ident =] $I:ident) => {};
+    ([= $X:tt =] $I:ident) => {
+        compile_error!(concat!("rukt: variable `", stringify!($I), "` is already defined in this scope; shadowing is not allowed"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_rest {
+    ([.. $H:tt $R:ident] [$($Z:tt)*] $K:tt $C:tt $D:tt) => {
+        $crate::eval_let_rest_detect!([=$H=]
+            [$R [$($Z)*] $K $C $]
+            [[$H $R] [$($Z)* ..] $K $C $]
+        );
+    };
+    ([$X:tt $($Y:tt)*] [$($Z:tt)*] $K:tt $C:tt $D:tt) => {
+        $crate::eval_let_rest!([$($Y)*] [$($Z)* $X] $K $C $);
+    };
+    ([] $Z:tt $K:tt ($T:tt $S:tt $L:tt $N:tt [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($L $D LL:tt $D($D CC:tt)*) => {
+                $crate::eval::block!($D($D CC)*);
+            };
+            ($D SS:tt [$D($D LL:tt)*] $D($D CC:tt)*) => {
+                $crate::eval_let_mismatch!($D SS [$D($D LL)*]);
+            };
+        }
+        __rukt_transcribe!($S [$L] $T () $N [$($P)* $L] [$($V)* $S] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_mismatch {
+    ($S:tt [$L:tt]) => {
+        compile_error!(concat!("rukt: value `", stringify!($S), "` does not match pattern `", stringify!($L), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_rest_detect {
+    ([$(=)$+] [$R:ident [$($Z:tt)*] [] $C:tt $D:tt] $F:tt) => {
+        $crate::eval_let_rest_split!([$($Z)* $D($D __rukt_rest:tt)*] __rukt_rest $R $C $);
+    };
+    ([$(=)$+] [$R:ident [$($Z:tt)*] () $C:tt $D:tt] $F:tt) => {
+        $crate::eval_let_rest_split!(($($Z)* $D($D __rukt_rest:tt)*) __rukt_rest $R $C $);
+    };
+    ([$(=)$+] [$R:ident [$($Z:tt)*] {} $C:tt $D:tt] $F:tt) => {
+        $crate::eval_let_rest_split!({$($Z)* $D($D __rukt_rest:tt)*} __rukt_rest $R $C $);
+    };
+    ([=$H:tt=] $Y:tt [$($F:tt)*]) => {
+        $crate::eval_let_rest!($($F)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_rest_split {
+    // the hidden repetition is bound in the environment alongside the rest of the pattern
+    ($L:tt $X:ident $R:ident ($T:tt $S:tt $O:tt $N:tt $P:tt $V:tt) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($L $D LL:tt [$D($D RR:tt)*] $D OO:tt $D SS:tt $D TT:tt $D NN:tt [$D($D PP:tt)*] [$D($D VV:tt)*]) => {
+                $crate::eval::block!($D TT () $D NN [$D($D PP)* $D LL $D($D RR)*] [$D($D VV)* $D SS [$D($D $X)*]] $);
+            };
+            ($D SS:tt $D LL:tt $D RR:tt [$D($D OO:tt)*] $D($D CC:tt)*) => {
+                $crate::eval_let_mismatch!($D SS [$D($D OO)*]);
+            };
+        }
+        __rukt_transcribe!($S $L [$D $R:tt] [$O] $S $T $N $P $V);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_let_binding_pub {
+    ({ ; $($T:tt)* } $S:tt [$I:ident as $X:ident] $A:tt [in $G:ident $($E:tt)*] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::export_group!($G [$($E)*] [([$S] $X $A [as])] $);
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+    ({ ; $($T:tt)* } $S:tt $I:ident $A:tt [in $G:ident $($E:tt)*] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::export_group!($G [$($E)*] [([$S] $I $A [])] $);
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+    ({ ; $($T:tt)* } $S:tt [$I:ident as $X:ident] $A:tt $E:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::export_escape!([$S] [] [$DD] ($crate::export_variable; $X $A $E [$DD:tt] $));
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
     ({ ; $($T:tt)* } $S:tt $I:ident $A:tt $E:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
-        $crate::utils::escape_repetitions!([$S] [] [$DD] ($crate::export_variable; $I $A $E [$DD:tt] $));
+        $crate::export_escape!([$S] [] [$DD] ($crate::export_variable; $I $A $E [$DD:tt] $));
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
         $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! export_variable {
-    ([$S:tt] $I:ident [$($A:tt)*] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+macro_rules! eval_const_binding {
+    ($T:tt $S:tt [#[macro_export] $($R:tt)*] $CA:tt [$($MA:tt)*] $I:ident $Y:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_const_binding!($T $S [$($R)*] $CA [$($MA)* #[macro_export]] $I $Y $E $N $P $V $);
+    };
+    ($T:tt $S:tt [#[$($A:tt)*] $($R:tt)*] [$($CA:tt)*] $MA:tt $I:ident $Y:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_const_binding!($T $S [$($R)*] [$($CA)* #[$($A)*]] $MA $I $Y $E $N $P $V $);
+    };
+    ({ ; $($T:tt)* } $S:tt [] [$($CA:tt)*] $MA:tt $I:ident [$Y:ty] [$($E:tt)*] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $($CA)* $($E)* const $I: $Y = $S;
+        $crate::utils::escape_repetitions!([$S] [] [$DD] ($crate::export_constant; $I $MA [$($E)*] [$DD:tt] $));
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_append_name {
+    ([$($R:tt)*] [$I:ident] $A:tt $E:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $($R)*!($T () ($crate::eval_append; $I $A $E $N) $P $V $);
+    };
+    ($R:tt [$_:ident $($I:ident)+] $A:tt $E:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_append_name!($R [$($I)*] $A $E $T $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_append {
+    ({ { $($G:tt)* }; $($T:tt)* } { $($R:tt)* } $I:ident $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::escape_repetitions!([{ $($R)* $($G)* }] [] [$DD] ($crate::eval_append_define; $I $A $E [$DD:tt] [{ $($T)* } $N $P $V] $));
+    };
+    ({ { $($G:tt)* }; $($T:tt)* } [$($R:tt)*] $I:ident $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::escape_repetitions!([[$($R)* $($G)*]] [] [$DD] ($crate::eval_append_define; $I $A $E [$DD:tt] [{ $($T)* } $N $P $V] $));
+    };
+    ({ { $($G:tt)* }; $($T:tt)* } ($($R:tt)*) $I:ident $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::escape_repetitions!([($($R)* $($G)*)] [] [$DD] ($crate::eval_append_define; $I $A $E [$DD:tt] [{ $($T)* } $N $P $V] $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_append_define {
+    // the rest of the block needs to be expanded from within the expansion that redefines the macro
+    ([$S:tt] $I:ident [$($A:tt)*] [$([$($E:tt)*])*] [$($M:tt)+] [$T:tt $N:tt $P:tt $V:tt] $D:tt) => {
         $($A)*
         macro_rules! $I {
             ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
@@ -246,499 +684,2403 @@ macro_rules! export_variable {
                 $I!{@unescape $}
             };
         }
-        $($E)* use $I;
+        $($($E)* use $I;)*
+        $crate::eval::block!($T () $N $P $V $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! export_function {
-    ([$S:tt] $I:ident [$($A:tt)*] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
-        $($A)*
-        macro_rules! $I {
-            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
-                $FF!($TT $S $D($CC)* $PP $VV $);
-            };
-            (@unescape $RR:tt $($M)*) => {
-                $crate::eval_call!({} $S $RR ($crate::eval::stop;) [] [] $);
+macro_rules! eval_fn_guard {
+    // the guard ends at the first brace-enclosed group, which is the body of the function
+    ({ { $($B:tt)* } $($T:tt)* } [$($G:tt)*] [$($H:tt)*] $I:ident $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({
+            $($H)* {
+                if $($G)* {
+                    $($B)*
+                } else {
+                    $crate::eval_guard_failed($I $($G)*)
+                }
+            }
+            $($T)*
+        } $S $N $P $V $);
+    };
+    ({ $X:tt $($T:tt)* } [$($G:tt)*] $H:tt $I:ident $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_fn_guard!({ $($T)* } [$($G)* $X] $H $I $S $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_fn_capture {
+    ([] $B:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture_scanned!([] [] [] $K $);
+    };
+    ([$DD:tt __rukt_steps:tt $($P:tt)*] $B:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture!([$($P)*] $B $K $);
+    };
+    // the environment of the parent block comes first in nested blocks
+    ([[$($P:tt)*] $($Q:tt)*] $B:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture!([$($P)* $($Q)*] $B $K $);
+    };
+    ([$($DE:tt $E:ident : tt)*] [$($B:tt)*] $K:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$([=$B=])*] [] [] [] [] [] ($crate::eval_fn_capture_scanned; $K $) $);
+    };
+    // patterns from destructuring and function parameters can bind repetitions, capture everything
+    ($P:tt $B:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture_end!(@all $K $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_fn_capture_scanned {
+    // every identifier in the body is a candidate, and the names that don't refer to a variable are
+    // dropped afterwards, but anything else following a dollar sign can't be resolved by name
+    //
+    // the step counter of checked evaluation always follows the function
+    (
+        $R:tt
+        [$($W:ident)*]
+        []
+        ($G:tt $I:ident $RR:tt $B:tt $T:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*])
+        $D:tt
+    ) => {
+        macro_rules! __rukt_transcribe {
+            ([$($P)*] $D($D CC:tt)*) => {
+                $crate::eval_fn_capture_filter!([$(($D$W))*] $D($D CC)*);
             };
-            ($D($RR:tt)*) => {
-                $I!(@unescape ($D($RR)*) $);
+        }
+        __rukt_transcribe!([$($V)*] [$($W)*] [$DD __rukt_steps:tt] [$L] ($G $I $RR $B $T $N [$DD __rukt_steps:tt $($P)*] [$L $($V)*]) $);
+    };
+    ($R:tt [$($W:ident)*] [] ($G:tt $I:ident $RR:tt $B:tt $T:tt $N:tt $P:tt $V:tt) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $D($D CC:tt)*) => {
+                $crate::eval_fn_capture_filter!([$(($D$W))*] $D($D CC)*);
             };
         }
-        $($E)* use $I;
+        __rukt_transcribe!($V [$($W)*] [] [] ($G $I $RR $B $T $N $P $V) $);
+    };
+    ($R:tt $W:tt $X:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture_end!(@all $K $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! eval_use_import {
-    ($T:tt $S:tt [$I:ident] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
-        $crate::eval::block!($T () $N [$($P)* $D$I:tt] [$($V)* $S] $);
-    };
-    ($T:tt $S:tt [$_:ident $(::$I:ident)+] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_use_import!($T $S [$($I)::*] $N $P $V $);
+macro_rules! eval_fn_capture_end {
+    (@all ($G:tt $I:ident $R:tt $B:tt $T:tt $N:tt $P:tt $V:tt) $D:tt) => {
+        $crate::eval_fn_defined!($P $V ($G $I $R $B $T $N $P $V) $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! eval_if_statement {
-    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_if_statement_block!({ $($T)* } [$($A)* { $($B1)* }] $N $P $V $);
-    };
-    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_if_statement_block!({ $($T)* } [$($A)* { $($B2)* }] $N $P $V $);
+macro_rules! eval_fn_capture_filter {
+    // error messages can mention any variable
+    ([$X:tt $($Y:tt)*] [error $($G:ident)*] $P:tt $V:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture_end!(@all $K $);
     };
-    ({ { $($B:tt)* } else if $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_statement; [$($A)* { $($B)* }] $N)) $P $V $);
+    ([($X:tt) $($Y:tt)*] [$F:ident $($G:ident)*] [] [] $K:tt $D:tt) => {
+        $crate::eval_fn_capture_filter!([$($Y)*] [$($G)*] [$D$F:tt] [$X] $K $);
     };
-    ({ { $($B:tt)* } else if $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_statement; [$($A)*] $N)) $P $V $);
+    // variables mentioned more than once are only captured the first time
+    ([($X:tt) $($Y:tt)*] [$F:ident $($G:ident)*] [$($DP:tt $Q:ident : tt)*] [$($V:tt)*] $K:tt $D:tt) => {
+        macro_rules! __rukt_capture {
+            $(
+                ($Q $D($D CC:tt)*) => {
+                    $crate::eval_fn_capture_filter!($D($D CC)*);
+                };
+            )*
+            ($D FF:ident $D YY:tt $D GG:tt [$D($D PP:tt)*] [$D($D VV:tt)*] $D KK:tt $D DD:tt) => {
+                $crate::eval_fn_capture_filter!($D YY $D GG [$D($D PP)* $D DD $D FF:tt] [$D($D VV)* $X] $D KK $D DD);
+            };
+        }
+        __rukt_capture!($F [$($Y)*] [$($G)*] [$($DP $Q : tt)*] [$($V)*] $K $);
     };
-    ({ { $($B:tt)* } $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_if_statement_block!({ ; $($T)* } [$($A)* { $($B)* }] $N $P $V $);
+    // the name doesn't refer to a variable in scope, so it was transcribed as-is
+    ([$X:tt $($Y:tt)*] [$F:ident $($G:ident)*] $P:tt $V:tt $K:tt $D:tt) => {
+        $crate::eval_fn_capture_filter!([$($Y)*] [$($G)*] $P $V $K $);
     };
-    ({ { $($B:tt)* } $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_if_statement_block!({ ; $($T)* } [$($A)*] $N $P $V $);
+    ([] [] $P:tt $V:tt $K:tt $D:tt) => {
+        $crate::eval_fn_defined!($P $V $K $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! eval_if_statement_block {
-    ({} [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::block!($B () ($crate::eval::parent; {} $P $V $N) $P $V $);
-    };
-    ({ ; $($T:tt)* } [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::block!($B () ($crate::eval::stop;) $P $V $);
-        $crate::eval::block!({ $($T)* } () $N $P $V $);
+macro_rules! eval_fn_defined {
+    ([$($FP:tt)*] [$($FV:tt)*] ([] $I:ident $R:tt $B:tt $T:tt $N:tt [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        $crate::eval::block!($T () $N [$($P)* $D$I:tt] [$($V)* { fn $I $R [$($FP)*] [$($FV)*] $B }] $);
     };
-    ($T:tt [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::block!($B () ($crate::eval::stop;) $P $V $);
-        $crate::eval::block!($T () $N $P $V $);
+    ([$($FP:tt)*] [$($FV:tt)*] ([$X:ident $A:tt $E:tt] $I:ident $R:tt $B:tt $T:tt $N:tt [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        $crate::export_escape!([{ fn $I $R [$($FP)*] [$($FV)*] $B }] [] [$DD] ($crate::export_function; $X $A $E [$DD:tt] $));
+        $crate::eval::block!($T () $N [$($P)* $D$I:tt] [$($V)* { fn $I $R [$($FP)*] [$($FV)*] $B }] $);
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! eval_statement {
-    ({; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::block!({ $($T)* } () $N $P $V $);
+macro_rules! eval_guard_failed {
+    ({ ($I:ident $($G:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: guard of `", stringify!($I), "` failed: ", stringify!($($G)*)));
     };
-    ({} $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-        $F!({} $S $($C)* $P $V $);
+}
+
+#[cfg(feature = "strings")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_error {
+    ($M:literal [$($P:tt)*] $V:tt $D:tt) => {
+        $crate::eval_error_scan!([$($P)*] [] [] ($M [$($P)*] $V) $);
     };
 }
 
-/// Evaluate statements within blocks.
+// interpolating the variables requires splitting the message, which only a procedural macro can do
+#[cfg(not(feature = "strings"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_error {
+    ($M:literal $P:tt $V:tt $D:tt) => {
+        compile_error!($M);
+    };
+}
+
+#[doc(hidden)]
+pub use eval_error as error;
+
+#[cfg(feature = "strings")]
+#[doc(hidden)]
+pub use rukt_macros::eval_error_message as error_message;
+
+#[cfg(feature = "strings")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_error_scan {
+    // collect the variables bound outside of repetitions
+    ([($($G:tt)*) $($X:tt)*] [$($K:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_scan!([$($G)*] [[$($X)*] $($K)*] $R $C $);
+    };
+    ([[$($G:tt)*] $($X:tt)*] [$($K:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_scan!([$($G)*] [[$($X)*] $($K)*] $R $C $);
+    };
+    ([{$($G:tt)*} $($X:tt)*] [$($K:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_scan!([$($G)*] [[$($X)*] $($K)*] $R $C $);
+    };
+    ([$H:tt ($($G:tt)*) $($X:tt)*] $K:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_detect!([=$H=]
+            [[$($X)*] $K $R $C $]
+            [[($($G)*) $($X)*] $K $R $C $]
+        );
+    };
+    ([$H:tt $I:ident : $($X:tt)*] $K:tt [$($R:tt)*] $C:tt $D:tt) => {
+        $crate::eval_error_detect!([=$H=]
+            [[$($X)*] $K [$($R)* $I] $C $]
+            [[$I : $($X)*] $K [$($R)*] $C $]
+        );
+    };
+    ([$_:tt $($X:tt)*] $K:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_scan!([$($X)*] $K $R $C $);
+    };
+    ([] [$X:tt $($K:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_error_scan!($X [$($K)*] $R $C $);
+    };
+    ([] [] [$($I:ident)*] ($M:literal [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($($P)*) => {
+                $crate::eval::error_message!($M [$($I [$D$I])*]);
+            };
+        }
+        __rukt_transcribe!($($V)*);
+    };
+}
+
+#[cfg(feature = "strings")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_error_detect {
+    ([$(=)$+] [$($Y:tt)*] $Z:tt) => {
+        $crate::eval_error_scan!($($Y)*);
+    };
+    ([=$H:tt=] $Y:tt [$($Z:tt)*]) => {
+        $crate::eval_error_scan!($($Z)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_static_binding {
+    ($T:tt () $K:tt $I:ident $Y:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: the value of `static ", stringify!($I), "` is `()`, expected a static initializer"));
+    };
+    ($T:tt { fn $F:ident $($R:tt)* } $K:tt $I:ident $Y:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: the value of `static ", stringify!($I), "` is the function `", stringify!($F), "`, expected a static initializer"));
+    };
+    ($T:tt [$($X:tt)*] $K:tt $I:ident $Y:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_static_list!([$($X)*] [$($X)*] $T [$($X)*] $K $I $Y $N $P $V $);
+    };
+    ($T:tt $S:tt $K:tt $I:ident $Y:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_static_item!($S $T $S $K $I $Y $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_static_list {
+    // lists that already contain commas are kept as-is, otherwise the elements are separated with commas
+    ([, $($R:tt)*] $X:tt $($C:tt)*) => {
+        $crate::eval_static_item!($X $($C)*);
+    };
+    ([$_1:tt , $($R:tt)*] $X:tt $($C:tt)*) => {
+        $crate::eval_static_item!($X $($C)*);
+    };
+    ([$_1:tt $_2:tt , $($R:tt)*] $X:tt $($C:tt)*) => {
+        $crate::eval_static_item!($X $($C)*);
+    };
+    ([$_1:tt $_2:tt $_3:tt , $($R:tt)*] $X:tt $($C:tt)*) => {
+        $crate::eval_static_item!($X $($C)*);
+    };
+    ([$_1:tt $_2:tt $_3:tt $_4:tt $($R:tt)*] $X:tt $($C:tt)*) => {
+        $crate::eval_static_list!([$($R)*] $X $($C)*);
+    };
+    ([$($R:tt)*] [$($X:tt)*] $($C:tt)*) => {
+        $crate::eval_static_item!([$($X),*] $($C)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_static_item {
+    ($X:tt { ; $($T:tt)* } $S:tt [$($K:tt)*] $I:ident [$Y:ty] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $($K)* $I: $Y = $X;
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_binding_pub_pattern {
+    ({ ; $($T:tt)* } $S:tt $L:tt $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!([$L] [] [] [] ({ $($T)* } $S $L $A $E $N $P $V) $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_binding_pub_scan {
+    ([($($G:tt)*) $($X:tt)*] $K:tt $Q:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!([$($G)* $($X)*] $K $Q $R $C $);
+    };
+    ([[$($G:tt)*] $($X:tt)*] $K:tt $Q:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!([$($G)* $($X)*] $K $Q $R $C $);
+    };
+    ([{$($G:tt)*} $($X:tt)*] $K:tt $Q:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!([$($G)* $($X)*] $K $Q $R $C $);
+    };
+    ([$H:tt ($($G:tt)*) $($X:tt)*] [$($K:tt)*] [$($Q:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_detect!([=$H=]
+            [[$($G)*] [* $($K)*] [([$($X)*] [$($K)*]) $($Q)*] $R $C $]
+            [[($($G)*) $($X)*] [$($K)*] [$($Q)*] $R $C $]
+        );
+    };
+    ([$H:tt $I:ident : $($X:tt)*] $K:tt $Q:tt [$($R:tt)*] $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_detect!([=$H=]
+            [[$($X)*] $K $Q [$($R)* ($I $K [$D $I])] $C $]
+            [[$I : $($X)*] $K $Q [$($R)*] $C $]
+        );
+    };
+    ([$_:tt $($X:tt)*] $K:tt $Q:tt $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!([$($X)*] $K $Q $R $C $);
+    };
+    ([] $_:tt [($X:tt $K:tt) $($Q:tt)*] $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_scan!($X $K [$($Q)*] $R $C $);
+    };
+    ([] [] [] $R:tt $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_export!($R [] $C $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_binding_pub_detect {
+    ([$(=)$+] [$($Y:tt)*] $Z:tt) => {
+        $crate::eval_let_binding_pub_scan!($($Y)*);
+    };
+    ([=$H:tt=] $Y:tt [$($Z:tt)*]) => {
+        $crate::eval_let_binding_pub_scan!($($Z)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_let_binding_pub_export {
+    ([($I:ident [* $($K:tt)*] [$($X:tt)*]) $($R:tt)*] [$($W:tt)*] $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_export!([($I [$($K)*] [[$D($($X)*)*]]) $($R)*] [$($W)*] $C $);
+    };
+    ([($I:ident [] $X:tt) $($R:tt)*] [$($W:tt)*] $C:tt $D:tt) => {
+        $crate::eval_let_binding_pub_export!([$($R)*] [$($W)* ($I $X)] $C $);
+    };
+    ([] [$(($I:ident [$($X:tt)*]))*] ({ $($T:tt)* } $S:tt $L:tt $A:tt [in $G:ident $($E:tt)*] $N:tt [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($L) => {
+                $crate::export_group!($G [$($E)*] [$(([$($X)*] $I $A []))*] $);
+            };
+        }
+        __rukt_transcribe!($S);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $L] [$($V)* $S] $);
+    };
+    ([] [$(($I:ident [$($X:tt)*]))*] ({ $($T:tt)* } $S:tt $L:tt $A:tt $E:tt $N:tt [$($P:tt)*] [$($V:tt)*]) $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($L) => {
+                $(
+                    $crate::utils::escape_repetitions!([$($X)*] [] [$DD] ($crate::export_variable; $I $A $E [$DD:tt] $));
+                )*
+            };
+        }
+        __rukt_transcribe!($S);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $L] [$($V)* $S] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_variable {
+    ([$S:tt] $I:ident [$($A:tt)*] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+        $($A)*
+        macro_rules! $I {
+            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
+                $FF!($TT $S $D($CC)* $PP $VV $);
+            };
+            (@unescape $($M)*) => {
+                $S
+            };
+            () => {
+                $I!{@unescape $}
+            };
+        }
+        $($E)* use $I;
+    };
+    ([$S:tt] $I:ident as $X:ident [$($A:tt)*] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+        $($A)*
+        macro_rules! $I {
+            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
+                $FF!($TT $S $D($CC)* $PP $VV $);
+            };
+            (@unescape $($M)*) => {
+                $S
+            };
+            () => {
+                $I!{@unescape $}
+            };
+        }
+        $($E)* use $I as $X;
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_group {
+    // macros can only be re-exported outside of the crate with `#[macro_export]`
+    ($G:ident [pub] $X:tt $D:tt) => {
+        pub mod $G {
+            $crate::export_group!(@items $G [pub] $X $);
+        }
+    };
+    ($G:ident [$($E:tt)+] $X:tt $D:tt) => {
+        $($E)* mod $G {
+            $crate::export_group!(@items $G [pub(crate)] $X $);
+        }
+    };
+    (@items $G:ident $E:tt [$(($S:tt $I:ident $A:tt $K:tt))*] $D:tt) => {
+        $(
+            $crate::export_group_item!($G $S $I $A [] $K $E $);
+        )*
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_group_item {
+    // `#[macro_export]` moves the macro to the root of the crate, so variables that aren't renamed with `as` get a name derived from the group
+    ($G:ident $S:tt $I:ident [#[macro_export] $($A:tt)*] [$($R:tt)*] [] $E:tt $D:tt) => {
+        $crate::eval::export_group_name!([__rukt_ $G _ $I] ($crate::export_group_rename; $S $I [$($R)* #[macro_export] $($A)*] $E $));
+    };
+    ($G:ident $S:tt $I:ident [#[$($B:tt)*] $($A:tt)*] [$($R:tt)*] $K:tt $E:tt $D:tt) => {
+        $crate::export_group_item!($G $S $I [$($A)*] [$($R)* #[$($B)*]] $K $E $);
+    };
+    ($G:ident $S:tt $I:ident [] $R:tt $K:tt $E:tt $D:tt) => {
+        $crate::export_escape!($S [] [$DD] ($crate::export_variable; $I $R $E [$DD:tt] $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_group_rename {
+    ($X:ident $S:tt $I:ident $A:tt $E:tt $D:tt) => {
+        $crate::export_escape!($S [] [$DD] ($crate::export_variable; $X as $I $A $E [$DD:tt] $));
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+pub use rukt_macros::utils_concat_idents as export_group_name;
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_group_name_disabled {
+    ([__rukt_ $G:ident _ $I:ident] $($T:tt)*) => {
+        compile_error!(concat!(
+            "rukt: `#[macro_export]` on `", stringify!($I), "` in group `", stringify!($G),
+            "` requires the `gensym` feature of the `rukt` crate, or a name that's unique across the crate with `as`"
+        ));
+    };
+}
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+pub use export_group_name_disabled as export_group_name;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_constant {
+    // the constant and the macro can't share a `use` declaration
+    ([$S:tt] $I:ident [#[macro_export]] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+        #[macro_export]
+        macro_rules! $I {
+            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
+                $FF!($TT $S $D($CC)* $PP $VV $);
+            };
+        }
+    };
+    ([$S:tt] $I:ident [] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+        macro_rules! __rukt_constant {
+            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
+                $FF!($TT $S $D($CC)* $PP $VV $);
+            };
+        }
+        $($E)* use __rukt_constant as $I;
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_escape {
+    // same as `escape_repetitions`, but `crate::` paths would also resolve to the invoking crate
+    ([$($T:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::utils_scan!(@paths [$([=$T=])*] [] $R [] [] $E ($crate::export_escaped; $N) $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_escaped {
+    ($R:tt $W:tt $X:tt ($F:path; $($C:tt)*)) => {
+        $F!($R $($C)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! export_function {
+    ([$S:tt] $I:ident [$($A:tt)*] [$($E:tt)+] [$($M:tt)+] $D:tt) => {
+        $($A)*
+        macro_rules! $I {
+            ($TT:tt $SS:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt $($M)*) => {
+                $FF!($TT $S $D($CC)* $PP $VV $);
+            };
+            (@unescape $RR:tt $($M)*) => {
+                $crate::eval_call!({} $S $RR ($crate::eval::stop;) [] [] $);
+            };
+            (@expr $D($RR:tt)*) => {
+                {
+                    macro_rules! __rukt_unescape {
+                        ($D II:ident $($M)*) => {
+                            $crate::eval_call!({} $S ($D($RR)*) ($crate::eval::emit; $D II) [] [] $);
+                        };
+                    }
+                    __rukt_unescape!(__rukt_value $);
+                    __rukt_value
+                }
+            };
+            ($D($RR:tt)*) => {
+                $I!(@unescape ($D($RR)*) $);
+            };
+        }
+        $($E)* use $I;
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_import {
+    // consecutive imports skip the block evaluator to save recursion depth
+    ({ use $($J:ident)::+; $($T:tt)* } $S:tt [$I:ident] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $($J)::*!({ $($T)* } () ($crate::eval_use_import; [$($J)::*] $N) [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+    ({ use $($J:ident)::+ as $A:ident; $($T:tt)* } $S:tt [$I:ident] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $($J)::*!({ $($T)* } () ($crate::eval_use_import; [$A] $N) [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+    ($T:tt $S:tt [$I:ident] $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!($T () $N [$($P)* $D$I:tt] [$($V)* $S] $);
+    };
+    ($T:tt $S:tt [$_:ident $(::$I:ident)+] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_import!($T $S [$($I)::*] $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_import_pub {
+    // functions are re-exported with the same forwarding macro as the original definition
+    ({ ; $($T:tt)* } { fn $($F:tt)* } [$I:ident] $A:tt $E:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::export_escape!([{ fn $($F)* }] [] [$DD] ($crate::export_function; $I $A $E [$DD:tt] $));
+        $crate::eval_define!($I [$($P)*] [$($V)*] $);
+        $crate::eval::block!({ $($T)* } () $N [$($P)* $D$I:tt] [$($V)* { fn $($F)* }] $);
+    };
+    ($T:tt $S:tt [$I:ident] $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_let_binding_pub!($T $S $I $A $E $N $P $V $);
+    };
+    ($T:tt $S:tt [$_:ident $(::$I:ident)+] $A:tt $E:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_import_pub!($T $S [$($I)::*] $A $E $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_group {
+    ([$($R:tt)*] { $($I:ident)::+ as $A:ident $(, $($G:tt)*)? } [$($U:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_group!([$($R)*] { $($($G)*)* } [$($U)* use $($R)* $($I)::* as $A;] $T $N $P $V $);
+    };
+    ([$($R:tt)*] { $($I:ident)::+ $(, $($G:tt)*)? } [$($U:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_group!([$($R)*] { $($($G)*)* } [$($U)* use $($R)* $($I)::*;] $T $N $P $V $);
+    };
+    ([$($R:tt)*] { $($I:ident::)* { $($H:tt)* } $(, $($G:tt)*)? } [$($U:tt)*] $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_group!([$($R)*] { $($($G)*)* } [$($U)* use $($R)* $($I::)* { $($H)* };] $T $N $P $V $);
+    };
+    ($R:tt {} [$($U:tt)*] { $($T:tt)* } $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($U)* $($T)* } () $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_glob {
+    ($T:tt $S:tt [$($R:tt)*] [$I:ident $($L:ident)+] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_glob!($T $S [$($R)* $I::] [$($L)*] $N $P $V $);
+    };
+    // the names need to be made visible from the block before importing them
+    ($T:tt { $($G:ident),* $(,)? } $R:tt [$I:ident] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::use_glob_respan!([$I] [$($G)*] ($crate::eval_use_glob_respanned; $R $T $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_glob_respanned {
+    ([$($G:ident)*] $R:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_use_group!($R { $($G),* } [] $T $N $P $V $);
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+pub use rukt_macros::utils_respan as use_glob_respan;
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_glob_select {
+    // without `respan`, the names come from the identifiers mentioned in the rest of the block
+    ($I:tt $G:tt ($F:path; $R:tt { $($T:tt)* } $($C:tt)*)) => {
+        $crate::utils_scan!(@idents [$([=$T=])*] [] [] [] [] [] ($crate::eval_use_glob_scanned; $G ($F; $R { $($T)* } $($C)*) $) $);
+    };
+}
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+pub use eval_use_glob_select as use_glob_respan;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_glob_scanned {
+    // literal identifiers in a matcher ignore hygiene, so the names listed in the group pick the
+    // identifiers with the same name from the block
+    ($R:tt [$($W:ident)*] $X:tt [$($G:ident)*] $K:tt $D:tt) => {
+        macro_rules! __rukt_glob {
+            $(
+                ([($G $D UU:ident) $D($D WW:tt)*] [$D($D MM:tt)*] $D KK:tt $D DD:tt) => {
+                    __rukt_glob!([$D($D WW)*] [$D($D MM)* $D UU] $D KK $D DD);
+                };
+            )*
+            ([$D XX:tt $D($D WW:tt)*] $D MM:tt $D KK:tt $D DD:tt) => {
+                __rukt_glob!([$D($D WW)*] $D MM $D KK $D DD);
+            };
+            ([] $D MM:tt $D KK:tt $D DD:tt) => {
+                $crate::eval_use_glob_unique!($D MM [] $D KK $D DD);
+            };
+        }
+        __rukt_glob!([$(($W $W))*] [] $K $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_use_glob_unique {
+    // names mentioned more than once are only imported the first time
+    ([$U:ident $($M:ident)*] [$($S:ident)*] $K:tt $D:tt) => {
+        macro_rules! __rukt_glob {
+            $(
+                ($S $D($D CC:tt)*) => {
+                    $crate::eval_use_glob_unique!($D($D CC)*);
+                };
+            )*
+            ($D UU:ident [$D($D MM:tt)*] [$D($D SS:tt)*] $D($D CC:tt)*) => {
+                $crate::eval_use_glob_unique!([$D($D MM)*] [$D($D SS)* $D UU] $D($D CC)*);
+            };
+        }
+        __rukt_glob!($U [$($M)*] [$($S)*] $K $);
+    };
+    ([] $S:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!($S $($C)*);
+    };
+}
+
+#[cfg(not(any(feature = "proc", feature = "nightly-metavar-expr")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_expand_escape {
+    // wrapping the tokens makes it possible to recognize dollar signs in a single step
+    ([$($B:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$B=])*] $R $E $N);
+    };
+}
+
+#[cfg(not(any(feature = "proc", feature = "nightly-metavar-expr")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_expand_escape_walk {
+    // each step copies the tokens up to the first double dollar sign or group among the next 16
+    ([[$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $($E)*] [$($E)*] $N);
+    };
+    ([[=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)*] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)*] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)*] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=$X:tt=] [$(=)$+] [$(=)$+] $($T:tt)*] [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $X $($E)*] [$($E)*] $N);
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=$X:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $X] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=$X:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $X] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=$X:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$([=$G=])*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $X] $E ($crate::eval_expand_escape_walk) $N));
+    };
+    ([[=$A:tt=] [=$B:tt=] [=$C:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$M:tt=] [=$O:tt=] [=$Q:tt=] [=$S:tt=] [=$U:tt=] [=$W:tt=] [=$X:tt=] [=$Y:tt=] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::eval_expand_escape_walk!([$($T)*] [$($R)* $A $B $C $H $I $J $K $L $M $O $Q $S $U $W $X $Y] $E $N);
+    };
+    ([$([=$A:tt=])*] [$($R:tt)*] $E:tt ($F:path; $($C:tt)*)) => {
+        $F!([$($R)* $($A)*] $($C)*);
+    };
+}
+
+#[cfg(not(any(feature = "proc", feature = "nightly-metavar-expr")))]
+#[doc(hidden)]
+pub use eval_expand_escape as expand_escape;
+
+#[cfg(all(feature = "proc", not(feature = "nightly-metavar-expr")))]
+#[doc(hidden)]
+pub use rukt_macros::eval_expand_escape as expand_escape;
+
+// transcribers understand the double dollar sign natively with `macro_metavar_expr`
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_expand_escape {
+    ([$($B:tt)*] [$($R:tt)*] $E:tt ($F:path; $($C:tt)*)) => {
+        $F!([$($R)* $($B)*] $($C)*);
+    };
+}
+
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+pub use eval_expand_escape as expand_escape;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_expand_escaped {
+    ([$($B:tt)*] $P:tt $V:tt [$($M:tt)+]) => {
+        macro_rules! __rukt_transcribe {
+            ($P $($M)*) => {
+                $($B)*
+            };
+        }
+        __rukt_transcribe!($V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_if_statement {
+    // a trailing `if` sets up the nested block right away, its value is the value of the block
+    (
+        { { $($B1:tt)* } else { $($B2:tt)* } }
+        true
+        []
+        $N:tt
+        [$DD:tt __rukt_steps:tt $($P:tt)*]
+        [$L:tt $($V:tt)*]
+        $D:tt
+    ) => {
+        $crate::eval::block!({ $($B1)* } () ($crate::eval::parent; {} $N) [$DD __rukt_steps:tt [$($P)*]] [$L [$($V)*]] $);
+    };
+    (
+        { { $($B1:tt)* } else { $($B2:tt)* } }
+        false
+        []
+        $N:tt
+        [$DD:tt __rukt_steps:tt $($P:tt)*]
+        [$L:tt $($V:tt)*]
+        $D:tt
+    ) => {
+        $crate::eval::block!({ $($B2)* } () ($crate::eval::parent; {} $N) [$DD __rukt_steps:tt [$($P)*]] [$L [$($V)*]] $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } } true [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($B1)* } () ($crate::eval::parent; {} $N) [$P] [$V] $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } } false [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($B2)* } () ($crate::eval::parent; {} $N) [$P] [$V] $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_if_statement_block!({ $($T)* } [$($A)* { $($B1)* }] $N $P $V $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_if_statement_block!({ $($T)* } [$($A)* { $($B2)* }] $N $P $V $);
+    };
+    ({ { $($B:tt)* } else if $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_statement; [$($A)* { $($B)* }] $N)) $P $V $);
+    };
+    ({ { $($B:tt)* } else if $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_statement; [$($A)*] $N)) $P $V $);
+    };
+    ({ { $($B:tt)* } $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_if_statement_block!({ ; $($T)* } [$($A)* { $($B)* }] $N $P $V $);
+    };
+    ({ { $($B:tt)* } $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_if_statement_block!({ ; $($T)* } [$($A)*] $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_if_statement_block {
+    ({ ; $($T:tt)* } [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    ($T:tt [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($T () $N $P $V $);
+    };
+    ({} [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_nested_block!($B {} $N $P $V $);
+    };
+    ({ ; $($T:tt)* } [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::stop;) $P $V $);
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    ($T:tt [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::stop;) $P $V $);
+        $crate::eval::block!($T () $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_for_loop {
+    ({ $B:tt $($T:tt)* } [$($X:tt)*] $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_for_loop!({ $B $($T)* } ($($X)*) $L $N $P $V $);
+    };
+    ({ $B:tt $($T:tt)* } ($($X:tt)*) [index $I:ident $J:ident] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::integers!(($crate::eval_for_index; @start [$($X)*] [$I $J] $B { $($T)* } $N $P $V $));
+    };
+    ({ $B:tt $($T:tt)* } ($($X:tt)*) $L:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $(
+            $crate::eval_for_iteration!($B [$L] [$X] $P $V $);
+        )*
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_for_iteration {
+    ($B:tt [$I:ident] [$X:tt] [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::stop;) [$($P)* $D$I:tt] [$($V)* $X] $);
+    };
+    ($B:tt [$L:tt] [$X:tt] [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::stop;) [$($P)* $L] [$($V)* $X] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_for_index_detect {
+    ([$(=)$+] [$(=)$+] [$I:ident $J:ident] $L:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!($T () ($crate::eval::operator; [] ($crate::eval_for_loop; [index $I $J] $N)) $P $V $);
+    };
+    ([=$Q1:tt=] [=$Q2:tt=] $R:tt $L:tt { $($T:tt)* } $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_for_loop; $L $N)) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_for_index {
+    ([$([$($K:tt)*])*] @start $X:tt $R:tt $B:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_for_index!($X [$($($K)*)*] $R $B $T $N $P $V $);
+    };
+    ([$X:tt $($XR:tt)*] [$K:tt $($KR:tt)*] [$I:ident $J:ident] $B:tt $T:tt $N:tt [$($P:tt)*] [$($V:tt)*] $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::stop;) [$($P)* $D$I:tt $D$J:tt] [$($V)* $K $X] $);
+        $crate::eval_for_index!([$($XR)*] [$($KR)*] [$I $J] $B $T $N [$($P)*] [$($V)*] $);
+    };
+    ([] $K:tt $R:tt $B:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($T () $N $P $V $);
+    };
+    ($X:tt [] $R:tt $B:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: can't iterate with an index over more than 256 elements");
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_statement {
+    ({; $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!({ $($T)* } () $N $P $V $);
+    };
+    ({} $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({} $S $($C)* $P $V $);
+    };
+}
+
+/// Evaluate statements within blocks.
+///
+/// Rukt blocks can contain the following statements:
+///
+/// - [Expression statements](#expression-statements)
+/// - [Let bindings](#let-bindings)
+/// - [Expand statements](#expand-statements)
+/// - [Error statements](#error-statements)
+/// - [Warn statements](#warn-statements)
+/// - [If statements](#if-statements)
+/// - [For loops](#for-loops)
+/// - [Function definitions](#function-definitions)
+/// - [Exports](#exports)
+/// - [Imports](#imports)
+/// - [Re-exports](#re-exports)
+/// - [Function exports](#function-exports)
+/// - [Group exports](#group-exports)
+/// - [Constant exports](#constant-exports)
+/// - [Static items](#static-items)
+/// - [Registries](#registries)
+///
+/// # Expression statements
+///
+/// Just like in Rust, expressions can appear as top-level statements, followed
+/// by a semicolon `;`.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     42;
+///     ["hello", "world"];
+/// }
+/// ```
+///
+/// When the current block is evaluated in an expression context, the last
+/// expression in the block will be used as the result of the overall block if
+/// the expression is not followed by a semicolon `;`.
+///
+/// Stray semicolons are ignored, and an empty block doesn't do anything.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {}
+/// rukt! {
+///     ;
+///     let value = 42;;
+///     expand {}
+/// }
+/// ```
+///
+/// # Let bindings
+///
+/// They mirror Rust's own `let` bindings. They allow you bind the result of an
+/// [`expression`] to a variable.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let message = "hello";
+/// }
+/// ```
+///
+/// Note that unlike in Rust, you can't implicitly shadow a previous variable
+/// with the same name. The same goes for function names and `use` imports.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let message = "hello";
+///     let message = "world"; // error: variable `message` is already defined in this scope
+///     let _ = message;
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let not = true;
+///     use rukt::builtins::not; // error: variable `not` is already defined in this scope
+/// }
+/// ```
+///
+/// There's also no `let mut`, all variables are immutable.
+///
+/// Using an underscore `_` as the variable name will explicitly discard the
+/// result of the expression.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = "hello";
+/// }
+/// ```
+///
+/// The left side can also be a pattern for destructuring the value specified as
+/// a delimiter-enclosed
+/// [`macro_rules`](https://doc.rust-lang.org/reference/macros-by-example.html)
+/// matchers. This is particularly useful for binding [repeated
+/// fragments](https://veykril.github.io/tlborm/decl-macros/macros-methodical.html#repetitions).
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let {$($name:ident: $operator:tt,)*} = {
+///         add: +,
+///         sub: -,
+///         mul: *,
+///         div: /,
+///     };
+/// }
+/// ```
+///
+/// If the value doesn't match the pattern, the error shows both of them.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let ($name:ident: $ty:ty) = (1 2); // error: value `(1 2)` does not match pattern `($name:ident: $ty:ty)`
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let [$($x:ident),*] = [a, 1]; // error: value `[a, 1]` does not match pattern `[$($x:ident),*]`
+/// }
+/// ```
+///
+/// Patterns can also contain literal tokens, which the value needs to match
+/// exactly. This makes it possible to validate the shape of a value while
+/// destructuring it, and a single literal token on its own only validates the
+/// value without binding anything.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let [ok $x:tt] = [ok 5];
+///     let 5 = x;
+///     expand {
+///         assert_eq!($x, 5);
+///     }
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let [ok $x:tt] = [no 5]; // error: value `[no 5]` does not match pattern `[ok $x:tt]`
+/// }
+/// ```
+///
+/// The pattern can end with `..$name` to bind the remaining tokens as a
+/// bracketed `[]` group. Unlike a repetition, the rest is a regular value that
+/// can be passed around and inspected with [`builtins`](crate::builtins).
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let [$first:tt ..$rest] = [1 2 3 4];
+///     let [$last:tt ..$empty] = [5];
+///     expand {
+///         assert_eq!(stringify!($first $rest $empty), "1 [2 3 4] []");
+///     }
+/// }
+/// ```
+///
+/// Note that depending on the fragment specifier you might not be able to
+/// inspect the tokens further. You can usually stick to `tt` and `ident`. See
+/// [forwarding a matched
+/// fragment](https://doc.rust-lang.org/stable/reference/macros-by-example.html#forwarding-a-matched-fragment).
+///
+/// A comma-separated list of names in parentheses `()` binds each element of a
+/// parenthesized group to the name at the same position. This is the
+/// conventional way to return several values from a
+/// [function](#function-definitions).
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     fn split($first:tt $($rest:tt)*) {
+///         ($first [$($rest)*])
+///     }
+///     let (head, tail) = split(1 2 3);
+///     expand {
+///         assert_eq!(stringify!($head $tail), "1 [2 3]");
+///     }
+/// }
+/// ```
+///
+/// The group must have exactly as many elements as there are names.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let (a, b) = (1 2 3); // error: rukt: value `(1 2 3)` doesn't have the same number of elements as `(a, b)`
+/// }
+/// ```
+///
+/// A binding can end with `assert` followed by a condition. The expression is
+/// evaluated first, then its value is bound to the pattern, and finally the
+/// condition is evaluated with the new variables in scope. When the condition
+/// is `true`, the block carries on with the bindings. When it's `false`, the
+/// block stops with an error that shows the condition and the bound value.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let count = 3 assert count > 0;
+///     let [$($item:ident)*] = [a b c] assert [$($item)*] == [a b c];
+///     expand {
+///         assert_eq!(stringify!($($item)*), "a b c");
+///     }
+/// }
+/// ```
+///
+/// Since the pattern is matched before the condition, a value that doesn't
+/// match the pattern reports the usual error instead. Any other result than
+/// `true` or `false` is an error as well.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let count = 0 assert count > 0; // error: rukt: assertion `count > 0` failed for `let count`
+/// }
+/// ```
+///
+/// # Expand statements
+///
+/// The `expand` statement will substitute all variables accessible in the
+/// current scope in the given code block, and paste the resulting Rust code as
+/// part of the expansion of the [`rukt`](crate::rukt) macro. The expansion
+/// doesn't include the braces `{}` used to delimit the code block.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let message = "Expanded from Rukt!";
+///     expand {
+///         fn example() -> &'static str {
+///             $message
+///         }
+///     }
+/// }
+/// # assert_eq!(example(), "Expanded from Rukt!");
+/// ```
+///
+/// Variable substitutions in the code block rely on the standard `$variable`
+/// syntax handled by
+/// [`macro_rules`](https://doc.rust-lang.org/reference/macros-by-example.html#metavariables).
+///
+/// You can use a double dollar sign `$$` to emit a literal dollar sign `$`
+/// instead of substituting a variable. This makes it possible to expand code
+/// that contains its own `macro_rules` metavariables and repetitions.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let ($name:ident) = (add);
+///     expand {
+///         macro_rules! $name {
+///             ($$($$x:expr),*) => {
+///                 0 $$(+ $$x)*
+///             };
+///         }
+///     }
+/// }
+/// # fn main() {
+/// assert_eq!(add!(1, 2, 3), 6);
+/// # }
+/// ```
+///
+/// # Error statements
+///
+/// The `error` statement aborts compilation with the given message. With the
+/// `strings` feature, variables can be interpolated in the message with
+/// `{name}`, which is replaced by the stringified value of the variable.
+/// Without it, the message is reported as written.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["strings"] }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let token = [+];
+///     if token != [-] {
+///         error "unexpected token: {token}"; // error: unexpected token: [+]
+///     }
+/// }
+/// ```
+///
+/// Use `{{` and `}}` to include literal braces in the message. Only variables
+/// bound outside of repetitions can be interpolated.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let token = [-];
+///     if token != [-] {
+///         error "expected {{-}}, found {token}";
+///     }
+/// }
+/// ```
+///
+/// # Warn statements
+///
+/// The `warn` statement reports a warning with the given message and
+/// continues the evaluation. This is useful for flagging deprecated usage of
+/// exported Rukt functions or variables.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let legacy = true;
+///     if legacy {
+///         warn "the legacy mode is deprecated";
+///     }
+/// }
+/// ```
+///
+/// `macro_rules` can't emit warnings directly, so the statement expands to a
+/// use of a `#[deprecated]` item with the message as its note. The warning
+/// shows up as a regular `deprecated` lint, which means that it can be silenced
+/// with `#[allow(deprecated)]` on the surrounding module or item. Unlike with
+/// error statements, the message can't interpolate variables because the note
+/// of the `deprecated` attribute must be a string literal.
+///
+/// ```text
+/// warning: use of deprecated unit struct `_::rukt_warning`: the legacy mode is deprecated
+/// ```
+///
+/// Denying the lint turns the warning into an error.
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+/// # use rukt::rukt;
+/// rukt! {
+///     warn "the legacy mode is deprecated"; // error: use of deprecated unit struct `_::rukt_warning`
+/// }
+/// ```
+///
+/// # If statements
+///
+/// They're exactly the same as Rust's own `if` statements. You can use `if`
+/// statements to evaluate Rukt code conditionally.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = 0;
+///     if value == 0 {
+///         expand {
+///             compile_error!("invalid"); // error: invalid
+///         }
+///     }
+/// }
+/// ```
+///
+/// You can also use `if` statements in
+/// [expression](crate::eval::expression#if-expressions) contexts as long as
+/// they specify an explicit `else` branch.
+///
+/// # For loops
+///
+/// You can use `for` loops to evaluate Rukt code for each token tree in a
+/// bracketed `[...]` or parenthesized `(...)` list.
+///
+/// ```
+/// # use rukt::rukt;
+/// let mut names = Vec::new();
+/// rukt! {
+///     let items = [a b c];
+///     for item in items {
+///         expand {
+///             names.push(stringify!($item));
+///         }
+///     }
+/// }
+/// assert_eq!(names, ["a", "b", "c"]);
+/// ```
+///
+/// Like with [let bindings](#let-bindings), you can also use a pattern to
+/// destructure each element.
+///
+/// ```
+/// # use rukt::rukt;
+/// let mut fields = Vec::new();
+/// rukt! {
+///     for [$name:ident: $value:expr] in ([a: 1] [b: 2]) {
+///         expand {
+///             fields.push((stringify!($name), $value));
+///         }
+///     }
+/// }
+/// assert_eq!(fields, [("a", 1), ("b", 2)]);
+/// ```
+///
+/// Writing `($index, $item)` binds the zero-based index of each element
+/// alongside the element itself. The index is an unsuffixed decimal integer
+/// literal like `0`, `1` or `2`, so it can be used with `concat!` or as a
+/// tuple field, for example. Lists can't contain more than 256 elements when
+/// iterating with an index.
+///
+/// ```
+/// # use rukt::rukt;
+/// let mut names = Vec::new();
+/// rukt! {
+///     for ($i, $item) in [a b c] {
+///         expand {
+///             names.push(concat!("item_", $i, "_", stringify!($item)));
+///         }
+///     }
+/// }
+/// assert_eq!(names, ["item_0_a", "item_1_b", "item_2_c"]);
+/// ```
+///
+/// The body of the loop is evaluated separately for each element. Variables
+/// defined inside the loop are not visible after the loop.
+///
+/// # Function definitions
+///
+/// Just like in regular Rust, you can define functions with the `fn` keyword.
+/// Rukt functions use `macro_rules` patterns to match their arguments.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     fn hello($name:literal) {
+///         let message = { concat!("hello ", $name) };
+///         message
+///     }
+///     let result = hello("world");
+///     expand {
+///         assert_eq!($result, "hello world");
+///     }
+/// }
+/// ```
+///
+/// The last expression in the body will be returned if it's not followed by a
+/// semicolon `;`. Otherwise, or when the body is empty, the function will
+/// return the unit token `()` by default.
+///
+/// Functions return a single value, but returning a parenthesized group makes
+/// it possible to bind several names at once with a [tuple
+/// binding](#let-bindings) like `let (a, b) = f();`.
+///
+/// Rukt functions can be passed around as values and as arguments to other
+/// functions. Recursion is supported.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     fn apply($f:tt $($args:tt)*) {
+///         f($($args)*)
+///     }
+///     fn twice($arg:tt) {
+///         [$arg, $arg]
+///     }
+///     let result = apply($twice 42);
+///     expand {
+///         assert_eq!($result, [42, 42]);
+///     }
+/// }
+/// ```
+///
+/// Additionally, functions will capture the variables currently in scope at
+/// their definition. Only the variables mentioned in the body are kept, so that
+/// functions defined in large blocks stay cheap to pass around and call. When
+/// the scope contains destructured variables or the body uses repetitions,
+/// every variable is captured.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let a = 1;
+///     fn get_function($b:tt) {
+///         let c = 3;
+///         fn f() {
+///             [$a, $b, $c]
+///         }
+///         f
+///     }
+///     let result = get_function(2)();
+///     expand {
+///         assert_eq!($result, [1, 2, 3]);
+///     }
+/// }
+/// ```
+///
+/// Functions can have a guard introduced with `where`. The guard is an
+/// [`expression`] evaluated with the parameters in scope before the body. If
+/// it evaluates to `false`, the call fails with an error mentioning the guard.
+/// The guard ends at the first brace-enclosed group, which is the body of the
+/// function, so braces in the guard itself need to be wrapped in parentheses.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     fn half($x:tt) where x == 2 || x == 4 {
+///         ($x / 2)
+///     }
+///     let result = half(4);
+///     expand {
+///         assert_eq!($result, 2);
+///     }
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     fn half($x:tt) where x == 2 || x == 4 {
+///         ($x / 2)
+///     }
+///     let result = half(3); // error: guard of `half` failed: x == 2 || x == 4
+/// }
+/// ```
+///
+/// When the arguments don't match the parameters of the function, the error
+/// shows the expected pattern alongside the arguments that were supplied.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     fn add($a:literal $b:literal) {
+///         ($a + $b)
+///     }
+///     let result = add(1); // error: mismatched arguments for `add`
+/// }
+/// ```
+///
+/// Only functions can be called. Calling any other value is an error.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = 42;
+///     let result = value(); // error: cannot call non-function value `42`
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = [1 2 3];
+///     let result = value(); // error: cannot call non-function value `[1 2 3]`
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = { fn broken };
+///     let result = value(); // error: cannot call non-function value `{fn broken}`
+/// }
+/// ```
+///
+/// # Exports
+///
+/// By default, none of the variables created during the expansion of a
+/// [`rukt`](crate::rukt) block will be visible to the outside.
+///
+/// You can use the `pub` keyword with the `#[macro_export]` attribute to export
+/// variables and make them accessible from [`rukt`](crate::rukt) blocks in
+/// other crates.
+///
+/// ```
+/// # use rukt::rukt;
+/// // my_crate/src/lib.rs
+/// rukt! {
+///     #[macro_export]
+///     pub let values = {
+///         A: 1,
+///         B: 2,
+///         C: 3,
+///     };
+/// }
+/// ```
+/// ```
+/// # mod my_crate {
+/// #     use rukt::rukt;
+/// #     rukt! {
+/// #         pub(crate) let values = {
+/// #             A: 1,
+/// #             B: 2,
+/// #             C: 3,
+/// #         };
+/// #     }
+/// # }
+/// # use rukt::rukt;
+/// rukt! {
+///     let {$($name:ident: $value:expr,)*} = my_crate::values;
+///     expand {
+///         enum MyEnum {
+///             $($name = $value,)*
+///         }
+///     }
+/// }
+/// assert_eq!(MyEnum::A as u32, 1);
+/// assert_eq!(MyEnum::B as u32, 2);
+/// assert_eq!(MyEnum::C as u32, 3);
+/// ```
+///
+/// In addition to binding the variable in the current scope, the `let`
+/// statement will generate a [`builtin`](crate::builtins) that resolves to the
+/// assigned value.
+///
+/// You can make the variable accessible only to other [`rukt`](crate::rukt)
+/// blocks in your own crate with the usual `pub(...)` variants. Of course when
+/// the variable is not meant to be visible to other crates there's no need for
+/// `#[macro_export]`.
+///
+/// In regular Rust, `pub(self)` is equivalent to not using `pub` in the first
+/// place. In Rukt it can be used to signal that you want to export the variable
+/// as a builtin without extending its visibility beyond the current Rust scope.
+/// As mentioned earlier, Rukt variables are not exported by default, there's no
+/// trace of them in the surrounding Rust code unless you use the `pub` keyword.
+///
+/// Exported variables can also be used directly as macros in the surrounding
+/// Rust code.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     pub(self) let numbers = [1, 2, 3];
+/// }
+/// assert_eq!(numbers!(), [1, 2, 3]);
+/// ```
 ///
-/// Rukt blocks can contain the following statements:
+/// Exported bindings can also destructure the assigned value. Every variable
+/// bound by the pattern gets exported individually. Variables bound inside a
+/// repetition are exported as a bracketed list of the matched tokens, with one
+/// level of nesting per repetition.
 ///
-/// - [Expression statements](#expression-statements)
-/// - [Let bindings](#let-bindings)
-/// - [Expand statements](#expand-statements)
-/// - [If statements](#if-statements)
-/// - [Function definitions](#function-definitions)
-/// - [Exports](#exports)
-/// - [Imports](#imports)
-/// - [Function exports](#function-exports)
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     pub(self) let {default: $default:tt, $($name:ident: $value:expr,)*} = {
+///         default: 0,
+///         A: 1,
+///         B: 2,
+///     };
+/// }
+/// rukt! {
+///     use name;
+///     use default;
+///     expand {
+///         assert_eq!(stringify!($name), "[A B]");
+///         assert_eq!($default, 0);
+///     }
+/// }
+/// ```
 ///
-/// # Expression statements
+/// Wildcard patterns like `pub let _ = ...` don't bind anything and are
+/// rejected with a compile error.
 ///
-/// Just like in Rust, expressions can appear as top-level statements, followed
-/// by a semicolon `;`.
+/// The exported macro can be given a different name with `as`. The variable
+/// keeps its original name within the current [`rukt`](crate::rukt) block, but
+/// other blocks need to refer to it by its public name.
 ///
 /// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     42;
-///     ["hello", "world"];
+///     pub(crate) let table as MY_CRATE_TABLE = [1, 2, 3];
+///     expand {
+///         assert_eq!($table, [1, 2, 3]);
+///     }
+/// }
+/// rukt! {
+///     use MY_CRATE_TABLE;
+///     expand {
+///         assert_eq!($MY_CRATE_TABLE, [1, 2, 3]);
+///     }
 /// }
 /// ```
 ///
-/// When the current block is evaluated in an expression context, the last
-/// expression in the block will be used as the result of the overall block if
-/// the expression is not followed by a semicolon `;`.
-///
-/// # Let bindings
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     pub(crate) let table as MY_CRATE_TABLE = [1, 2, 3];
+/// }
+/// rukt! {
+///     use table; // error: cannot find macro `table` in this scope
+/// }
+/// ```
 ///
-/// They mirror Rust's own `let` bindings. They allow you bind the result of an
-/// [`expression`] to a variable.
+/// Since the exported macro is named after the variable, two
+/// [`rukt`](crate::rukt) blocks exporting the same name in the same module
+/// collide. Adding `in` followed by a group name exports the variable from a
+/// new module with that name instead, so other blocks import it through the
+/// group.
 ///
 /// ```
+/// # mod config {
+/// #     use rukt::rukt;
+/// rukt! {
+///     pub(crate) let value in defaults = [1 2];
+/// }
+/// rukt! {
+///     pub(crate) let value in overrides = [3];
+/// }
+/// # }
 /// # use rukt::rukt;
 /// rukt! {
-///     let message = "hello";
+///     use config::defaults::value as defaults;
+///     use config::overrides::value as overrides;
+///     expand {
+///         assert_eq!(stringify!($defaults $overrides), "[1 2] [3]");
+///     }
 /// }
 /// ```
 ///
-/// Note that unlike in Rust, you can't implicitly shadow a previous variable
-/// with the same name.
+/// The module gets the visibility of the statement. Each statement generates
+/// its own module, so using the same group twice in the same module is an
+/// error. To export several variables in the same group, bind them all with a
+/// single destructuring pattern.
 ///
-/// ```compile_fail
+/// ```
+/// # mod config {
+/// #     use rukt::rukt;
+/// rukt! {
+///     pub(crate) let {width: $width:tt, height: $height:tt,} in size = {
+///         width: 800,
+///         height: 600,
+///     };
+/// }
+/// # }
 /// # use rukt::rukt;
 /// rukt! {
-///     let message = "hello";
-///     let message = "world"; // error: duplicate matcher binding
-///     let _ = message;
+///     use config::size::{width, height};
+///     expand {
+///         assert_eq!([$width, $height], [800, 600]);
+///     }
 /// }
 /// ```
 ///
-/// There's also no `let mut`, all variables are immutable.
+/// Macros with the `#[macro_export]` attribute always end up at the root of
+/// the crate, regardless of the module they're defined in. With the `gensym`
+/// feature, variables exported in a group are defined at the root as
+/// `__rukt_<group>_<name>`, so `#[macro_export] pub let value in defaults`
+/// stays importable as `my_crate::defaults::value` even if other groups also
+/// export a `value`. The group names themselves still need to be unique
+/// across the crate. Without the `gensym` feature the derived name can't be
+/// generated, so the statement is an error unless the variable is renamed with
+/// `as` to a name that's unique across the crate, for example `pub let value
+/// as my_crate_defaults in defaults`. The variable is then available both as
+/// `my_crate::defaults::my_crate_defaults` and as `my_crate::my_crate_defaults`.
 ///
-/// Using an underscore `_` as the variable name will explicitly discard the
-/// result of the expression.
+/// # Imports
+///
+/// Rukt supports `use` statements as an alternative to `let` bindings for
+/// bringing exported variables into scope.
 ///
 /// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     let _ = "hello";
+///     pub(crate) let numbers = [1, 2, 3];
+/// }
+/// rukt! {
+///     use numbers;
+///     expand {
+///         assert_eq!($numbers, [1, 2, 3]);
+///     }
 /// }
 /// ```
 ///
-/// The left side can also be a pattern for destructuring the value specified as
-/// a delimiter-enclosed
-/// [`macro_rules`](https://doc.rust-lang.org/reference/macros-by-example.html)
-/// matchers. This is particularly useful for binding [repeated
-/// fragments](https://veykril.github.io/tlborm/decl-macros/macros-methodical.html#repetitions).
+/// While you can refer to exported variables by path in
+/// [expressions](expression), they must be brought into scope within the
+/// [`rukt`](crate::rukt) code block to allow substitution in token trees.
 ///
-/// ```
+/// ```compile_fail
 /// # use rukt::rukt;
 /// rukt! {
-///     let {$($name:ident: $operator:tt,)*} = {
-///         add: +,
-///         sub: -,
-///         mul: *,
-///         div: /,
-///     };
+///     pub(crate) let numbers = [1, 2, 3];
+/// }
+/// rukt! {
+///     expand {
+///         assert_eq!($numbers, [1, 2, 3]); // error: no rules expected the token `$`
+///     }
 /// }
 /// ```
 ///
-/// Note that depending on the fragment specifier you might not be able to
-/// inspect the tokens further. You can usually stick to `tt` and `ident`. See
-/// [forwarding a matched
-/// fragment](https://doc.rust-lang.org/stable/reference/macros-by-example.html#forwarding-a-matched-fragment).
+/// Rukt `use` statements also support the `as` keyword for bringing exported
+/// variables into scope under a different name.
 ///
-/// # Expand statements
+/// ```
+/// # mod path {
+/// #     pub mod to {
+/// #         use rukt::rukt;
+/// #         rukt! {
+/// #             pub(crate) let my_variable = 123;
+/// #         }
+/// #     }
+/// # }
+/// # use rukt::rukt;
+/// rukt! {
+///     use path::to::my_variable;
+///     use path::to::my_variable as alias;
+/// }
+/// ```
 ///
-/// The `expand` statement will substitute all variables accessible in the
-/// current scope in the given code block, and paste the resulting Rust code as
-/// part of the expansion of the [`rukt`](crate::rukt) macro. The expansion
-/// doesn't include the braces `{}` used to delimit the code block.
+/// Multiple variables exported from the same module can be imported at once by
+/// grouping them in braces `{}`. Groups can be nested and can also use the `as`
+/// keyword.
 ///
 /// ```
+/// # mod path {
+/// #     pub mod to {
+/// #         use rukt::rukt;
+/// #         rukt! {
+/// #             pub(crate) let a = 1;
+/// #             pub(crate) let b = 2;
+/// #         }
+/// #         pub mod nested {
+/// #             use rukt::rukt;
+/// #             rukt! {
+/// #                 pub(crate) let c = 3;
+/// #             }
+/// #         }
+/// #     }
+/// # }
 /// # use rukt::rukt;
 /// rukt! {
-///     let message = "Expanded from Rukt!";
+///     use path::to::{a, b as alias, nested::{c}};
 ///     expand {
-///         fn example() -> &'static str {
-///             $message
-///         }
+///         assert_eq!([$a, $alias, $c], [1, 2, 3]);
 ///     }
 /// }
-/// # assert_eq!(example(), "Expanded from Rukt!");
 /// ```
 ///
-/// Variable substitutions in the code block rely on the standard `$variable`
-/// syntax handled by
-/// [`macro_rules`](https://doc.rust-lang.org/reference/macros-by-example.html#metavariables).
+/// Note that both variants of the `use` statement are nothing more than a
+/// restricted version of `let` which only allow binding exported variables.
+/// They're functionally completely equivalent. Rukt `use` statements simply
+/// make it easier to identify imports at first glance.
 ///
-/// # If statements
+/// ```
+/// # mod path {
+/// #     pub mod to {
+/// #         use rukt::rukt;
+/// #         rukt! {
+/// #             pub(crate) let my_variable = 123;
+/// #         }
+/// #     }
+/// # }
+/// # use rukt::rukt;
+/// rukt! {
+///     let my_variable = path::to::my_variable;
+///     let alias = path::to::my_variable;
+/// }
+/// ```
 ///
-/// They're exactly the same as Rust's own `if` statements. You can use `if`
-/// statements to evaluate Rukt code conditionally.
+/// # Re-exports
 ///
-/// ```compile_fail
+/// Imported variables can be exported again with `pub use`. This binds the
+/// variable in the current scope and generates a new
+/// [`builtin`](crate::builtins) in the current module, exactly like `pub let`.
+///
+/// ```
+/// # mod internal {
+/// #     use rukt::rukt;
+/// #     rukt! {
+/// #         pub(crate) let opcodes = [add sub];
+/// #         pub(crate) let registers = [a b c];
+/// #     }
+/// # }
+/// mod facade {
+///     use rukt::rukt;
+///     rukt! {
+///         pub(crate) use crate::internal::opcodes;
+///         pub(crate) use crate::internal::registers as regs;
+///     }
+/// }
 /// # use rukt::rukt;
+/// # fn main() {
 /// rukt! {
-///     let value = 0;
-///     if value == 0 {
-///         expand {
-///             compile_error!("invalid"); // error: invalid
-///         }
+///     use facade::{opcodes, regs};
+///     expand {
+///         assert_eq!(stringify!($opcodes $regs), "[add sub] [a b c]");
 ///     }
 /// }
+/// # }
 /// ```
 ///
-/// You can also use `if` statements in
-/// [expression](crate::eval::expression#if-expressions) contexts as long as
-/// they specify an explicit `else` branch.
+/// Just like other exports, use the `#[macro_export]` attribute to make the
+/// re-exported variable accessible outside the crate.
 ///
-/// # Function definitions
+/// Re-exporting a [function](#function-exports) generates the same macro as the
+/// original `pub fn`, so the function can be called from
+/// [`rukt`](crate::rukt) blocks and invoked as a plain macro through the new
+/// path.
 ///
-/// Just like in regular Rust, you can define functions with the `fn` keyword.
-/// Rukt functions use `macro_rules` patterns to match their arguments.
+/// ```
+/// # mod internal {
+/// #     use rukt::rukt;
+/// #     rukt! {
+/// #         pub(crate) fn double($x:literal) {
+/// #             ($x * 2)
+/// #         }
+/// #     }
+/// # }
+/// mod facade {
+///     use rukt::rukt;
+///     rukt! {
+///         pub(crate) use crate::internal::double;
+///     }
+/// }
+/// # use rukt::rukt;
+/// # fn main() {
+/// rukt! {
+///     use facade::double;
+///     let value = double(21);
+///     expand {
+///         assert_eq!($value, 42);
+///     }
+/// }
+/// assert_eq!(facade::double!(@expr 4), 8);
+/// # }
+/// ```
+///
+/// # Function exports
+///
+/// Just like variables, you can export functions with the `pub` keyword.
 ///
 /// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     fn hello($name:literal) {
-///         let message = { concat!("hello ", $name) };
-///         message
+///     pub(self) fn foo() {
+///         "hello"
 ///     }
-///     let result = hello("world");
+/// }
+/// rukt! {
+///     let result = foo();
 ///     expand {
-///         assert_eq!($result, "hello world");
+///         assert_eq!($result, "hello");
 ///     }
 /// }
 /// ```
 ///
-/// The last expression in the body will be returned if it's not followed by a
-/// semicolon `;`. Otherwise, the function will return the unit token `()` by
-/// default.
+/// To make the function accessible outside the crate, make sure to use the
+/// `#[macro_export]` attribute.
 ///
-/// Rukt functions can be passed around as values and as arguments to other
-/// functions. Recursion is supported.
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     #[macro_export]
+///     pub fn foo() {
+///         "hello"
+///     }
+/// }
+/// ```
+///
+/// Like variables, functions can be exported under a different name with `as`.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     pub(self) fn greet as my_crate_greet() {
+///         "hello"
+///     }
+///     let result = greet();
+///     expand {
+///         assert_eq!($result, "hello");
+///     }
+/// }
+/// rukt! {
+///     let result = my_crate_greet();
+///     expand {
+///         assert_eq!($result, "hello");
+///     }
+/// }
+/// ```
+///
+/// Exported functions and variables can be used from other crates. Paths
+/// starting with `crate::` in exported values are rewritten to `$crate::`, so
+/// they keep referring to the exporting crate. Function bodies should refer to
+/// other exports and builtins through such paths instead of bare names, which
+/// are resolved in the crate using the export.
 ///
 /// ```
 /// # use rukt::rukt;
-/// rukt! {
-///     fn apply($f:tt $($args:tt)*) {
-///         f($($args)*)
+/// mod helpers {
+///     # use rukt::rukt;
+///     rukt! {
+///         pub(crate) fn double($x:tt) {
+///             [$x $x]
+///         }
 ///     }
-///     fn twice($arg:tt) {
-///         [$arg, $arg]
+/// }
+/// rukt! {
+///     pub(crate) fn quad($x:tt) {
+///         let pair = crate::helpers::double($x);
+///         crate::helpers::double($pair)
 ///     }
-///     let result = apply($twice 42);
+/// }
+/// # fn main() {
+/// rukt! {
+///     let value = quad(1);
 ///     expand {
-///         assert_eq!($result, [42, 42]);
+///         assert_eq!(stringify!($value), "[[1 1] [1 1]]");
 ///     }
 /// }
+/// # }
 /// ```
 ///
-/// Additionally, functions will capture all the variables currently in scope at
-/// their definition.
+/// Note that exported functions can also be invoked directly as macros outside
+/// of [`rukt`](crate::rukt) blocks.
 ///
 /// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     let a = 1;
-///     fn get_function($b:tt) {
-///         let c = 3;
-///         fn f() {
-///             [$a, $b, $c]
+///     pub(crate) fn generate_message($name:literal) {
+///         expand {
+///             const MESSAGE: &str = concat!("hello ", $name);
 ///         }
-///         f
-///     }
-///     let result = get_function(2)();
-///     expand {
-///         assert_eq!($result, [1, 2, 3]);
 ///     }
 /// }
+/// generate_message!("world");
+/// assert_eq!(MESSAGE, "hello world");
 /// ```
 ///
-/// # Exports
-///
-/// By default, none of the variables created during the expansion of a
-/// [`rukt`](crate::rukt) block will be visible to the outside.
-///
-/// You can use the `pub` keyword with the `#[macro_export]` attribute to export
-/// variables and make them accessible from [`rukt`](crate::rukt) blocks in
-/// other crates.
+/// To use the return value of an exported function in an expression context
+/// outside of [`rukt`](crate::rukt) blocks, invoke the macro with `@expr`.
 ///
 /// ```
 /// # use rukt::rukt;
-/// // my_crate/src/lib.rs
 /// rukt! {
-///     #[macro_export]
-///     pub let values = {
-///         A: 1,
-///         B: 2,
-///         C: 3,
-///     };
+///     pub(crate) fn double($x:tt) {
+///         ($x * 2)
+///     }
 /// }
+/// const VALUE: u32 = double!(@expr 21);
+/// assert_eq!(VALUE, 42);
 /// ```
+///
+/// The invocation expands to a block, so any item produced by `expand`
+/// statements in the function body is only visible within the expression.
+///
+/// # Group exports
+///
+/// You can use `pub group` to export an index of variables exported from the
+/// same module. The group can then be used to import all of them at once with a
+/// glob `use` statement.
+///
 /// ```
 /// # mod my_crate {
 /// #     use rukt::rukt;
-/// #     rukt! {
-/// #         pub(crate) let values = {
-/// #             A: 1,
-/// #             B: 2,
-/// #             C: 3,
-/// #         };
-/// #     }
+/// rukt! {
+///     pub(crate) let opcodes = [add sub];
+///     pub(crate) let registers = [a b c];
+///     pub(crate) group tables { opcodes, registers }
+/// }
 /// # }
+/// # fn main() {
 /// # use rukt::rukt;
 /// rukt! {
-///     let {$($name:ident: $value:expr,)*} = my_crate::values;
+///     use my_crate::tables::*;
 ///     expand {
-///         enum MyEnum {
-///             $($name = $value,)*
-///         }
+///         assert_eq!(stringify!($opcodes $registers), "[add sub] [a b c]");
 ///     }
 /// }
-/// assert_eq!(MyEnum::A as u32, 1);
-/// assert_eq!(MyEnum::B as u32, 2);
-/// assert_eq!(MyEnum::C as u32, 3);
+/// # }
 /// ```
 ///
-/// In addition to binding the variable in the current scope, the `let`
-/// statement will generate a [`builtin`](crate::builtins) that resolves to the
-/// assigned value.
-///
-/// You can make the variable accessible only to other [`rukt`](crate::rukt)
-/// blocks in your own crate with the usual `pub(...)` variants. Of course when
-/// the variable is not meant to be visible to other crates there's no need for
-/// `#[macro_export]`.
-///
-/// In regular Rust, `pub(self)` is equivalent to not using `pub` in the first
-/// place. In Rukt it can be used to signal that you want to export the variable
-/// as a builtin without extending its visibility beyond the current Rust scope.
-/// As mentioned earlier, Rukt variables are not exported by default, there's no
-/// trace of them in the surrounding Rust code unless you use the `pub` keyword.
+/// The group itself is an exported variable holding the names it lists. The
+/// glob `use` statement resolves the group, and then imports every name it
+/// lists from the module containing the group, as if they had been listed in a
+/// [grouped import](#imports).
 ///
-/// Exported variables can also be used directly as macros in the surrounding
-/// Rust code.
+/// The names listed in the group come from the expansion of the exporting
+/// block, so because of
+/// [hygiene](https://doc.rust-lang.org/reference/macros-by-example.html#hygiene)
+/// they wouldn't match the `$variable` substitutions written in your own code.
+/// With the `gensym` feature, the glob `use` statement gives them the span of the
+/// group name in the statement with `respan` before importing them. Otherwise,
+/// it scans the rest of the block and only imports the names that are mentioned
+/// there, using your own identifiers. This costs about one level of recursion
+/// for every identifier in the rest of the block, so enabling `gensym` is
+/// recommended for larger blocks.
 ///
-/// ```
-/// # use rukt::rukt;
-/// rukt! {
-///     pub(self) let numbers = [1, 2, 3];
-/// }
-/// assert_eq!(numbers!(), [1, 2, 3]);
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["gensym"] }
 /// ```
 ///
-/// # Imports
+/// Note that globs only work with groups exported through Rukt. Unlike in
+/// regular Rust, `use path::to::module::*` can't enumerate the variables
+/// exported from an arbitrary module.
 ///
-/// Rukt supports `use` statements as an alternative to `let` bindings for
-/// bringing exported variables into scope.
+/// # Constant exports
+///
+/// The `pub const` statement works like an exported [let binding](#exports),
+/// but also expands to a regular Rust constant holding the same value. This is
+/// convenient for values that need to be available both to other
+/// [`rukt`](crate::rukt) blocks and to ordinary Rust code.
 ///
 /// ```
-/// # use rukt::rukt;
+/// # mod my_crate {
+/// #     use rukt::rukt;
 /// rukt! {
-///     pub(crate) let numbers = [1, 2, 3];
+///     /// Maximum number of registers.
+///     pub(crate) const MAX_REGISTERS: usize = 16;
+///     expand {
+///         pub struct Registers([u8; $MAX_REGISTERS]);
+///     }
 /// }
+/// # }
+/// # use rukt::rukt;
+/// # use my_crate::MAX_REGISTERS;
+/// assert_eq!(MAX_REGISTERS, 16);
 /// rukt! {
-///     use numbers;
+///     use my_crate::MAX_REGISTERS;
+///     let double = { $MAX_REGISTERS * 2 };
 ///     expand {
-///         assert_eq!($numbers, [1, 2, 3]);
+///         assert_eq!($double, 32);
 ///     }
 /// }
 /// ```
 ///
-/// While you can refer to exported variables by path in
-/// [expressions](expression), they must be brought into scope within the
-/// [`rukt`](crate::rukt) code block to allow substitution in token trees.
+/// The visibility applies to both the constant and the exported variable. All
+/// the attributes apply to the constant, except for `#[macro_export]`, which
+/// applies to the exported variable. Note that with `#[macro_export]`, the
+/// variable is only available from the root of the crate.
 ///
-/// ```compile_fail
+/// Unlike variables exported with `pub let`, exported constants can't be
+/// invoked directly as macros in the surrounding Rust code. Use the constant
+/// itself instead.
+///
+/// # Static items
+///
+/// The `static` statement binds the result of an [`expression`] to a variable
+/// and expands to a regular Rust `static` item holding the same value. The
+/// visibility, `mut` and attributes apply to the static item. Unlike
+/// [constant exports](#constant-exports), nothing is exported to other
+/// [`rukt`](crate::rukt) blocks.
+///
+/// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     pub(crate) let numbers = [1, 2, 3];
-/// }
-/// rukt! {
+///     static TABLE: [u32; 3] = [1 2 3];
+///     let copy = TABLE;
 ///     expand {
-///         assert_eq!($numbers, [1, 2, 3]); // error: no rules expected the token `$`
+///         assert_eq!(TABLE, [1, 2, 3]);
+///         assert_eq!(stringify!($copy), "[1 2 3]");
 ///     }
 /// }
 /// ```
 ///
-/// Rukt `use` statements also support the `as` keyword for bringing exported
-/// variables into scope under a different name.
+/// Brackets without commas are turned into comma-separated arrays, so lists
+/// can be used directly as array initializers. Brackets that already contain
+/// commas are left as-is. The value is checked once evaluated, and evaluating
+/// to `()` or to a function is an error.
 ///
-/// ```
-/// # mod path {
-/// #     pub mod to {
-/// #         use rukt::rukt;
-/// #         rukt! {
-/// #             pub(crate) let my_variable = 123;
-/// #         }
-/// #     }
-/// # }
+/// ```compile_fail
 /// # use rukt::rukt;
 /// rukt! {
-///     use path::to::my_variable;
-///     use path::to::my_variable as alias;
+///     fn nothing() {}
+///     static EMPTY: u32 = nothing(); // error: the value of `static EMPTY` is `()`
 /// }
 /// ```
 ///
-/// Note that both variants of the `use` statement are nothing more than a
-/// restricted version of `let` which only allow binding exported variables.
-/// They're functionally completely equivalent. Rukt `use` statements simply
-/// make it easier to identify imports at first glance.
-///
-/// ```
-/// # mod path {
-/// #     pub mod to {
-/// #         use rukt::rukt;
-/// #         rukt! {
-/// #             pub(crate) let my_variable = 123;
-/// #         }
-/// #     }
-/// # }
+/// ```compile_fail
 /// # use rukt::rukt;
 /// rukt! {
-///     let my_variable = path::to::my_variable;
-///     let alias = path::to::my_variable;
+///     fn nothing() {}
+///     let callback = nothing;
+///     static CALLBACK: u32 = callback; // error: the value of `static CALLBACK` is the function `nothing`
 /// }
 /// ```
 ///
-/// # Function exports
+/// # Registries
 ///
-/// Just like variables, you can export functions with the `pub` keyword.
+/// Registries are exported variables that other [`rukt`](crate::rukt) blocks
+/// can contribute to. The `pub registry` statement exports an empty `{}`
+/// group, and the `append` statement reads the current value from the given
+/// path and redefines the variable in the current module with the new tokens
+/// added at the end.
 ///
 /// ```
-/// # use rukt::rukt;
-/// rukt! {
-///     pub(self) fn foo() {
-///         "hello"
+/// mod registry {
+///     rukt::rukt! {
+///         pub(crate) registry handlers;
 ///     }
 /// }
-/// rukt! {
-///     let result = foo();
+/// mod click {
+///     rukt::rukt! {
+///         pub(crate) append crate::registry::handlers {
+///             on_click => handle_click,
+///         };
+///     }
+/// }
+/// mod keyboard {
+///     rukt::rukt! {
+///         pub(crate) append crate::click::handlers {
+///             on_key_down => handle_key_down,
+///             on_key_up => handle_key_up,
+///         };
+///     }
+/// }
+/// # fn main() {
+/// rukt::rukt! {
+///     use keyboard::handlers;
+///     let {$($event:ident => $handler:ident,)*} = handlers;
 ///     expand {
-///         assert_eq!($result, "hello");
+///         let events = [$(stringify!($event)),*];
+///         assert_eq!(events, ["on_click", "on_key_down", "on_key_up"]);
 ///     }
 /// }
+/// # }
 /// ```
 ///
-/// To make the function accessible outside the crate, make sure to use the
-/// `#[macro_export]` attribute.
+/// Like with `pub let`, using `pub(...)` on the `append` statement re-exports
+/// the variable from the current module with the specified visibility.
+/// Without `pub`, the new value is only visible in the rest of the current
+/// [`rukt`](crate::rukt) block.
 ///
 /// ```
 /// # use rukt::rukt;
 /// rukt! {
-///     #[macro_export]
-///     pub fn foo() {
-///         "hello"
+///     pub(self) registry numbers;
+///     append numbers { 1 };
+///     append numbers { 2 };
+///     let result = numbers;
+///     expand {
+///         assert_eq!(stringify!($result), "{1 2}");
 ///     }
 /// }
 /// ```
 ///
-/// Note that exported functions can also be invoked directly as macros outside
-/// of [`rukt`](crate::rukt) blocks.
+/// Note that unlike `pub let`, the `pub registry` and `append` statements don't
+/// bind any variable in the current scope. Referring to the registry by name in
+/// an expression goes through the exported macro, which always holds the latest
+/// value.
 ///
-/// ```
-/// # use rukt::rukt;
-/// rukt! {
-///     pub(crate) fn generate_message($name:literal) {
-///         expand {
-///             const MESSAGE: &str = concat!("hello ", $name);
-///         }
-///     }
-/// }
-/// generate_message!("world");
-/// assert_eq!(MESSAGE, "hello world");
-/// ```
+/// Because of the way `macro_rules` shadowing works, a macro redefined by an
+/// `append` statement can only be referred to from the rest of the same
+/// [`rukt`](crate::rukt) block, or through the re-export. Appending to the same
+/// registry from several [`rukt`](crate::rukt) blocks in the same module will
+/// result in ambiguity errors. Instead, each contribution needs to live in its
+/// own module, and refer to the previous one by path. This also means that the
+/// order in which contributions get appended is always explicit.
+///
+/// # Recursion depth
+///
+/// Each statement adds a few levels of macro recursion, and long programs can
+/// run into the `#![recursion_limit]` of the crate. Simple statements are
+/// evaluated without going through the expression evaluator:
+///
+/// - `let` bindings of a single literal take one level,
+/// - `let` bindings of a single group take two levels,
+/// - `let` bindings of a single variable take two levels,
+/// - `let _ = ...;` with a single literal or group takes one level,
+/// - consecutive `use` statements take two levels each.
 ///
-/// However, outside of [`rukt`](crate::rukt) blocks, expanding exported
-/// functions in expression contexts is currently not supported.
+/// Other statements take at least four levels. Function definitions take about
+/// eight levels, plus about one for every identifier in the body, which is
+/// scanned for the variables to capture. In expressions, lists of identifiers
+/// are passed along as-is, lists of variables take one level, and other groups
+/// take at least three: the group is scanned for dollar signs `$`, which takes
+/// one level for every 8 tokens or nested group up to the first dollar sign,
+/// and two more levels substitute variables or space the tokens consistently.
+/// With the default recursion limit of 128, a block can hold about 100 simple
+/// statements, compared to about 30 statements going through the expression
+/// evaluator. The body of `expand` statements is scanned for double dollar
+/// signs `$$`, which takes one level for every 16 tokens and about three levels
+/// for each group. With the `proc` feature, the body is scanned by a procedural
+/// macro in a single step, and with the `nightly-metavar-expr` feature it's not
+/// scanned at all. For bigger programs, raise the recursion limit or split the
+/// program into several [`rukt`](crate::rukt) blocks.
 #[doc(inline)]
 pub use eval_block as block;
 
+#[cfg(feature = "checked")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_block_checked_step {
+    ({ $($T:tt)+ } $S:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [[$L:literal [$($H:literal)*]] $($V:tt)*] $D:tt) => {
+        compile_error!(concat!(
+            "rukt: evaluation exceeded ", stringify!($L), " steps\n",
+            "tokens = ", stringify!({ $($T)* }), "\n",
+            "help: ", $($H,)* "or split the program into smaller blocks",
+        ));
+    };
+    ({ $($T:tt)+ } $S:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [[$L:tt $H:tt $X:tt $($Y:tt)*] $($V:tt)*] $D:tt) => {
+        $crate::eval_block!({ $($T)* } (__rukt_step) $N [$DD __rukt_steps:tt $($P)*] [[$L $H $($Y)*] $($V)*] $);
+    };
+}
+
+#[cfg(feature = "checked")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_block_checked {
+    ($L:tt $H:tt $T:tt) => {
+        $crate::utils::integers!(($crate::eval_range; [0 $L] ".." ($T ($crate::eval_block_checked_start; $L $H) [] [] $)));
+    };
+}
+
+#[cfg(feature = "checked")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_block_checked_start {
+    ($T:tt [$($X:tt)*] $L:tt $H:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($T () ($crate::eval::stop;) [$D __rukt_steps:tt] [[$L $H $($X)*]] $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_block_with {
+    ({ $($I:ident = $X:tt),* $(,)? } $T:tt $D:tt) => {
+        $crate::eval::block!($T () ($crate::eval::stop;) [$($D $I:tt)*] [$($X)*] $);
+    };
+    ({ $($B:tt)* } $T:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid bindings `", stringify!($($B)*), "`, ",
+            "expected `name = value` pairs where each value is a single token tree",
+        ));
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_expression {
     ({ if $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_expression; [] $N)) $P $V $);
     };
+    ({ do { $($B:tt)* } $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_nested_block!({ $($B)* ; } { $($T)* } $N $P $V $);
+    };
     ({ true $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!({ $($T)* } true $($C)* $P $V $);
     };
     ({ false $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!({ $($T)* } false $($C)* $P $V $);
     };
+    ({ crate $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_builtin!({ $($T)* } () [crate] $N $P $V $);
+    };
     ({ $I:ident $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         macro_rules! __rukt_transcribe {
             ($P $TT:tt $NN:tt $PP:tt $VV:tt) => {
@@ -747,30 +3089,84 @@ macro_rules! eval_expression {
         }
         __rukt_transcribe!($V { $($T)* } $N $P $V);
     };
-    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+    // lists of identifiers can't reference variables, and the fragments are spaced like the
+    // transcription would space them
+    ({ ($($X:ident)*) $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } ($($X)*) $($C)* $P $V $);
+    };
+    ({ ($($X:ident),*) $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } ($($X),*) $($C)* $P $V $);
+    };
+    ({ [$($X:ident)*] $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } [$($X)*] $($C)* $P $V $);
+    };
+    ({ [$($X:ident),*] $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } [$($X),*] $($C)* $P $V $);
+    };
+    ({ {$($X:ident)*} $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } {$($X)*} $($C)* $P $V $);
+    };
+    ({ {$($X:ident),*} $($T:tt)* } $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!({ $($T)* } {$($X),*} $($C)* $P $V $);
+    };
+    // lists of variables are transcribed right away
+    ({ ($($Q:tt $X:ident)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         macro_rules! __rukt_transcribe {
             ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
-                $FF!($TT ($($R)*) $D($CC)* $PP $VV $);
+                $FF!($TT ($($Q $X)*) $D($CC)* $PP $VV $);
             };
         }
         __rukt_transcribe!($V { $($T)* } $N $P $V);
     };
-    ({ [$($R:tt)*] $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+    ({ ($($Q:tt $X:ident),*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         macro_rules! __rukt_transcribe {
             ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
-                $FF!($TT [$($R)*] $D($CC)* $PP $VV $);
+                $FF!($TT ($($Q $X),*) $D($CC)* $PP $VV $);
             };
         }
         __rukt_transcribe!($V { $($T)* } $N $P $V);
     };
-    ({ {$($R:tt)*} $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+    ({ [$($Q:tt $X:ident)*] $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $FF!($TT [$($Q $X)*] $D($CC)* $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ [$($Q:tt $X:ident),*] $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $FF!($TT [$($Q $X),*] $D($CC)* $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ {$($Q:tt $X:ident)*} $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $FF!($TT {$($Q $X)*} $D($CC)* $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V { $($T)* } $N $P $V);
+    };
+    ({ {$($Q:tt $X:ident),*} $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         macro_rules! __rukt_transcribe {
             ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
-                $FF!($TT {$($R)*} $D($CC)* $PP $VV $);
+                $FF!($TT {$($Q $X),*} $D($CC)* $PP $VV $);
             };
         }
         __rukt_transcribe!($V { $($T)* } $N $P $V);
     };
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils_scan!(@dollar [$([=$R=])*] [] [] [] [] [] ($crate::eval_expression_group; ($($R)*) { $($T)* } $N $P $V $) $);
+    };
+    ({ [$($R:tt)*] $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils_scan!(@dollar [$([=$R=])*] [] [] [] [] [] ($crate::eval_expression_group; [$($R)*] { $($T)* } $N $P $V $) $);
+    };
+    ({ {$($R:tt)*} $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils_scan!(@dollar [$([=$R=])*] [] [] [] [] [] ($crate::eval_expression_group; {$($R)*} { $($T)* } $N $P $V $) $);
+    };
     ({ ! $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [!] $N) $P $V $);
     };
@@ -779,9 +3175,38 @@ macro_rules! eval_expression {
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_expression_group {
+    // groups are only transcribed with the environment when they contain a dollar sign, otherwise
+    // the generated macro only spaces the tokens like the transcription would
+    ($R:tt $W:tt [] $G:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $FF!($TT $G $D($CC)* $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($T $N $P $V);
+    };
+    ($R:tt $W:tt $X:tt $G:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $TT:tt ($FF:path; $D($CC:tt)*) $PP:tt $VV:tt) => {
+                $FF!($TT $G $D($CC)* $PP $VV $);
+            };
+        }
+        __rukt_transcribe!($V $T $N $P $V);
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_if_expression {
+    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } true [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_nested_block!({ $($B1)* } { $($T)* } $N $P $V $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } false [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_nested_block!({ $($B2)* } { $($T)* } $N $P $V $);
+    };
     ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } true [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval_if_expression_block!({ $($T)* } [$($A)* { $($B1)* }] $N $P $V $);
     };
@@ -794,13 +3219,19 @@ macro_rules! eval_if_expression {
     ({ { $($B:tt)* } else if $($T:tt)* } false [$($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
         $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval_if_expression; [$($A)*] $N)) $P $V $);
     };
+    ({ { $($B:tt)* } $($T:tt)* } true $A:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: `if` expressions must have an `else` branch");
+    };
+    ({ { $($B:tt)* } $($T:tt)* } false $A:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!("rukt: `if` expressions must have an `else` branch");
+    };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_if_expression_block {
     ($T:tt [$B:tt $($A:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::block!($B () ($crate::eval::parent; $T $P $V $N) $P $V $);
+        $crate::eval_nested_block!($B $T $N $P $V $);
     };
 }
 
@@ -835,6 +3266,7 @@ macro_rules! eval_builtin {
 /// - [Builtins](crate::builtins)
 /// - [Operators](operator)
 /// - [If expressions](#if-expressions)
+/// - [Do expressions](#do-expressions)
 ///
 /// # Literals
 ///
@@ -847,6 +3279,7 @@ macro_rules! eval_builtin {
 ///     let number = 42;
 ///     let string = "hello";
 ///     let boolean = true;
+///     let lifetime = 'static;
 ///     let operator = +;
 ///     let separator = ::;
 ///     let punctuation = .;
@@ -872,7 +3305,7 @@ macro_rules! eval_builtin {
 ///         $taco
 ///     };
 ///     expand {
-///         assert_eq!(stringify!($arbitrary), "{ [1 2 3] { chat => bouc, cheese => pizza, } }");
+///         assert_eq!(stringify!($arbitrary), "{[1 2 3] {chat => bouc, cheese => pizza,}}");
 ///     }
 /// }
 /// ```
@@ -908,6 +3341,27 @@ macro_rules! eval_builtin {
 /// }
 /// ```
 ///
+/// Since builtins are regular macros, the evaluator can't tell an undefined
+/// variable apart from a builtin that isn't in scope, so the error comes from
+/// Rust's own name resolution. A fallback macro defined by the evaluator would
+/// take precedence over the builtins imported in the surrounding code, or
+/// conflict with them. To list the variables that are actually in scope at
+/// this point, you can use the [`breakpoint`](crate::builtins::breakpoint)
+/// builtin.
+///
+/// Builtins can also be referred to with a path, in which case they don't need
+/// to be imported.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let number = rukt::builtins::identity(123);
+///     expand {
+///         assert_eq!($number, 123);
+///     }
+/// }
+/// ```
+///
 /// If you want to store an identifier token in a variable you'll have to
 /// extract it from a token tree, for example using `let` destructuring.
 ///
@@ -946,6 +3400,64 @@ macro_rules! eval_builtin {
 /// Note that unlike in regular Rust, the condition of `else if` clauses will
 /// always be eagerly evaluated, even when the branch to take has already been
 /// decided.
+///
+/// Each branch evaluates to the value of its trailing expression. A branch that
+/// is empty or ends with a statement evaluates to `()`, so the two branches
+/// don't need to have the same shape.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let result = if true {
+///         let value = 1;
+///     } else {
+///         2
+///     };
+///     expand {
+///         assert_eq!($result, ());
+///     }
+/// }
+/// ```
+///
+/// Since the result of an `if` expression is always used, the `else` branch
+/// can't be omitted.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let result = if true { 1 }; // error: rukt: `if` expressions must have an `else` branch
+/// }
+/// ```
+///
+/// # Do expressions
+///
+/// A `do` expression evaluates a nested block for its side effects, like
+/// `expand` statements, and always results in `()`. This makes it possible to
+/// emit code from within a larger expression.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let result = if true {
+///         do {
+///             expand {
+///                 const EMITTED: u32 = 42;
+///             }
+///             "ignored"
+///         }
+///     } else {
+///         "unreachable"
+///     };
+///     expand {
+///         assert_eq!($result, ());
+///     }
+/// }
+/// assert_eq!(EMITTED, 42);
+/// ```
+///
+/// The trailing expression of the block is discarded, as if it was followed by
+/// a semicolon `;`. Variables defined in the block are not visible outside of
+/// it.
 #[doc(inline)]
 pub use eval_expression as expression;
 
@@ -962,89 +3474,536 @@ macro_rules! eval_operator {
         __rukt_transcribe!($V { $($T)* } $S ($crate::eval::operator; $O $N) $P $V);
     };
 
-    // builtin
-    ({ .$I:ident $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_builtin!({ $($T)* } $S [$I] ($crate::eval::operator; $O $N) $P $V $);
+    // builtin
+    ({ .$I:ident $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_builtin!({ $($T)* } $S [$I] ($crate::eval::operator; $O $N) $P $V $);
+    };
+
+    // ! operator
+    ($T:tt $S:tt [!] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_not!($T $S $N $P $V $);
+    };
+
+    // ranges
+    ($T:tt $S:tt [(..) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::integers!(($crate::eval_range; [$R $S] ".." ($T $N $P $V $)));
+    };
+    ($T:tt $S:tt [(..=) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::integers!(($crate::eval_range; [$R $S] "..=" ($T $N $P $V $)));
+    };
+    ({ ..= $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(..=) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ .. $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(..) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+
+    // chained comparisons
+    ({ == $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [==] { $($T)* } $S $N) $P $V $);
+    };
+    ({ != $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [!=] { $($T)* } $S $N) $P $V $);
+    };
+    ({ < $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [<] { $($T)* } $S $N) $P $V $);
+    };
+    ({ > $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [>] { $($T)* } $S $N) $P $V $);
+    };
+    ({ <= $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [<=] { $($T)* } $S $N) $P $V $);
+    };
+    ({ >= $($T:tt)* } $S:tt [($O:tt) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::operator!({} $S [($O) $R] ($crate::eval_comparison_chain; [>=] { $($T)* } $S $N) $P $V $);
+    };
+
+    // comparison operators
+    ($T:tt $S:tt [(==) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::tokens_equal!([$R $S] ($crate::eval_compare_result; [true false] $T $N $P $V));
+    };
+    ($T:tt $S:tt [(!=) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::utils::tokens_equal!([$R $S] ($crate::eval_compare_result; [false true] $T $N $P $V));
+    };
+    ($T:tt $S:tt [(<) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$R $S] [$R $S] [true false false] $T $N $P $V $));
+    };
+    ($T:tt $S:tt [(>) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$R $S] [$R $S] [false false true] $T $N $P $V $));
+    };
+    ($T:tt $S:tt [(<=) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$R $S] [$R $S] [true true false] $T $N $P $V $));
+    };
+    ($T:tt $S:tt [(>=) $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_order_table!(($crate::eval_order; [$R $S] [$R $S] [false true true] $T $N $P $V $));
+    };
+    ({ == $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(==) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ != $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(!=) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ < $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(<) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ > $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(>) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ <= $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(<=) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ({ >= $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [(>=) $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+
+    // boolean operators
+    ($T:tt $S:tt [&& $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_and!($T $R $S $N $P $V $);
+    };
+    ({ && $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [&& $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+    ($T:tt $S:tt [|| $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_or!($T $R $S $N $P $V $);
+    };
+    ({ || $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [|| $S] ($crate::eval::operator; $O $N)) $P $V $);
+    };
+
+    // default operator, only reached once pending operators have been applied
+    ({ ?? $($T:tt)* } false [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] $N) $P $V $);
+    };
+    ({ ?? $($T:tt)* } $S:tt [] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S [] $N $P $V $);
+    };
+
+    // nothing
+    ($T:tt $S:tt [] ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $S $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_call {
+    (
+        $T:tt
+        { fn $I:ident ($($R:tt)*) $([$($FP:tt)*] [$($FV:tt)*])? { $($B:tt)* } }
+        $A:tt
+        $N:tt
+        $P:tt
+        $V:tt
+        $D:tt
+    ) => {
+        macro_rules! __rukt_transcribe {
+            (($($R)*) $D($D CC:tt)*) => {
+                $crate::eval_call_matched!($D($D CC)*);
+            };
+            ($D AA:tt [$D($D RR:tt)*] $D($D CC:tt)*) => {
+                compile_error!(concat!(
+                    "rukt: mismatched arguments for `", stringify!($I), "`\n",
+                    "expected = (", stringify!($D($D RR)*), ")\n",
+                    "found = ", stringify!($D AA),
+                ));
+            };
+        }
+        __rukt_transcribe!($A [$($R)*] $T { fn $I ($($R)*) $([$($FP)*] [$($FV)*])* { $($B)* } } $A $N $P $V $);
+    };
+    ($T:tt $F:tt $A:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: cannot call non-function value `", stringify!($F), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_call_matched {
+    (
+        $R:tt
+        $T:tt
+        { fn $I:ident ($($R2:tt)*) $([$($FP:tt)*] [$($FV:tt)*])? { $($B:tt)* } }
+        $A:tt
+        $N:tt
+        $P:tt
+        $V:tt
+        $D:tt
+    ) => {
+        $crate::eval::block!(
+            { $($B)* }
+            ()
+            ($crate::eval::parent; $T $P $V $N)
+            [$($($FP)*)* $D$I:tt ($($R2)*)]
+            [$($($FV)*)* { fn $I ($($R2)*) $([$($FP)*] [$($FV)*])* { $($B)* } } $A]
+        $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_not {
+    ($T:tt true ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T false $($C)* $P $V $);
+    };
+    ($T:tt false ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T true $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_compare_result {
+    (true [$R1:tt $R2:tt] $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt) => {
+        $F!($T $R1 $($C)* $P $V $);
+    };
+    (false [$R1:tt $R2:tt] $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt) => {
+        $F!($T $R2 $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_opaque {
+    ([$($S:tt)*] $M:literal) => {
+        compile_error!(concat!(
+            "rukt: can't ", $M, " `", stringify!($($S)*), "`, ",
+            "values parsed with fragment specifiers other than `tt`, `ident` and `lifetime` are opaque and can't be inspected\n",
+            "help: destructure the value with `tt` instead, for example `let [$x:tt] = [...];`",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_comparison_chain {
+    ({} $X:tt [$O:tt] $T:tt $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::expression!($T () ($crate::eval::operator; [($O) $S] ($crate::eval::operator; [&& $X] $N)) $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_order_table {
+    // the integers between 0 and 255 paired with their low digit, and the digits following each digit,
+    // the rows are paired with the high digit in order
+    (($F:path; $($C:tt)*)) => {
+        $F!(
+            [
+                [(0 0) (1 1) (2 2) (3 3) (4 4) (5 5) (6 6) (7 7) (8 8) (9 9) (10 10) (11 11) (12 12) (13 13) (14 14) (15 15)]
+                [(16 0) (17 1) (18 2) (19 3) (20 4) (21 5) (22 6) (23 7) (24 8) (25 9) (26 10) (27 11) (28 12) (29 13) (30 14) (31 15)]
+                [(32 0) (33 1) (34 2) (35 3) (36 4) (37 5) (38 6) (39 7) (40 8) (41 9) (42 10) (43 11) (44 12) (45 13) (46 14) (47 15)]
+                [(48 0) (49 1) (50 2) (51 3) (52 4) (53 5) (54 6) (55 7) (56 8) (57 9) (58 10) (59 11) (60 12) (61 13) (62 14) (63 15)]
+                [(64 0) (65 1) (66 2) (67 3) (68 4) (69 5) (70 6) (71 7) (72 8) (73 9) (74 10) (75 11) (76 12) (77 13) (78 14) (79 15)]
+                [(80 0) (81 1) (82 2) (83 3) (84 4) (85 5) (86 6) (87 7) (88 8) (89 9) (90 10) (91 11) (92 12) (93 13) (94 14) (95 15)]
+                [(96 0) (97 1) (98 2) (99 3) (100 4) (101 5) (102 6) (103 7) (104 8) (105 9) (106 10) (107 11) (108 12) (109 13) (110 14) (111 15)]
+                [(112 0) (113 1) (114 2) (115 3) (116 4) (117 5) (118 6) (119 7) (120 8) (121 9) (122 10) (123 11) (124 12) (125 13) (126 14) (127 15)]
+                [(128 0) (129 1) (130 2) (131 3) (132 4) (133 5) (134 6) (135 7) (136 8) (137 9) (138 10) (139 11) (140 12) (141 13) (142 14) (143 15)]
+                [(144 0) (145 1) (146 2) (147 3) (148 4) (149 5) (150 6) (151 7) (152 8) (153 9) (154 10) (155 11) (156 12) (157 13) (158 14) (159 15)]
+                [(160 0) (161 1) (162 2) (163 3) (164 4) (165 5) (166 6) (167 7) (168 8) (169 9) (170 10) (171 11) (172 12) (173 13) (174 14) (175 15)]
+                [(176 0) (177 1) (178 2) (179 3) (180 4) (181 5) (182 6) (183 7) (184 8) (185 9) (186 10) (187 11) (188 12) (189 13) (190 14) (191 15)]
+                [(192 0) (193 1) (194 2) (195 3) (196 4) (197 5) (198 6) (199 7) (200 8) (201 9) (202 10) (203 11) (204 12) (205 13) (206 14) (207 15)]
+                [(208 0) (209 1) (210 2) (211 3) (212 4) (213 5) (214 6) (215 7) (216 8) (217 9) (218 10) (219 11) (220 12) (221 13) (222 14) (223 15)]
+                [(224 0) (225 1) (226 2) (227 3) (228 4) (229 5) (230 6) (231 7) (232 8) (233 9) (234 10) (235 11) (236 12) (237 13) (238 14) (239 15)]
+                [(240 0) (241 1) (242 2) (243 3) (244 4) (245 5) (246 6) (247 7) (248 8) (249 9) (250 10) (251 11) (252 12) (253 13) (254 14) (255 15)]
+            ]
+            [
+                (0 [1 2 3 4 5 6 7 8 9 10 11 12 13 14 15])
+                (1 [2 3 4 5 6 7 8 9 10 11 12 13 14 15])
+                (2 [3 4 5 6 7 8 9 10 11 12 13 14 15])
+                (3 [4 5 6 7 8 9 10 11 12 13 14 15])
+                (4 [5 6 7 8 9 10 11 12 13 14 15])
+                (5 [6 7 8 9 10 11 12 13 14 15])
+                (6 [7 8 9 10 11 12 13 14 15])
+                (7 [8 9 10 11 12 13 14 15])
+                (8 [9 10 11 12 13 14 15])
+                (9 [10 11 12 13 14 15])
+                (10 [11 12 13 14 15])
+                (11 [12 13 14 15])
+                (12 [13 14 15])
+                (13 [14 15])
+                (14 [15])
+                (15 [])
+            ]
+            $($C)*
+        );
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_order {
+    // the digits of both integers are looked up first, then compared with a single arm for each pair of digits
+    ([$([$(($I:tt $L:tt))*])*] [$(($G:tt [$($S:tt)*]))*] [$X:literal $Y:literal] [$A:tt $B:tt] $M:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_order {
+            $(
+                (@cmp $D K:tt [$G $D($D X:tt)*] [$G $D($D Y:tt)*]) => {
+                    __rukt_order!(@cmp $D K [$D($D X)*] [$D($D Y)*]);
+                };
+                $(
+                    (@cmp ([$D LT:tt $D EQ:tt $D GT:tt] $D TT:tt ($D F:path; $D($D C:tt)*) $D PP:tt $D VV:tt $D DD:tt) [$G $D($D X:tt)*] [$S $D($D Y:tt)*]) => {
+                        $D F!($D TT $D LT $D($D C)* $D PP $D VV $D DD);
+                    };
+                )*
+            )*
+            (@cmp ([$D LT:tt $D EQ:tt $D GT:tt] $D TT:tt ($D F:path; $D($D C:tt)*) $D PP:tt $D VV:tt $D DD:tt) [] []) => {
+                $D F!($D TT $D EQ $D($D C)* $D PP $D VV $D DD);
+            };
+            (@cmp ([$D LT:tt $D EQ:tt $D GT:tt] $D TT:tt ($D F:path; $D($D C:tt)*) $D PP:tt $D VV:tt $D DD:tt) $D X:tt $D Y:tt) => {
+                $D F!($D TT $D GT $D($D C)* $D PP $D VV $D DD);
+            };
+            $(
+                $(
+                    ($I $D($D R:tt)*) => {
+                        __rukt_order!($D($D R)* [$G $L]);
+                    };
+                )*
+            )*
+            // suffixed integers are looked up again without their suffix
+            ($D X:tt $D Y:tt @cmp $D K:tt) => {
+                $crate::eval::strip_suffixes!([$A $B] ($crate::eval_order_stripped; [$A $B] $D K));
+            };
+            ($D Y:tt @cmp $D K:tt $D G:tt) => {
+                $crate::eval::strip_suffixes!([$A $B] ($crate::eval_order_stripped; [$A $B] $D K));
+            };
+        }
+        __rukt_order!($A $B @cmp ($M $T $N $P $V $));
+    };
+    ($I:tt $G:tt [$X:tt $Y:tt] [$A:tt $B:tt] $M:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!(
+            "rukt: can't order `", stringify!($A), "` and `", stringify!($B), "`, ",
+            "expected integers between 0 and 255",
+        ));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_order_stripped {
+    ([] [] [$A:tt $B:tt] $K:tt) => {
+        compile_error!(concat!(
+            "rukt: can't order `", stringify!($A), "` and `", stringify!($B), "`, ",
+            "expected integers between 0 and 255",
+        ));
+    };
+    // the result is a boolean, so the suffix isn't needed anymore
+    ([$X:tt $Y:tt] $U:tt $R:tt ($M:tt $T:tt $N:tt $P:tt $V:tt $D:tt)) => {
+        $crate::eval_order_table!(($crate::eval_order; [$X $Y] [$X $Y] $M $T $N $P $V $));
+    };
+}
+
+#[cfg(feature = "proc")]
+#[doc(hidden)]
+pub use rukt_macros::eval_strip_suffixes as strip_suffixes;
+
+#[cfg(feature = "proc")]
+#[doc(hidden)]
+pub use rukt_macros::eval_attach_suffix as attach_suffix;
+
+#[cfg(not(feature = "proc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_strip_suffixes_disabled {
+    // without the `proc` feature there's nothing to strip, literals can't be split apart
+    ($X:tt ($F:path; $($C:tt)*)) => {
+        $F!([] [] $($C)*);
+    };
+}
+
+#[cfg(not(feature = "proc"))]
+#[doc(hidden)]
+pub use eval_strip_suffixes_disabled as strip_suffixes;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range {
+    ([$([$H:tt $($I:tt)*])*] [$A:tt $B:tt] $E:tt ($T:tt $N:tt $P:tt $V:tt $D:tt)) => {
+        macro_rules! __rukt_chunk {
+            (@done [$D HB:tt] [$D HA:tt] $D KK:tt) => {
+                $crate::eval_range_walk!([$D HA $D HB] [$A $B] $E [$([$H $($I)*])*] $D KK);
+            };
+            $(
+                ($H [$D($D X:tt)*] $D($D KK:tt)*) => {
+                    __rukt_chunk!($D($D X)* [$H] $D($D KK)*);
+                };
+                $(
+                    ($I [$D($D X:tt)*] $D($D KK:tt)*) => {
+                        __rukt_chunk!($D($D X)* [$H] $D($D KK)*);
+                    };
+                )*
+            )*
+            ($D X:tt [$D($D Y:tt)*] $D($D KK:tt)*) => {
+                $crate::eval::strip_suffixes!([$A $B] ($crate::eval_range_stripped; [$A $B] $E ($T $N $P $V $)));
+            };
+        }
+        __rukt_chunk!($A [$B [@done]] ($T $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range_stripped {
+    ([] [] [$A:tt $B:tt] $E:tt $K:tt) => {
+        compile_error!(concat!(
+            "rukt: invalid range `", stringify!($A), $E, stringify!($B), "`, ",
+            "expected integers between 0 and 255",
+        ));
+    };
+    // the suffix is attached back to each integer of the range
+    ([$X:tt $Y:tt] $U:tt $R:tt $E:tt ($T:tt $N:tt $P:tt $V:tt $D:tt)) => {
+        $crate::utils::integers!(($crate::eval_range; [$X $Y] $E ($T ($crate::eval_range_suffix; $U $N) $P $V $)));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range_suffix {
+    ($T:tt $S:tt $U:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::attach_suffix!($S $U ($crate::eval_range_suffixed; $T $N $P $V $));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range_suffixed {
+    ($S:tt $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $S $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range_walk {
+    ([$HA:tt $HB:tt] [$A:tt $B:tt] $E:tt $G:tt ($T:tt $N:tt $P:tt $V:tt $D:tt)) => {
+        macro_rules! __rukt_range {
+            (@seek [[$HA $D($D X:tt)*] $D($D R:tt)*] $D KK:tt) => {
+                __rukt_range!(@start [$HA $D($D X)*] [$D($D R)*] $D KK);
+            };
+            (@seek [[$HB $D($D X:tt)*] $D($D R:tt)*] $D KK:tt) => {
+                compile_error!(concat!("rukt: reversed range `", stringify!($A), $E, stringify!($B), "`"));
+            };
+            (@seek [$D C:tt $D($D R:tt)*] $D KK:tt) => {
+                __rukt_range!(@seek [$D($D R)*] $D KK);
+            };
+            (@start [$A $D($D X:tt)*] $D R:tt $D KK:tt) => {
+                __rukt_range!(@collect [] [$A $D($D X)*] $D R $D KK);
+            };
+            (@start [$B $D($D X:tt)*] $D R:tt $D KK:tt) => {
+                compile_error!(concat!("rukt: reversed range `", stringify!($A), $E, stringify!($B), "`"));
+            };
+            (@start [$D H:tt $D($D X:tt)*] $D R:tt $D KK:tt) => {
+                __rukt_range!(@start [$D($D X)*] $D R $D KK);
+            };
+            (@collect [$D($D L:tt)*] [$B $D($D X:tt)*] $D R:tt $D KK:tt) => {
+                $crate::eval_range_result!([$D($D L)*] $B $E $D KK);
+            };
+            (@collect [$D($D L:tt)*] [$D H:tt $D($D X:tt)*] $D R:tt $D KK:tt) => {
+                __rukt_range!(@collect [$D($D L)* $D H] [$D($D X)*] $D R $D KK);
+            };
+            (@collect $D L:tt [] [[$HB $D($D X:tt)*] $D($D R:tt)*] $D KK:tt) => {
+                __rukt_range!(@collect $D L [$HB $D($D X)*] [$D($D R)*] $D KK);
+            };
+            (@collect [$D($D L:tt)*] [] [[$D($D X:tt)*] $D($D R:tt)*] $D KK:tt) => {
+                __rukt_range!(@collect [$D($D L)* $D($D X)*] [] [$D($D R)*] $D KK);
+            };
+        }
+        __rukt_range!(@seek $G ($T $N $P $V $D));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_range_result {
+    ([$($L:tt)*] $B:tt "..=" ($T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt)) => {
+        $F!($T [$($L)* $B] $($C)* $P $V $);
+    };
+    ([$($L:tt)*] $B:tt ".." ($T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt)) => {
+        $F!($T [$($L)*] $($C)* $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_skip_operand {
+    ({ ! $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ if $($T:tt)* } $S:tt [$($K:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S [if $($K)*] $N $P $V $);
+    };
+    ({ do { $($B:tt)* } $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ $R:tt $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_skip_operator {
+    // calls and builtins
+    ({ ($($R:tt)*) $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ .$I:ident $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ ::$I:ident $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ ::<$F:tt> $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
+    };
+    ({ ::<$F:tt, $X:tt> $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S $K $N $P $V $);
     };
 
-    // ! operator
-    ($T:tt $S:tt [!] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_not!($T $S $N $P $V $);
+    // if expressions
+    ({ { $($B:tt)* } else if $($T:tt)* } $S:tt [if $($K:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S [if $($K)*] $N $P $V $);
+    };
+    ({ { $($B1:tt)* } else { $($B2:tt)* } $($T:tt)* } $S:tt [if $($K:tt)*] $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operator!({ $($T)* } $S [$($K)*] $N $P $V $);
     };
 
-    // comparison operators
-    ($T:tt $S:tt [== $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::utils::escape!([[$R] [$S]] [] [__rukt_dollar] ($crate::eval_compare_escaped; true false $T $N $P $V));
+    // binary operators
+    ({ == $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ($T:tt $S:tt [!= $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::utils::escape!([[$R] [$S]] [] [__rukt_dollar] ($crate::eval_compare_escaped; false true $T $N $P $V));
+    ({ != $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ({ == $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [== $S] ($crate::eval::operator; $O $N)) $P $V $);
+    ({ < $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ({ != $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [!= $S] ($crate::eval::operator; $O $N)) $P $V $);
+    ({ > $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-
-    // boolean operators
-    ($T:tt $S:tt [&& $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_and!($T $R $S $N $P $V $);
+    ({ <= $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ({ && $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [&& $S] ($crate::eval::operator; $O $N)) $P $V $);
+    ({ >= $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ($T:tt $S:tt [|| $R:tt] $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval_or!($T $R $S $N $P $V $);
+    ({ && $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ({ || $($T:tt)* } $S:tt $O:tt $N:tt $P:tt $V:tt $D:tt) => {
-        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [|| $S] ($crate::eval::operator; $O $N)) $P $V $);
+    ({ || $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-
-    // nothing
-    ($T:tt $S:tt [] ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-        $F!($T $S $($C)* $P $V $);
+    ({ ..= $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-}
-
-#[doc(hidden)]
-#[macro_export]
-macro_rules! eval_call {
-    (
-        $T:tt
-        { fn $I:ident ($($R:tt)*) $([$($FP:tt)*] [$($FV:tt)*])? { $($B:tt)* } }
-        $A:tt
-        $N:tt
-        $P:tt
-        $V:tt
-        $D:tt
-    ) => {
-        $crate::eval::block!(
-            { $($B)* }
-            ()
-            ($crate::eval::parent; $T $P $V $N)
-            [$($($FP)*)* $D$I:tt ($($R)*)]
-            [$($($FV)*)* { fn $I ($($R)*) $([$($FP)*] [$($FV)*])* { $($B)* } } $A]
-        $);
-    }
-}
-
-#[doc(hidden)]
-#[macro_export]
-macro_rules! eval_not {
-    ($T:tt true ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-        $F!($T false $($C)* $P $V $);
+    ({ .. $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-    ($T:tt false ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
-        $F!($T true $($C)* $P $V $);
+    ({ ?? $($T:tt)* } $S:tt $K:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_skip_operand!({ $($T)* } $S $K $N $P $V $);
     };
-}
 
-#[doc(hidden)]
-#[macro_export]
-macro_rules! eval_compare_escaped {
-    ([$S1:tt $S2:tt] $R1:tt $R2:tt $T:tt $N:tt $P:tt $V:tt) => {
-        $crate::utils::select!($S2 [[$S1 [$R1]] [[$_:tt] [$R2]]] ($crate::eval_unwrap; $T $N $P $V) $);
-    }
+    // end of the expression
+    ($T:tt $S:tt [] ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($T $S $($C)* $P $V $);
+    };
 }
 
 #[doc(hidden)]
@@ -1063,6 +4022,9 @@ macro_rules! eval_and {
     ($T:tt true false ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!($T false $($C)* $P $V $);
     };
+    ($T:tt $L:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: `&&` expects boolean operands, got `", stringify!($L), "` and `", stringify!($R), "`"));
+    };
 }
 
 #[doc(hidden)]
@@ -1081,6 +4043,9 @@ macro_rules! eval_or {
     ($T:tt true false ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!($T true $($C)* $P $V $);
     };
+    ($T:tt $L:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: `||` expects boolean operands, got `", stringify!($L), "` and `", stringify!($R), "`"));
+    };
 }
 
 /// Evaluate operator.
@@ -1089,6 +4054,8 @@ macro_rules! eval_or {
 ///
 /// - [Comparison operators](#comparison-operators)
 /// - [Boolean operators](#boolean-operators)
+/// - [Default operator](#default-operator)
+/// - [Range operators](#range-operators)
 /// - [Function calls](#function-calls)
 /// - [Builtin operators](#builtin-operators)
 ///
@@ -1109,6 +4076,117 @@ macro_rules! eval_or {
 /// }
 /// ```
 ///
+/// Tokens are compared by their spelling, not by their value, so `1 == 0x1`
+/// is `false`. Builtins can perform the same comparison with
+/// [`tokens_equal`](crate::utils::tokens_equal).
+///
+/// Integers between `0` and `255` can also be ordered with `<`, `>`, `<=` and
+/// `>=`. The integers are looked up in a table instead of being parsed, so
+/// larger integers can't be ordered.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = 42;
+///     let small = value < 100;
+///     let large = value >= 200;
+///     expand {
+///         assert_eq!([$small, $large], [true, false]);
+///     }
+/// }
+/// ```
+///
+/// Ordering other tokens will fail to compile.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = "a" < "b"; // error: rukt: can't order `"a"` and `"b"`, expected integers between 0 and 255
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = 255 < 256; // error: rukt: can't order `255` and `256`, expected integers between 0 and 255
+/// }
+/// ```
+///
+/// Literals are atomic tokens that `macro_rules` can't split apart, so by
+/// default suffixed integers like `42u32` can't be ordered. With the `proc`
+/// feature, the suffix is stripped before looking up the integers. An
+/// unsuffixed integer can be compared with a suffixed one, but mixing
+/// different suffixes is an error.
+///
+/// ```
+/// # #[cfg(feature = "proc")]
+/// # fn main() {
+/// # use rukt::rukt;
+/// rukt! {
+///     let value = 42u32;
+///     let small = value < 100u32;
+///     let large = value >= 200;
+///     expand {
+///         assert_eq!([$small, $large], [true, false]);
+///     }
+/// }
+/// # }
+/// # #[cfg(not(feature = "proc"))]
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = 1u8 < 2u32; // error: rukt: mismatched integer suffixes in `1u8` and `2u32`
+/// }
+/// ```
+///
+/// Values parsed with fragment specifiers like `expr`, `ty` or `literal` are
+/// opaque. Their tokens can't be inspected anymore, so comparing them will
+/// fail to compile. Destructure the value with `tt` instead.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let ($value:expr) = (1 + 2);
+///     let _ = value == (1 + 2); // error: rukt: can't compare `1 + 2`, values parsed with fragment specifiers other than `tt`, `ident` and `lifetime` are opaque and can't be inspected
+/// }
+/// ```
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let ($($value:tt)*) = (1 + 2);
+///     let equal = [$($value)*] == [1 + 2];
+///     expand {
+///         assert_eq!($equal, true);
+///     }
+/// }
+/// ```
+///
+/// Like in Python, comparisons can be chained. `a < b < c` is equivalent to
+/// `a < b && b < c`, and this works with all the comparison operators
+/// including `==` and `!=`.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let ordered = 1 < 2 < 3;
+///     let unordered = 1 < 3 < 2;
+///     expand {
+///         assert_eq!([$ordered, $unordered], [true, false]);
+///     }
+/// }
+/// rukt! {
+///     let same = 7 == 7 == 7;
+///     let different = 7 == 7 != 7;
+///     expand {
+///         assert_eq!([$same, $different], [true, false]);
+///     }
+/// }
+/// ```
+///
 /// # Boolean operators
 ///
 /// You can use the typical `!`, `&&`, and `||` boolean operators.
@@ -1149,13 +4227,110 @@ macro_rules! eval_or {
 /// # use rukt::rukt;
 /// rukt! {
 ///     let value = 42;
-///     let _ = true && value; // error: no rules expected the token `42`
+///     let _ = true && value; // error: rukt: `&&` expects boolean operands, got `true` and `42`
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = [] || false; // error: rukt: `||` expects boolean operands, got `[]` and `false`
 /// }
 /// ```
 ///
 /// Note that unlike in regular Rust, the right-side of `&&` and `||` is not
 /// lazy and will always be evaluated eagerly.
 ///
+/// # Default operator
+///
+/// The `??` operator returns the left-side unless it's `false`, in which case
+/// it evaluates and returns the right-side. This lets you provide defaults for
+/// values where `false` stands for the absence of a result.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let found = false;
+///     let a = found ?? 0;
+///     let b = [1 2 3] ?? 0;
+///     let c = false ?? false ?? 42;
+///     expand {
+///         assert_eq!($a, 0);
+///         assert_eq!(stringify!($b), "[1 2 3]");
+///         assert_eq!($c, 42);
+///     }
+/// }
+/// ```
+///
+/// The `??` operator has the lowest precedence and, unlike `&&` and `||`, the
+/// right-side is only evaluated when the left-side is `false`.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     fn fail() {
+///         expand {
+///             compile_error!("unreachable");
+///         }
+///     }
+///     let value = 1 == 1 ?? fail();
+///     expand {
+///         assert_eq!($value, true);
+///     }
+/// }
+/// ```
+///
+/// # Range operators
+///
+/// Integers between `0` and `255` can be turned into a list with `..` and
+/// `..=`. The list can be iterated over with a [`for`](block#for-loops) loop.
+///
+/// ```
+/// # use rukt::rukt;
+/// rukt! {
+///     let exclusive = 0..4;
+///     let inclusive = 0..=4;
+///     expand {
+///         assert_eq!(stringify!($exclusive), "[0 1 2 3]");
+///         assert_eq!(stringify!($inclusive), "[0 1 2 3 4]");
+///     }
+///     for i in 1..3 {
+///         expand {
+///             assert!($i == 1 || $i == 2);
+///         }
+///     }
+/// }
+/// ```
+///
+/// With the `proc` feature, the bounds can also have the same suffix, or a
+/// suffix on only one of them. The suffix is stripped to build the list, then
+/// attached back to every integer.
+///
+/// ```
+/// # #[cfg(feature = "proc")]
+/// # fn main() {
+/// # use rukt::rukt;
+/// rukt! {
+///     let indices = 0..3usize;
+///     expand {
+///         assert_eq!(stringify!($indices), "[0usize 1usize 2usize]");
+///     }
+/// }
+/// # }
+/// # #[cfg(not(feature = "proc"))]
+/// # fn main() {}
+/// ```
+///
+/// Reversed ranges will fail to compile. Ranges written inside literal token
+/// trees like `[0..4]` are left untouched.
+///
+/// ```compile_fail
+/// # use rukt::rukt;
+/// rukt! {
+///     let _ = 4..0; // error: rukt: reversed range `4..0`
+/// }
+/// ```
+///
 /// # Function calls
 ///
 /// You can call Rukt [functions](block#function-definitions) by supplying arguments enclosed in parentheses
@@ -1197,15 +4372,61 @@ macro_rules! eval_or {
 #[doc(inline)]
 pub use eval_operator as operator;
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_nested_block {
+    // the step counter stays in front so that the nested block keeps counting
+    ($B:tt $T:tt $N:tt [$DD:tt __rukt_steps:tt $($P:tt)*] [$L:tt $($V:tt)*] $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::parent; $T $N) [$DD __rukt_steps:tt [$($P)*]] [$L [$($V)*]] $);
+    };
+    ($B:tt $T:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval::block!($B () ($crate::eval::parent; $T $N) [$P] [$V] $);
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_parent {
+    // the parent block picks up the remaining steps of the nested one
+    (
+        {}
+        $S:tt
+        $RT:tt
+        [$RD:tt __rukt_steps:tt $($RP:tt)*]
+        [$RL:tt $($RV:tt)*]
+        ($F:path; $($C:tt)*)
+        [$DD:tt __rukt_steps:tt $($P:tt)*]
+        [$L:tt $($V:tt)*]
+        $D:tt
+    ) => {
+        $F!($RT $S $($C)* [$RD __rukt_steps:tt $($RP)*] [$L $($RV)*] $);
+    };
     ({} $S:tt $RT:tt $RP:tt $RV:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
         $F!($RT $S $($C)* $RP $RV $);
     };
+    (
+        {}
+        $S:tt
+        $RT:tt
+        ($F:path; $($C:tt)*)
+        [$DD:tt __rukt_steps:tt [$($RP:tt)*] $($P:tt)*]
+        [$L:tt [$($RV:tt)*] $($V:tt)*]
+        $D:tt
+    ) => {
+        $F!($RT $S $($C)* [$DD __rukt_steps:tt $($RP)*] [$L $($RV)*] $);
+    };
+    ({} $S:tt $RT:tt ($F:path; $($C:tt)*) [$RP:tt $($P:tt)*] [$RV:tt $($V:tt)*] $D:tt) => {
+        $F!($RT $S $($C)* $RP $RV $);
+    };
 }
 
 /// Resume evaluation of the parent block.
+///
+/// The continuation either carries the remaining tokens and the environment of
+/// the parent block, `(parent; T P V N)`, or only the remaining tokens,
+/// `(parent; T N)`. The second form is used for nested `if` and `do` blocks,
+/// where the environment of the parent block is the first entry of the nested
+/// one.
 #[doc(inline)]
 pub use eval_parent as parent;
 
@@ -1219,6 +4440,36 @@ macro_rules! eval_stop {
 #[doc(inline)]
 pub use eval_stop as stop;
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_emit {
+    ({} $S:tt $I:ident $P:tt $V:tt $) => {
+        let $I = $S;
+    };
+}
+
+/// End evaluation and bind the current subject to a local variable.
+#[doc(inline)]
+pub use eval_emit as emit;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_forward {
+    ({} $S:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($S $($C)*);
+    };
+    ({ $($T:tt)+ } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+        compile_error!(concat!("rukt: unexpected tokens `", stringify!($($T)*), "` after the expression"));
+    };
+}
+
+/// End evaluation and invoke a user macro with the current subject.
+///
+/// The continuation `(forward; (F; C))` expands to `F!(S C)`, where `S` is the
+/// current subject. Tokens left over after the expression are an error.
+#[doc(inline)]
+pub use eval_forward as forward;
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! eval_unwrap {
@@ -1230,3 +4481,52 @@ macro_rules! eval_unwrap {
 /// Helper accepting tokens for the current subject as first argument.
 #[doc(inline)]
 pub use eval_unwrap as unwrap;
+
+// substitute the variables referenced in the given tokens and pass them to the continuation,
+// tokens without any dollar sign are passed as-is without pasting the environment
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_substitute {
+    ($R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!($R $R $N $P $V $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_substitute_scan {
+    ([($($G:tt)*) $($X:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!([$($G)* $($X)*] $R $N $P $V $);
+    };
+    ([[$($G:tt)*] $($X:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!([$($G)* $($X)*] $R $N $P $V $);
+    };
+    ([{$($G:tt)*} $($X:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!([$($G)* $($X)*] $R $N $P $V $);
+    };
+    ([$H:ident $($X:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!([$($X)*] $R $N $P $V $);
+    };
+    ([$H:tt $($X:tt)*] $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_detect!([=$H=] [$($X)*] $R $N $P $V $);
+    };
+    ([] $R:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        $F!($R $($C)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! eval_substitute_detect {
+    ([$(=)$+] $X:tt [$($R:tt)*] ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+        macro_rules! __rukt_transcribe {
+            ($P $D($D CC:tt)*) => {
+                $F!([$($R)*] $D($D CC)*);
+            };
+        }
+        __rukt_transcribe!($V $($C)*);
+    };
+    ([=$H:tt=] $X:tt $R:tt $N:tt $P:tt $V:tt $D:tt) => {
+        $crate::eval_substitute_scan!($X $R $N $P $V $);
+    };
+}
@@ -1,4 +1,6 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+#![cfg_attr(feature = "nightly-metavar-expr", doc(test(attr(feature(macro_metavar_expr)))))]
 
 pub mod builtins;
 pub mod eval;
@@ -15,3 +17,346 @@ macro_rules! rukt {
         $crate::eval::block!({ $($T)* } () ($crate::eval::stop;) [] [] $);
     };
 }
+
+/// Rukt code block invoked from another macro.
+///
+/// A `macro_rules!` macro that wraps a [`rukt`](crate::rukt) block can't write
+/// `$` signs for the repetitions and the metavariables of the block, since
+/// they would be interpreted by the outer macro. The usual workaround is to
+/// forward a `$` token through a second rule of the outer macro, and write the
+/// block with it. This entry point takes the forwarded `$` token as first
+/// argument, followed by the statements in braces, and evaluates the block
+/// with it instead of the `$` token of the `rukt` crate.
+///
+/// ```
+/// macro_rules! constants {
+///     ($($name:ident = $value:tt),*) => {
+///         // `$` right before the closing delimiter is passed as-is
+///         constants!(@inner [$($name $value)*] $);
+///     };
+///     (@inner [$($name:ident $value:tt)*] $d:tt) => {
+///         rukt::rukt_wrapped!($d {
+///             let names = [$($name)*];
+///             let values = [$($value)*];
+///             let [$d($d n:ident)*] = names;
+///             let [$d($d v:tt)*] = values;
+///             expand {
+///                 $d(const $d n: u32 = $d v;)*
+///             }
+///         });
+///     };
+/// }
+///
+/// constants!(A = 1, B = 2);
+/// assert_eq!([A, B], [1, 2]);
+/// ```
+///
+/// When the outer macro is exported and used from other crates, re-export the
+/// `rukt` crate under a hidden name, like `#[doc(hidden)] pub use rukt as
+/// __rukt;`, and invoke the block with `$crate::__rukt::rukt_wrapped!`, since
+/// the downstream crate might not depend on `rukt` directly. Variables,
+/// functions and exports defined in the block resolve at the invocation site of
+/// the outer macro.
+#[macro_export]
+macro_rules! rukt_wrapped {
+    ($D:tt { $($T:tt)* }) => {
+        $crate::eval::block!({ $($T)* } () ($crate::eval::stop;) [] [] $D);
+    };
+}
+
+/// Rukt code block with variables bound from the call site.
+///
+/// The first token tree is a braced list of `name = value` bindings, followed
+/// by the [statements](crate::eval::block) of the block. The values are taken
+/// verbatim as single token trees, without being evaluated, and the statements
+/// can refer to them like any other variable. This is convenient for seeding
+/// a block from the fragments matched by an outer macro, without emitting
+/// `let` statements that would need their `$` signs escaped.
+///
+/// ```
+/// macro_rules! describe {
+///     ($name:ident: $($value:tt)*) => {
+///         rukt::rukt_with! {
+///             { values = [$($value)*], fallback = "empty" }
+///             let description = if values == [] { fallback } else { values };
+///             expand {
+///                 const $name: &str = stringify!($description);
+///             }
+///         }
+///     };
+/// }
+///
+/// describe!(EMPTY:);
+/// describe!(NUMBERS: 1 2 3);
+/// assert_eq!(EMPTY, "\"empty\"");
+/// assert_eq!(NUMBERS, "[1 2 3]");
+/// ```
+///
+/// The statements are still part of the transcriber of the outer macro, so
+/// they can use its metavariables directly, like `$name` above. Variables of
+/// the block must have different names, and repetitions in patterns still need
+/// to be escaped.
+///
+/// Each value must be a single token tree. Wrap longer sequences of tokens in
+/// a group.
+///
+/// ```compile_fail
+/// rukt::rukt_with! {
+///     { value = 1 2 } // error: rukt: invalid bindings `value = 1 2`
+/// }
+/// ```
+#[macro_export]
+macro_rules! rukt_with {
+    ($B:tt $($T:tt)*) => {
+        $crate::eval_block_with!($B { $($T)* } $);
+    };
+}
+
+/// Evaluate a single Rukt [expression](crate::eval::expression) and forward
+/// the result to a macro.
+///
+/// This makes it possible to use the evaluator from regular `macro_rules!`
+/// definitions without adopting whole [`rukt`](crate::rukt) blocks. The first
+/// argument holds the tokens of the expression, and the second one the path of
+/// the callback followed by arbitrary context tokens. The expression is
+/// evaluated with an empty environment, so it can only refer to builtins, not
+/// to variables. The callback receives the resulting value as a single token
+/// tree, followed by the context tokens.
+///
+/// ```
+/// macro_rules! define {
+///     ($value:tt $name:ident) => {
+///         const $name: bool = $value;
+///     };
+/// }
+///
+/// macro_rules! same_tokens {
+///     ([$($a:tt)*] [$($b:tt)*] $name:ident) => {
+///         rukt::eval_to!(([$($a)*] == [$($b)*]) (define; $name));
+///     };
+/// }
+///
+/// same_tokens!([1 2] [1 2] SAME);
+/// same_tokens!([1 2] [2 1] DIFFERENT);
+/// assert_eq!([SAME, DIFFERENT], [true, false]);
+/// ```
+///
+/// The callback is invoked with the `name!(...)` syntax, so it can also be a
+/// path to an exported macro. Tokens left after the expression are an error.
+///
+/// ```compile_fail
+/// # macro_rules! define {
+/// #     ($value:tt $name:ident) => {
+/// #         const $name: bool = $value;
+/// #     };
+/// # }
+/// rukt::eval_to!((true false) (define; VALUE)); // error: rukt: unexpected tokens `false` after the expression
+/// ```
+#[macro_export]
+macro_rules! eval_to {
+    (($($T:tt)*) ($F:path; $($C:tt)*)) => {
+        $crate::eval::expression!({ $($T)* } () ($crate::eval::operator; [] ($crate::eval::forward; ($F; $($C)*))) [] [] $);
+    };
+}
+
+/// Define a [builtin](crate::builtins) from a list of rules.
+///
+/// Each rule matches a tuple with the subject and the parenthesized arguments
+/// of the call, and produces the resulting value. When the builtin is called
+/// as a function, like `name(args)`, the subject is `()`. The generated macro
+/// substitutes the variables in the arguments before trying the rules, and
+/// the first rule that matches wins.
+///
+/// ```
+/// # use rukt::{builtin, rukt};
+/// builtin! {
+///     first_or {
+///         ([$first:tt $($_:tt)*], ($default:tt)) => { $first };
+///         ([], ($default:tt)) => { $default };
+///     }
+/// }
+///
+/// rukt! {
+///     let fallback = 0;
+///     let a = [1 2 3].first_or($fallback);
+///     let b = [].first_or($fallback);
+///     expand {
+///         assert_eq!([$a, $b], [1, 0]);
+///     }
+/// }
+/// ```
+///
+/// A rule can also produce an invocation of `compile_error!`. When none of the
+/// rules match, the builtin reports the subject and the arguments it received.
+///
+/// ```compile_fail
+/// # use rukt::{builtin, rukt};
+/// builtin! {
+///     first_or {
+///         ([$first:tt $($_:tt)*], ($default:tt)) => { $first };
+///         ([], ($default:tt)) => { $default };
+///     }
+/// }
+///
+/// rukt! {
+///     let a = 42.first_or(0); // error: rukt: invalid arguments for `first_or`, no rule matches `(42, (0))`
+/// }
+/// ```
+///
+/// The builtin can be used as a function value. The function calls the
+/// builtin by name, so the macro needs to be in scope wherever the function
+/// gets called. Add `as` followed by a path to call the builtin through the
+/// path instead, typically when the macro is re-exported from a module. Paths
+/// starting with `crate` refer to the crate that invokes `builtin!`.
+///
+/// ```
+/// mod math {
+///     rukt::builtin! {
+///         builtin_double as crate::math::double {
+///             ($value:literal, ()) => { ($value * 2) };
+///         }
+///     }
+///     pub(crate) use builtin_double as double;
+/// }
+///
+/// # use rukt::rukt;
+/// # fn main() {
+/// rukt! {
+///     use crate::math::double;
+///     let f = double;
+///     let value = f(21);
+///     expand {
+///         assert_eq!($value, 42);
+///     }
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! builtin {
+    (
+        $(#[$A:meta])*
+        $I:ident $(as $($X:ident)::+)? {
+            $(($($L:tt)*) => { $($B:tt)* } $(;)?)*
+        }
+    ) => {
+        $crate::builtin_macro_path!([$([($($L)*) { $($B)* }])*] [$(#[$A])*] $I [$($($X)::+)?] $);
+    };
+}
+
+/// Rukt code block with a bound on the number of evaluation steps.
+///
+/// Deep Rukt programs can run into the compiler's recursion limit, which
+/// reports an error pointing at some internal macro of the evaluator. With the
+/// `checked` feature, this entry point counts the [statements](crate::eval::block)
+/// evaluated by the program, including the ones in nested blocks and loop
+/// bodies, and stops with a friendlier error when the count exceeds the limit.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["checked"] }
+/// ```
+///
+/// The default limit of 16 steps fits within the default recursion limit of
+/// the compiler. Each step takes a handful of recursion levels, so when you
+/// raise the limit, raise `#![recursion_limit]` accordingly. The limit must be
+/// an integer between 0 and 255.
+///
+/// ```
+/// # use rukt::rukt_checked;
+/// rukt_checked! {
+///     limit = 8;
+///     let a = 1;
+///     let b = 2;
+///     expand {
+///         assert_eq!($a + $b, 3);
+///     }
+/// }
+/// ```
+///
+/// The error shows the tokens that still needed to be evaluated.
+///
+/// ```compile_fail
+/// # use rukt::rukt_checked;
+/// rukt_checked! {
+///     limit = 2;
+///     let a = 1;
+///     let b = 2;
+///     let c = 3; // error: rukt: evaluation exceeded 2 steps
+/// }
+/// ```
+///
+/// Instead of a number of steps, you can give the recursion limit of the crate
+/// as a shorthand for a matching step limit. The value isn't read from the
+/// `#![recursion_limit]` attribute, so it has to be repeated, and it must be
+/// 128, 256, 512, 1024 or 2048, which map to 16, 32, 64, 128 and 255 steps. This
+/// assumes that each step takes about 8 levels of recursion. Most statements
+/// take less, but function definitions and long `expand` bodies can take more,
+/// so a program with a lot of them can still run into the recursion limit of
+/// the compiler before running out of steps.
+///
+/// ```
+/// # #![recursion_limit = "256"]
+/// # use rukt::rukt_checked;
+/// rukt_checked! {
+///     recursion_limit = 256;
+///     let values = [1 2 3];
+///     for value in values {
+///         expand {
+///             assert!($value > 0);
+///         }
+///     }
+/// }
+/// ```
+///
+/// When the program runs out of steps, the error recommends the next recursion
+/// limit.
+///
+/// ```compile_fail
+/// # use rukt::rukt_checked;
+/// rukt_checked! {
+///     recursion_limit = 128;
+///     let a0 = 0; let a1 = 1; let a2 = 2; let a3 = 3;
+///     let a4 = 4; let a5 = 5; let a6 = 6; let a7 = 7;
+///     let a8 = 8; let a9 = 9; let a10 = 10; let a11 = 11;
+///     let a12 = 12; let a13 = 13; let a14 = 14; let a15 = 15;
+///     let a16 = 16; // error: rukt: evaluation exceeded 16 steps
+/// }
+/// ```
+///
+/// Only the blocks evaluated with this entry point pay for the bookkeeping,
+/// enabling the feature doesn't change how [`rukt`](crate::rukt) blocks are
+/// evaluated.
+#[cfg(feature = "checked")]
+#[macro_export]
+macro_rules! rukt_checked {
+    // one step per 8 levels of recursion, like the default limit of 16 steps
+    (recursion_limit = 128; $($T:tt)*) => {
+        $crate::eval_block_checked!(16 ["set `#![recursion_limit = \"256\"]` and `recursion_limit = 256;`, "] { $($T)* });
+    };
+    (recursion_limit = 256; $($T:tt)*) => {
+        $crate::eval_block_checked!(32 ["set `#![recursion_limit = \"512\"]` and `recursion_limit = 512;`, "] { $($T)* });
+    };
+    (recursion_limit = 512; $($T:tt)*) => {
+        $crate::eval_block_checked!(64 ["set `#![recursion_limit = \"1024\"]` and `recursion_limit = 1024;`, "] { $($T)* });
+    };
+    (recursion_limit = 1024; $($T:tt)*) => {
+        $crate::eval_block_checked!(128 ["set `#![recursion_limit = \"2048\"]` and `recursion_limit = 2048;`, "] { $($T)* });
+    };
+    (recursion_limit = 2048; $($T:tt)*) => {
+        $crate::eval_block_checked!(255 [] { $($T)* });
+    };
+    (recursion_limit = $R:tt; $($T:tt)*) => {
+        compile_error!(concat!(
+            "rukt: unsupported recursion_limit `", stringify!($R), "`, ",
+            "expected 128, 256, 512, 1024 or 2048",
+        ));
+    };
+    (limit = $L:tt; $($T:tt)*) => {
+        $crate::eval_block_checked!($L [
+            "raise the limit with `rukt_checked!(limit = ...; ...)` and `#![recursion_limit = \"...\"]`, "
+        ] { $($T)* });
+    };
+    ($($T:tt)*) => {
+        $crate::rukt_checked!(limit = 16; $($T)*);
+    };
+}
@@ -79,6 +79,138 @@ macro_rules! utils_escape_collect_braces {
 #[doc(inline)]
 pub use utils_escape as escape;
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_unescape {
+    ($T:tt $R:tt [] $N:tt) => {
+        compile_error!("rukt: can't unescape without escape tokens");
+    };
+    ($T:tt $R:tt $E:tt $N:tt) => {
+        $crate::utils_replace_define!($E $T $R [$] $N $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_replace_define {
+    ([$($X:tt)*] $T:tt $R:tt $E:tt $N:tt $D:tt) => {
+        // the replacement is forwarded as an argument since it can contain dollar signs
+        macro_rules! __rukt_replace {
+            ([$($X)* $D($D TT:tt)*] [$D($D RR:tt)*] [$D($D EE:tt)*] $D NN:tt) => {
+                __rukt_replace!([$D($D TT)*] [$D($D RR)* $D($D EE)*] [$D($D EE)*] $D NN);
+            };
+            ([($D($D GG:tt)*) $D($D TT:tt)*] $D RR:tt $D EE:tt $D NN:tt) => {
+                __rukt_replace!([$D($D GG)*] [] $D EE ($crate::utils_escape_collect_parens; [$D($D TT)*] $D RR $D EE (__rukt_replace) $D NN));
+            };
+            ([[$D($D GG:tt)*] $D($D TT:tt)*] $D RR:tt $D EE:tt $D NN:tt) => {
+                __rukt_replace!([$D($D GG)*] [] $D EE ($crate::utils_escape_collect_brackets; [$D($D TT)*] $D RR $D EE (__rukt_replace) $D NN));
+            };
+            ([{$D($D GG:tt)*} $D($D TT:tt)*] $D RR:tt $D EE:tt $D NN:tt) => {
+                __rukt_replace!([$D($D GG)*] [] $D EE ($crate::utils_escape_collect_braces; [$D($D TT)*] $D RR $D EE (__rukt_replace) $D NN));
+            };
+            ([$D HH:tt $D($D TT:tt)*] [$D($D RR:tt)*] $D EE:tt $D NN:tt) => {
+                __rukt_replace!([$D($D TT)*] [$D($D RR)* $D HH] $D EE $D NN);
+            };
+            ([] $D RR:tt $D EE:tt ($D FF:path; $D($D CC:tt)*)) => {
+                $D FF!($D RR $D($D CC)*);
+            };
+        }
+        __rukt_replace!($T $R $E $N);
+    };
+}
+
+/// Replace the given escape tokens with dollar sign tokens `$`.
+///
+/// This is the inverse of [`escape`]. The macro accepts the source tokens,
+/// followed by the initial output tokens, followed by the escape tokens,
+/// followed by a next continuation.
+///
+/// ```
+/// # use rukt::utils::unescape;
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// unescape!([<dollar>name:ident(<dollar>(<dollar>arg:expr),*)] [] [<dollar>] (define; CALL_PATTERN));
+/// assert_eq!(CALL_PATTERN, "$name:ident($ ($arg:expr),*)");
+/// ```
+///
+/// This is a special case of [`replace_token`] matching a sequence of tokens
+/// instead of a single one. The escape tokens are matched by a `macro_rules!`
+/// definition generated for the occasion, so the macro can only be invoked
+/// where items are allowed.
+#[doc(inline)]
+pub use utils_unescape as unescape;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_replace_token {
+    ($T:tt $R:tt [$X:tt] $E:tt $N:tt) => {
+        $crate::utils_replace_token_detect!([=$X=] $T $R $E $N $);
+    };
+    ($T:tt $R:tt [$($X:tt)*] $E:tt $N:tt) => {
+        compile_error!(concat!("rukt: expected a single target token, got `", stringify!($($X)*), "`"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_replace_token_detect {
+    // a generated macro can't match a literal dollar sign so this is exactly what escape does
+    ([$(=)$+] $T:tt $R:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils::escape!($T $R $E $N);
+    };
+    ([=$X:tt=] $T:tt $R:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_replace_define!([$X] $T $R $E $N $);
+    };
+}
+
+/// Replace every occurrence of a target token with the given tokens.
+///
+/// The macro accepts the source tokens, followed by the initial output tokens,
+/// followed by the target token, followed by the replacement tokens, followed
+/// by a next continuation. The replacement is performed at all group levels.
+///
+/// ```
+/// # use rukt::utils::replace_token;
+/// macro_rules! define {
+///     ([$($T:tt)*]) => {
+///         $($T)*
+///     }
+/// }
+/// struct MyStruct(u32);
+/// replace_token!([
+///     impl __SELF__ {
+///         fn new() -> __SELF__ {
+///             __SELF__(42)
+///         }
+///     }
+/// ] [] [__SELF__] [MyStruct] (define;));
+/// assert_eq!(MyStruct::new().0, 42);
+/// ```
+///
+/// The replacement can contain dollar signs `$`, which makes it possible to
+/// turn `crate` into `$crate` for example. [`escape`] is equivalent to using
+/// [`replace_token`] with `$` as the target token.
+///
+/// ```
+/// # use rukt::utils::replace_token;
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// replace_token!([crate::utils::escape] [] [crate] [$crate] (define; PATH));
+/// assert_eq!(PATH.replace(" ", ""), "$crate::utils::escape");
+/// ```
+///
+/// Except when the target token is `$`, the target is matched by a
+/// `macro_rules!` definition generated for the occasion, so the macro can only
+/// be invoked where items are allowed.
+#[doc(inline)]
+pub use utils_replace_token as replace_token;
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! utils_escape_repetitions {
@@ -94,6 +226,79 @@ macro_rules! utils_escape_repetitions {
     ([$H:tt ($($G:tt)*) $($T:tt)*] $R:tt $E:tt $N:tt) => {
         $crate::utils_escape_repetitions_detect!([=$H=] ($($G)*) [$($T)*] $R $E $N);
     };
+    ([$H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([[$($G)*] $($T)*] [$($R)* $H] $E $N);
+    };
+    ([$H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([{$($G)*} $($T)*] [$($R)* $H] $E $N);
+    };
+    // copy runs of plain tokens at once, stopping right before the token preceding the next group
+    ([$A:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A] $E $N);
+    };
+    ([$A:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A] $E $N);
+    };
+    ([$A:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A] $E $N);
+    };
+    ([$A:tt $B:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B] $E $N);
+    };
+    ([$A:tt $B:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B] $E $N);
+    };
+    ([$A:tt $B:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B $C] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B $C] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B $C] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B $C $K] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B $C $K] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B $C $K] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B $C $K $L] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B $C $K $L] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B $C $K $L] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B $C $K $L $M] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B $C $K $L $M] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B $C $K $L $M] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $O:tt $H:tt ($($G:tt)*) $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H ($($G)*) $($T)*] [$($R)* $A $B $C $K $L $M $O] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $O:tt $H:tt [$($G:tt)*] $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H [$($G)*] $($T)*] [$($R)* $A $B $C $K $L $M $O] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $O:tt $H:tt {$($G:tt)*} $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H {$($G)*} $($T)*] [$($R)* $A $B $C $K $L $M $O] $E $N);
+    };
+    ([$A:tt $B:tt $C:tt $K:tt $L:tt $M:tt $O:tt $P:tt $H:tt $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_repetitions!([$H $($T)*] [$($R)* $A $B $C $K $L $M $O $P] $E $N);
+    };
     ([$H:tt $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
         $crate::utils::escape_repetitions!([$($T)*] [$($R)* $H] $E $N);
     };
@@ -194,47 +399,2017 @@ pub use utils_escape_repetitions as escape_repetitions;
 
 #[doc(hidden)]
 #[macro_export]
-macro_rules! utils_select {
-    ([$($T:tt)*] [$([[$($R1:tt)*] [$($R2:tt)*]])+] $N:tt $D:tt) => {
-        macro_rules! __rukt_dispatch {
-            $(
-                ([$($R1)*] ($FF:path; $D($CC:tt)*)) => {
-                    $FF!([$($R2)*] $D($CC)*);
-                };
-            )*
-        }
-        __rukt_dispatch!([$($T)*] $N);
+macro_rules! utils_scan {
+    // tokens are wrapped as `[=$X=]` so that a dollar sign `$` matches `[$(=)$+]`, and each step
+    // copies up to 8 plain tokens before handling the next dollar sign, group, or identifier
+    //
+    // the continuation receives the tokens with the escape in front of repetitions, the identifiers
+    // following a dollar sign (or all of them with `@idents`), and the other tokens following a
+    // dollar sign, `@dollar` stops at the first dollar sign and `@paths` turns `crate::` into
+    // `$crate::`, the other modes flatten groups in place since only `@paths` needs the tokens
+    (@dollar [[$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)*]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)*]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)*]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)*] $W $X $E $N $);
+    };
+    (@$M:ident [[=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)*] $W $X $E $N $);
+    };
+    (@$M:ident [[={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)*] $W $X $E $N $);
+    };
+    (@paths [[=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)*] $W $X $E $N $);
+    };
+    (@idents [[=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* crate] $W $X $E $N $);
+    };
+    (@idents [[=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B $H] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B $H $I] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B $H $I $J] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J $K $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J $K $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J $K $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J $K]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J $K]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J $K]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B $H $I $J $K] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J $K crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J $K $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@dollar [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] $($T:tt)*] $S:tt $R:tt $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([] [] [$D] $($C)*);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J $K $L $($E)*]] $($S)*] [] $W [$($X)* ($($G)*)] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J $K $L $($E)*]] $($S)*] [] $W [$($X)* [$($G)*]] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt [$($X:tt)*] [$($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J $K $L $($E)*]] $($S)*] [] $W [$($X)* {$($G)*}] [$($E)*] $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $L $D crate] $W [$($X)* crate] $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $L $D $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [$(=)$+] [=$Y:tt=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt [$($X:tt)*] $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $L $D $Y] $W [$($X)* $Y] $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=($($G:tt)*)=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[() [$($T)*] [$($R)* $A $B $H $I $J $K $L]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=[$($G:tt)*]=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[[] [$($T)*] [$($R)* $A $B $H $I $J $K $L]] $($S)*] [] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [={$($G:tt)*}=] $($T:tt)*] [$($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths [$([=$G=])*] [[{} [$($T)*] [$($R)* $A $B $H $I $J $K $L]] $($S)*] [] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=($($G:tt)*)=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K $L] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=[$($G:tt)*]=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K $L] $W $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [={$($G:tt)*}=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$([=$G=])* $($T)*] $S [$($R)* $A $B $H $I $J $K $L] $W $X $E $N $);
+    };
+    (@paths [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$Y:ident=] [=::=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan_path!($Y $Y [$($T)*] $S [$($R)* $A $B $H $I $J $K $L] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=crate=] $($T:tt)*] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J $K $L crate] $W $X $E $N $);
+    };
+    (@idents [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$Y:ident=] $($T:tt)*] $S:tt [$($R:tt)*] [$($W:tt)*] $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@idents [$($T)*] $S [$($R)* $A $B $H $I $J $K $L $Y] [$($W)* $Y] $X $E $N $);
+    };
+    (@$M:ident [[=$A:tt=] [=$B:tt=] [=$H:tt=] [=$I:tt=] [=$J:tt=] [=$K:tt=] [=$L:tt=] [=$O:tt=] $($T:tt)+] $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M [$($T)*] $S [$($R)* $A $B $H $I $J $K $L $O] $W $X $E $N $);
+    };
+    // the remaining tokens are plain, which closes the current group
+    (@$M:ident [$([=$A:tt=])*] [[() $T:tt [$($Q:tt)*]] $($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M $T [$($S)*] [$($Q)* ($($R)* $($A)*)] $W $X $E $N $);
+    };
+    (@$M:ident [$([=$A:tt=])*] [[[] $T:tt [$($Q:tt)*]] $($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M $T [$($S)*] [$($Q)* [$($R)* $($A)*]] $W $X $E $N $);
+    };
+    (@$M:ident [$([=$A:tt=])*] [[{} $T:tt [$($Q:tt)*]] $($S:tt)*] [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@$M $T [$($S)*] [$($Q)* {$($R)* $($A)*}] $W $X $E $N $);
+    };
+    (@$M:ident [$([=$A:tt=])*] [] [$($R:tt)*] $W:tt $X:tt $E:tt ($F:path; $($C:tt)*) $D:tt) => {
+        $F!([$($R)* $($A)*] $W $X $($C)*);
     };
 }
 
-/// Select tokens associated with the first matching pattern.
-#[doc(inline)]
-pub use utils_select as select;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_scan_path {
+    // the escape starts with a dollar sign, which turns `crate::` paths into `$crate::` paths
+    (crate $K:ident $T:tt $S:tt [$($R:tt)*] $W:tt $X:tt [$DE:tt $($E:tt)*] $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths $T $S [$($R)* $DE $K ::] $W $X [$DE $($E)*] $N $);
+    };
+    ($_:ident $K:ident $T:tt $S:tt [$($R:tt)*] $W:tt $X:tt $E:tt $N:tt $D:tt) => {
+        $crate::utils_scan!(@paths $T $S [$($R)* $K ::] $W $X $E $N $);
+    };
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_escape_metavariables {
+    ([($($G:tt)*) $($T:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::utils::escape_metavariables!([$($G)*] [] $E ($crate::utils_escape_collect_parens; [$($T)*] $R $E ($crate::utils::escape_metavariables) $N));
+    };
+    ([[$($G:tt)*] $($T:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::utils::escape_metavariables!([$($G)*] [] $E ($crate::utils_escape_collect_brackets; [$($T)*] $R $E ($crate::utils::escape_metavariables) $N));
+    };
+    ([{$($G:tt)*} $($T:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::utils::escape_metavariables!([$($G)*] [] $E ($crate::utils_escape_collect_braces; [$($T)*] $R $E ($crate::utils::escape_metavariables) $N));
+    };
+    ([$H:tt $I:ident $($T:tt)*] $R:tt $E:tt $N:tt) => {
+        $crate::utils_escape_metavariables_detect!([=$H=] $I [$($T)*] $R $E $N);
+    };
+    ([$H:tt $($T:tt)*] [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_metavariables!([$($T)*] [$($R)* $H] $E $N);
+    };
+    ([] [$($R:tt)*] $E:tt ($F:path; $($C:tt)*)) => {
+        $F!([$($R)*] $($C)*);
+    };
+}
 
-    #[test]
-    fn test_escape_repetitions() {
-        macro_rules! check {
-            ($T:tt $expected:expr) => {
-                assert_eq!(stringify!($T), $expected);
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_escape_metavariables_detect {
+    ([$(=)$+] $I:ident $T:tt [$($R:tt)*] [$($E:tt)*] $N:tt) => {
+        $crate::utils::escape_metavariables!($T [$($R)* $($E)* $I] [$($E)*] $N);
+    };
+    // the identifier can't start a metavariable itself so it's copied right away
+    ([=$H:tt=] $I:ident $T:tt [$($R:tt)*] $E:tt $N:tt) => {
+        $crate::utils::escape_metavariables!($T [$($R)* $H $I] $E $N);
+    };
+}
+
+/// Replace the dollar sign `$` prefixing `macro_rules` metavariables with the
+/// given tokens.
+///
+/// The macro accepts the source tokens, followed by the initial output tokens,
+/// followed by the escape tokens, followed by a next continuation.
+///
+/// ```
+/// # use rukt::utils::escape_metavariables;
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// escape_metavariables!([$name:ident($($arg:expr),*)] [] [<dollar>] (define; CALL_PATTERN));
+/// assert_eq!(CALL_PATTERN.replace(" ", ""), "<dollar>name:ident($(<dollar>arg:expr),*)");
+/// ```
+///
+/// Only the dollar signs `$` immediately followed by an identifier are
+/// replaced, which is the opposite of [`escape_repetitions`]. This is useful
+/// when tokens pasted into a generated macro should have their repetitions
+/// interpreted by `macro_rules`, while the metavariables are left for a later
+/// substitution.
+#[doc(inline)]
+pub use utils_escape_metavariables as escape_metavariables;
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_select {
+    // the name of the generated macro is derived from the arguments
+    ($T:tt $R:tt $N:tt $D:tt) => {
+        $crate::utils::hashed_ident!(__rukt_dispatch [$T $R $N] ($crate::utils_select_define; $T $R $N $D));
+    };
+}
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_select {
+    ($T:tt $R:tt $N:tt $D:tt) => {
+        $crate::utils_select_define!(__rukt_dispatch $T $R $N $D);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_select_define {
+    ($F:ident [$($T:tt)*] [$([[$($R1:tt)*] [$($R2:tt)*]])+] $N:tt $D:tt) => {
+        macro_rules! $F {
+            $(
+                // the names of the continuation metavariables can't collide with the captures
+                ([$($R1)*] ($__rukt_next:path; $D($__rukt_context:tt)*)) => {
+                    $__rukt_next!([$($R2)*] $D($__rukt_context)*);
+                };
+            )*
+        }
+        $F!([$($T)*] $N);
+    };
+}
+
+/// Select tokens associated with the first matching pattern.
+///
+/// ```
+/// # use rukt::utils::select;
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// select!([(1 2)] [
+///     [[[$_:tt]] [bracket]]
+///     [[($a:tt $b:tt)] [pair]]
+///     [[($($_:tt)*)] [parens]]
+/// ] (define; SELECTED) $);
+/// assert_eq!(SELECTED, "pair");
+/// ```
+///
+/// The result tokens are transcribed by the generated macro, so they can
+/// reference the metavariables bound by the pattern of their arm, including
+/// repetitions. This makes it possible to extract parts of the tokens and
+/// forward them to the continuation.
+///
+/// ```
+/// # use rukt::utils::select;
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// select!([(1 2 3)] [
+///     [[()] [empty]]
+///     [[($head:tt $($tail:tt)*)] [$head [$($tail)*]]]
+/// ] (define; SPLIT) $);
+/// assert_eq!(SPLIT, "1 [2 3]");
+/// ```
+///
+/// When `select` is invoked from the body of another `macro_rules!`
+/// definition, a metavariable written directly in a pattern would be
+/// substituted by the surrounding macro before reaching `select`. Write the
+/// dollar signs with the `$D` token of the [calling
+/// convention](crate::eval#calling-convention) instead, which is also what the
+/// last argument of `select` expects. Tokens that contain dollar signs but
+/// should be matched literally need to be escaped with [`escape`] first,
+/// otherwise they would bind metavariables as well.
+///
+/// ```
+/// # use rukt::rukt;
+/// macro_rules! first_or_unit {
+///     ({ () $($T:tt)* } $S:tt $N:tt $P:tt $V:tt $D:tt) => {
+///         rukt::utils::select!([$S] [
+///             [[[$D head:tt $D($D tail:tt)*]] [$D head]]
+///             [[$D other:tt] [()]]
+///         ] (first_or_unit; @done { $($T)* } $N $P $V $D) $);
+///     };
+///     ([$R:tt] @done $T:tt ($F:path; $($C:tt)*) $P:tt $V:tt $D:tt) => {
+///         $F!($T $R $($C)* $P $V $);
+///     };
+/// }
+/// rukt! {
+///     let a = [x y z].first_or_unit();
+///     let b = [].first_or_unit();
+///     expand {
+///         assert_eq!(stringify!($a $b), "x ()");
+///     }
+/// }
+/// ```
+///
+/// The metavariables `$__rukt_next` and `$__rukt_context` are used by the
+/// generated macro itself and can't be bound by the patterns. Like with
+/// [`partition`], the `gensym` feature derives the name of the generated macro
+/// from a hash of the arguments, and without it the name is fixed.
+#[doc(inline)]
+pub use utils_select as select;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_strip_delimiters {
+    ([($($X:tt)*)] ($F:path; $($C:tt)*) $E:tt) => {
+        $F!([$($X)*] [parens] $($C)*);
+    };
+    ([[$($X:tt)*]] ($F:path; $($C:tt)*) $E:tt) => {
+        $F!([$($X)*] [brackets] $($C)*);
+    };
+    ([{$($X:tt)*}] ($F:path; $($C:tt)*) $E:tt) => {
+        $F!([$($X)*] [braces] $($C)*);
+    };
+    ($X:tt $N:tt ($F:path; $($C:tt)*)) => {
+        $F!($X $($C)*);
+    };
+}
+
+/// Remove the delimiter of a group and pass the contents to the continuation.
+///
+/// The macro accepts a single group enclosed in brackets `[]`, followed by a
+/// next continuation, followed by an error continuation. The contents of the
+/// group and the kind of delimiter are passed to the next continuation as two
+/// bracketed `[]` groups. The kind of delimiter is one of `parens`, `brackets`
+/// or `braces`, and can be given back to [`with_delimiter`] to wrap tokens in
+/// the same delimiter.
+///
+/// ```
+/// # use rukt::utils::strip_delimiters;
+/// macro_rules! define {
+///     ([$($X:tt)*] [$K:ident] $I:ident) => {
+///         const $I: [&str; 2] = [stringify!($($X)*), stringify!($K)];
+///     }
+/// }
+/// strip_delimiters!([{ a b }] (define; STRIPPED) (unreachable;));
+/// assert_eq!(STRIPPED, ["a b", "braces"]);
+/// ```
+///
+/// Anything other than a single group is passed as-is to the error
+/// continuation, so the caller can report an error that makes sense in its own
+/// context.
+///
+/// ```
+/// # use rukt::utils::strip_delimiters;
+/// macro_rules! invalid {
+///     ([$($X:tt)*] $I:ident) => {
+///         const $I: &str = concat!("expected a group, got `", stringify!($($X)*), "`");
+///     }
+/// }
+/// strip_delimiters!([42] (unreachable;) (invalid; ERROR));
+/// assert_eq!(ERROR, "expected a group, got `42`");
+/// ```
+#[doc(inline)]
+pub use utils_strip_delimiters as strip_delimiters;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_with_delimiter {
+    ([$($X:tt)*] [parens] ($F:path; $($C:tt)*)) => {
+        $F!([($($X)*)] $($C)*);
+    };
+    ([$($X:tt)*] [brackets] ($F:path; $($C:tt)*)) => {
+        $F!([[$($X)*]] $($C)*);
+    };
+    ([$($X:tt)*] [braces] ($F:path; $($C:tt)*)) => {
+        $F!([{$($X)*}] $($C)*);
+    };
+}
+
+/// Wrap tokens in a group and pass it to the continuation.
+///
+/// This is the inverse of [`strip_delimiters`]. The macro accepts the contents
+/// of the group enclosed in brackets `[]`, followed by the kind of delimiter
+/// enclosed in brackets `[]`, followed by a next continuation. The resulting
+/// group is enclosed in brackets `[]` and prepended to the arguments of the
+/// continuation.
+///
+/// ```
+/// # use rukt::utils::with_delimiter;
+/// macro_rules! define {
+///     ([$G:tt] $I:ident) => {
+///         const $I: &str = stringify!($G);
+///     }
+/// }
+/// with_delimiter!([a b] [parens] (define; WRAPPED));
+/// assert_eq!(WRAPPED, "(a b)");
+/// ```
+///
+/// Builtins can forward the group to [`eval::unwrap`](crate::eval::unwrap) to
+/// make it the current subject.
+#[doc(inline)]
+pub use utils_with_delimiter as with_delimiter;
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_partition {
+    // the name of the generated macro is derived from the arguments
+    ($X:tt $R:tt $N:tt) => {
+        $crate::utils::hashed_ident!(__rukt_partition [$X $R $N] ($crate::utils_partition_define; $X $R $N $));
+    };
+}
+
+#[cfg(not(feature = "gensym"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_partition {
+    ($X:tt $R:tt $N:tt) => {
+        $crate::utils_partition_define!(__rukt_partition $X $R $N $);
+    };
+}
+
+#[cfg(feature = "gensym")]
+#[doc(hidden)]
+pub use rukt_macros::utils_hashed_ident as hashed_ident;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_partition_define {
+    ($F:ident [$($X:tt)*] [$($R:tt)*] $N:tt $D:tt) => {
+        macro_rules! $F {
+            // the names of the metavariables can't collide with the ones bound by the matcher
+            (@test [$($R)*] $D __rukt_element:tt [$D($D __rukt_rest:tt)*] [$D($D __rukt_matching:tt)*] $D __rukt_other:tt $D __rukt_next:tt) => {
+                $F!([$D($D __rukt_rest)*] [$D($D __rukt_matching)* $D __rukt_element] $D __rukt_other $D __rukt_next);
+            };
+            (@test $D __rukt_candidate:tt $D __rukt_element:tt $D __rukt_rest:tt $D __rukt_matching:tt [$D($D __rukt_other:tt)*] $D __rukt_next:tt) => {
+                $F!($D __rukt_rest $D __rukt_matching [$D($D __rukt_other)* $D __rukt_element] $D __rukt_next);
+            };
+            ([$D __rukt_element:tt $D($D __rukt_rest:tt)*] $D($D __rukt_state:tt)*) => {
+                $F!(@test [$D __rukt_element] $D __rukt_element [$D($D __rukt_rest)*] $D($D __rukt_state)*);
+            };
+            ([] $D __rukt_matching:tt $D __rukt_other:tt ($D __rukt_next:path; $D($D __rukt_context:tt)*)) => {
+                $D __rukt_next!($D __rukt_matching $D __rukt_other $D($D __rukt_context)*);
+            };
+        }
+        $F!([$($X)*] [] [] $N);
+    };
+}
+
+/// Split tokens into the ones that match a pattern and the ones that don't.
+///
+/// The macro accepts the tokens to split, followed by a matcher enclosed in
+/// brackets `[]`, followed by a next continuation. The matcher is tried against
+/// each top-level token tree on its own, so groups are single elements. The
+/// matching and non-matching tokens are passed to the continuation as two
+/// bracketed `[]` groups, and both keep their original order.
+///
+/// ```
+/// # use rukt::utils::partition;
+/// macro_rules! define {
+///     ([$($A:tt)*] [$($B:tt)*] $I:ident) => {
+///         const $I: [&str; 2] = [stringify!($($A)*), stringify!($($B)*)];
+///     }
+/// }
+/// partition!([a 1 b "c" (d)] [$x:ident] (define; PARTS));
+/// assert_eq!(PARTS, ["a b", "1 \"c\" (d)"]);
+/// ```
+///
+/// The matcher is pasted as-is in a `macro_rules!` definition generated for the
+/// occasion, so its dollar signs must already be the ones that introduce
+/// metavariables. When `partition` is invoked from the body of another
+/// `macro_rules!` definition, write them with the `$D` token of the [calling
+/// convention](crate::eval#calling-convention) like with [`select`]. The tokens
+/// being split are only passed as arguments, so they can contain dollar signs.
+///
+/// Metavariables starting with `__rukt_` are used by the generated macro
+/// itself and can't be bound by the matcher. With the `gensym` feature, the
+/// name of the generated macro is derived from a hash of the arguments, so
+/// calls in the same scope don't shadow each other. Without it, the name is
+/// fixed and only the most recent definition is visible.
+#[doc(inline)]
+pub use utils_partition as partition;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_for_each {
+    ([$($X:tt)*] $E:tt $F:tt) => {
+        $crate::utils_for_each_step!([] [$($X)*] $E $F);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_for_each_step {
+    ($A:tt [$H:tt $($X:tt)*] ($E:path; $($C:tt)*) $F:tt) => {
+        $E!([$H] $A ($crate::utils_for_each_step; [$($X)*] ($E; $($C)*) $F) $($C)*);
+    };
+    ($A:tt [] $E:tt ($F:path; $($C:tt)*)) => {
+        $F!($A $($C)*);
+    };
+}
+
+/// Invoke a macro for each top-level token tree and pass the accumulated
+/// result to the continuation.
+///
+/// The macro accepts the tokens to iterate over, followed by a continuation
+/// for each element, followed by a final continuation. Groups are single
+/// elements, and dollar signs are passed along like any other token.
+///
+/// ```
+/// # use rukt::utils::for_each;
+/// macro_rules! wrap {
+///     ([$X:tt] [$($A:tt)*] ($F:path; $($C:tt)*)) => {
+///         $F!([$($A)* ($X)] $($C)*);
+///     };
+/// }
+/// macro_rules! define {
+///     ([$($T:tt)*] $I:ident) => {
+///         const $I: &str = stringify!($($T)*);
+///     }
+/// }
+/// for_each!([a b [c d]] (wrap;) (define; WRAPPED));
+/// assert_eq!(WRAPPED.replace(" ", ""), "(a)(b)([cd])");
+/// ```
+///
+/// # Re-entry protocol
+///
+/// The accumulator starts out as an empty bracketed `[]` group. For each
+/// element, the macro of the element continuation is invoked with the element
+/// and the current accumulator, both enclosed in brackets `[]`, followed by a
+/// continuation for resuming the iteration, followed by the context of the
+/// element continuation.
+///
+/// ```
+/// macro_rules! element {
+///     ([$X:tt] [$($A:tt)*] ($F:path; $($C:tt)*) $($context:tt)*) => {
+///         $F!([$($A)* $X] $($C)*);
+///     };
+/// }
+/// ```
+///
+/// The macro must expand to a call to the resume continuation with the updated
+/// accumulator prepended to its context, which moves on to the next element.
+/// The context of the resume continuation holds the rest of the iteration
+/// state and should be forwarded as-is. Once there are no elements left, the
+/// final accumulator is prepended to the context of the final continuation.
+///
+/// The element continuation can also stop the iteration early by expanding to
+/// something else, like a call to another macro or a `compile_error!`.
+#[doc(inline)]
+pub use utils_for_each as for_each;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_split_at {
+    ($X:tt [$K:tt] $N:tt) => {
+        $crate::utils_split_at_literal!($K $X $K $N);
+    };
+    ($X:tt [$($U:tt)*] $N:tt) => {
+        $crate::utils_split_at_unary!([$($U)*] [] $X $N);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_split_at_literal {
+    // forward the count as a tt, a captured literal would no longer match the integer table
+    ($L:literal $X:tt $K:tt $N:tt) => {
+        $crate::utils::integers!(($crate::utils_split_at_decimal; $X $K $N $));
+    };
+    ($L:tt $X:tt $K:tt $N:tt) => {
+        $crate::utils_split_at_unary!([$K] [] $X $N);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_split_at_unary {
+    ([$U0:tt $U1:tt $U2:tt $U3:tt $U4:tt $U5:tt $U6:tt $U7:tt $($U:tt)*] [$($L:tt)*] [$X0:tt $X1:tt $X2:tt $X3:tt $X4:tt $X5:tt $X6:tt $X7:tt $($X:tt)*] $N:tt) => {
+        $crate::utils_split_at_unary!([$($U)*] [$($L)* $X0 $X1 $X2 $X3 $X4 $X5 $X6 $X7] [$($X)*] $N);
+    };
+    ([$U:tt $($UR:tt)*] [$($L:tt)*] [$X:tt $($XR:tt)*] $N:tt) => {
+        $crate::utils_split_at_unary!([$($UR)*] [$($L)* $X] [$($XR)*] $N);
+    };
+    // the count or the tokens ran out, in which case the count gets clamped
+    ($U:tt $L:tt $X:tt ($F:path; $($C:tt)*)) => {
+        $F!($L $X $($C)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_split_at_decimal {
+    ($G:tt [$($X:tt)*] $K:tt $N:tt $D:tt) => {
+        // the position of the count in its group of sixteen integers is the number of tokens left to take
+        macro_rules! __rukt_split_at {
+            ([[$K $D($D S0:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)*] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $K $D($D S1:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $K $D($D S2:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $K $D($D S3:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $K $D($D S4:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $K $D($D S5:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $K $D($D S6:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $K $D($D S7:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $K $D($D S8:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $K $D($D S9:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $K $D($D S10:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $D S10:tt $K $D($D S11:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $D S10:tt $D S11:tt $K $D($D S12:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D X11:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10 $D X11] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $D S10:tt $D S11:tt $D S12:tt $K $D($D S13:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D X11:tt $D X12:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10 $D X11 $D X12] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $D S10:tt $D S11:tt $D S12:tt $D S13:tt $K $D($D S14:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D X11:tt $D X12:tt $D X13:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10 $D X11 $D X12 $D X13] [$D($D R)*] $D($D C)*);
+            };
+            ([[$D S0:tt $D S1:tt $D S2:tt $D S3:tt $D S4:tt $D S5:tt $D S6:tt $D S7:tt $D S8:tt $D S9:tt $D S10:tt $D S11:tt $D S12:tt $D S13:tt $D S14:tt $K $D($D S15:tt)*] $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D X11:tt $D X12:tt $D X13:tt $D X14:tt $D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10 $D X11 $D X12 $D X13 $D X14] [$D($D R)*] $D($D C)*);
+            };
+            ([$D H:tt $D($D G:tt)*] [$D($D L:tt)*] [$D X0:tt $D X1:tt $D X2:tt $D X3:tt $D X4:tt $D X5:tt $D X6:tt $D X7:tt $D X8:tt $D X9:tt $D X10:tt $D X11:tt $D X12:tt $D X13:tt $D X14:tt $D X15:tt $D($D R:tt)*] $D N:tt) => {
+                __rukt_split_at!([$D($D G)*] [$D($D L)* $D X0 $D X1 $D X2 $D X3 $D X4 $D X5 $D X6 $D X7 $D X8 $D X9 $D X10 $D X11 $D X12 $D X13 $D X14 $D X15] [$D($D R)*] $D N);
+            };
+            ([] $D L:tt [$D X:tt $D($D R:tt)*] $D N:tt) => {
+                compile_error!(concat!("rukt: can't split at `", stringify!($K), "`, expected an integer between 0 and 255"));
+            };
+            ($D G:tt [$D($D L:tt)*] [$D($D R:tt)*] ($D F:path; $D($D C:tt)*)) => {
+                $D F!([$D($D L)* $D($D R)*] [] $D($D C)*);
+            };
+        }
+        __rukt_split_at!($G [] [$($X)*] $N);
+    };
+}
+
+/// Split tokens at the given index and pass both parts to the continuation.
+///
+/// The macro accepts the tokens to split, followed by the number of tokens to
+/// put in the first part, followed by a next continuation. The two parts are
+/// bracketed `[]` groups prepended to the arguments of the continuation.
+///
+/// ```
+/// # use rukt::utils::split_at;
+/// macro_rules! define {
+///     ([$($A:tt)*] [$($B:tt)*] $I:ident) => {
+///         const $I: [&str; 2] = [stringify!($($A)*), stringify!($($B)*)];
+///     }
+/// }
+/// split_at!([(a b) c $d e] [3] (define; PARTS));
+/// assert_eq!(PARTS, ["(a b) c $", "d e"]);
+/// ```
+///
+/// The count is either an integer literal between `0` and `255`, or any other
+/// tokens in which case the number of tokens is used as the count. Groups count
+/// as a single token. When the count exceeds the number of tokens, all the
+/// tokens end up in the first part and the second one is empty.
+///
+/// ```
+/// # use rukt::utils::split_at;
+/// macro_rules! define {
+///     ([$($A:tt)*] [$($B:tt)*] $I:ident) => {
+///         const $I: [&str; 2] = [stringify!($($A)*), stringify!($($B)*)];
+///     }
+/// }
+/// split_at!([a b c] [_ _] (define; UNARY));
+/// split_at!([a b c] [42] (define; CLAMPED));
+/// assert_eq!(UNARY, ["a b", "c"]);
+/// assert_eq!(CLAMPED, ["a b c", ""]);
+/// ```
+///
+/// Decimal counts are looked up in the table of [`integers`] by a
+/// `macro_rules!` definition generated for the occasion, so they can only be
+/// used where items are allowed. Sixteen tokens are moved at a time until the
+/// group containing the count is reached, and the remaining tokens are matched
+/// positionally.
+#[doc(inline)]
+pub use utils_split_at as split_at;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_tokens_equal {
+    // groups can contain dollar signs, escape them before pasting the operands in the generated macro
+    ([($($X:tt)*) $S:tt] $N:tt) => {
+        $crate::utils::escape!([[($($X)*)] [$S]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([[$($X:tt)*] $S:tt] $N:tt) => {
+        $crate::utils::escape!([[[$($X)*]] [$S]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([{$($X:tt)*} $S:tt] $N:tt) => {
+        $crate::utils::escape!([[{$($X)*}] [$S]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([$R:tt ($($X:tt)*)] $N:tt) => {
+        $crate::utils::escape!([[$R] [($($X)*)]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([$R:tt [$($X:tt)*]] $N:tt) => {
+        $crate::utils::escape!([[$R] [[$($X)*]]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([$R:tt {$($X:tt)*}] $N:tt) => {
+        $crate::utils::escape!([[$R] [{$($X)*}]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ([$R:tt $S:tt] $N:tt) => {
+        $crate::utils_tokens_equal_simple!([=$R=] [=$S=] [$R $S] $N);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_tokens_equal_simple {
+    ([$(=)$+] $_:tt [$R:tt $S:tt] $N:tt) => {
+        $crate::utils::escape!([[$R] [$S]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    ($_:tt [$(=)$+] [$R:tt $S:tt] $N:tt) => {
+        $crate::utils::escape!([[$R] [$S]] [] [__rukt_dollar] ($crate::utils_tokens_equal_escaped; $N $));
+    };
+    // single tokens other than dollar signs can be pasted as-is
+    ($A:tt $B:tt [$R:tt $S:tt] $N:tt) => {
+        $crate::utils_tokens_equal_escaped!([[$R] [$S]] $N $);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_tokens_equal_escaped {
+    // tokens that don't match themselves come from opaque fragments
+    ([[$S1:tt] [$S2:tt]] ($F:path; $($C:tt)*) $D:tt) => {
+        macro_rules! __rukt_compare {
+            ($S1 $S1 $D($D CC:tt)*) => {
+                $F!(true $D($D CC)*);
+            };
+            ($S1 $S2 $D($D CC:tt)*) => {
+                $F!(false $D($D CC)*);
+            };
+            ($S1 $D X:tt $D($D CC:tt)*) => {
+                $crate::eval_opaque!([$S2] "compare");
+            };
+            ($D X:tt $D($D CC:tt)*) => {
+                $crate::eval_opaque!([$S1] "compare");
+            };
+        }
+        __rukt_compare!($S1 $S2 $($C)*);
+    };
+}
+
+/// Compare two token trees and pass `true` or `false` to the continuation.
+///
+/// The macro accepts the two token trees to compare, followed by a next
+/// continuation. The result is prepended to the arguments of the continuation.
+///
+/// ```
+/// # use rukt::utils::tokens_equal;
+/// macro_rules! define {
+///     ($R:literal $I:ident) => {
+///         const $I: bool = $R;
+///     }
+/// }
+/// tokens_equal!([[$x (a, b)] [$x (a, b)]] (define; SAME));
+/// tokens_equal!([[1] [0x1]] (define; DIFFERENT));
+/// assert_eq!([SAME, DIFFERENT], [true, false]);
+/// ```
+///
+/// This is what the `==` and `!=` [operators](crate::eval::operator) use. The
+/// comparison is structural: groups are equal when they have the same
+/// delimiter and equal tokens, and other tokens are compared by their spelling.
+/// Literals are never compared by value, so `1`, `0x1` and `1u8` are all
+/// different tokens. Whitespace between tokens doesn't matter, but punctuation
+/// split into separate tokens like `: :` is different from `::`.
+///
+/// Values parsed with fragment specifiers other than `tt`, `ident` and
+/// `lifetime` are opaque, so comparing them is an error. The generated
+/// `macro_rules!` definition means that the macro can only be invoked where
+/// items are allowed.
+#[doc(inline)]
+pub use utils_tokens_equal as tokens_equal;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_integers {
+    (($F:path; $($C:tt)*)) => {
+        $F!([
+            [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15]
+            [16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31]
+            [32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47]
+            [48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63]
+            [64 65 66 67 68 69 70 71 72 73 74 75 76 77 78 79]
+            [80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95]
+            [96 97 98 99 100 101 102 103 104 105 106 107 108 109 110 111]
+            [112 113 114 115 116 117 118 119 120 121 122 123 124 125 126 127]
+            [128 129 130 131 132 133 134 135 136 137 138 139 140 141 142 143]
+            [144 145 146 147 148 149 150 151 152 153 154 155 156 157 158 159]
+            [160 161 162 163 164 165 166 167 168 169 170 171 172 173 174 175]
+            [176 177 178 179 180 181 182 183 184 185 186 187 188 189 190 191]
+            [192 193 194 195 196 197 198 199 200 201 202 203 204 205 206 207]
+            [208 209 210 211 212 213 214 215 216 217 218 219 220 221 222 223]
+            [224 225 226 227 228 229 230 231 232 233 234 235 236 237 238 239]
+            [240 241 242 243 244 245 246 247 248 249 250 251 252 253 254 255]
+        ] $($C)*);
+    };
+}
+
+/// Pass the supported integer literals in ascending order to the continuation.
+///
+/// Rukt can't do arithmetic on integer literals directly. Operations that need
+/// to order integers look them up in this table instead, which limits them to
+/// integers between `0` and `255`. The integers are split into groups of
+/// sixteen, so that looking up a large integer doesn't require walking through
+/// all the previous ones.
+///
+/// ```
+/// # use rukt::utils::integers;
+/// macro_rules! first {
+///     ([[$A:tt $B:tt $C:tt $($T:tt)*] $($G:tt)*] $I:ident) => {
+///         const $I: [u32; 3] = [$A, $B, $C];
+///     }
+/// }
+/// integers!((first; FIRST));
+/// assert_eq!(FIRST, [0, 1, 2]);
+/// ```
+#[doc(inline)]
+pub use utils_integers as integers;
+
+#[cfg(not(feature = "nightly-metavar-expr"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_count {
+    ([$($X:tt)*] $N:tt) => {
+        $crate::utils::integers!(($crate::utils_count_walk; [$($X)*] $N));
+    };
+}
+
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_count {
+    ([$($X:tt)*] $N:tt) => {
+        $crate::utils_count_walk!([_ _ _ _ _ _ _ _ _ _ _ _ _ _ _ _] [$($X)*] [$($X)*] $N);
+    };
+}
+
+#[cfg(feature = "nightly-metavar-expr")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_count_walk {
+    // the tokens are only walked to enforce the same limit as the portable implementation
+    ([$K:tt $($G:tt)*] [$X0:tt $X1:tt $X2:tt $X3:tt $X4:tt $X5:tt $X6:tt $X7:tt $X8:tt $X9:tt $X10:tt $X11:tt $X12:tt $X13:tt $X14:tt $X15:tt $($X:tt)*] $O:tt $N:tt) => {
+        $crate::utils_count_walk!([$($G)*] [$($X)*] $O $N);
+    };
+    ([] $X:tt $O:tt $N:tt) => {
+        compile_error!("rukt: can't count more than 255 tokens");
+    };
+    ($G:tt $X:tt [$($O:tt)*] ($F:path; $($C:tt)*)) => {
+        $F!(${count($O)} $($C)*);
+    };
+}
+
+#[cfg(not(feature = "nightly-metavar-expr"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_count_walk {
+    // skip a whole group of integers for every sixteen tokens
+    ([$K:tt $($G:tt)*] [$X0:tt $X1:tt $X2:tt $X3:tt $X4:tt $X5:tt $X6:tt $X7:tt $X8:tt $X9:tt $X10:tt $X11:tt $X12:tt $X13:tt $X14:tt $X15:tt $($X:tt)*] $N:tt) => {
+        $crate::utils_count_walk!([$($G)*] [$($X)*] $N);
+    };
+    ([[$($K:tt)*] $($G:tt)*] $X:tt $N:tt) => {
+        $crate::utils_count_walk!(@group [$($K)*] $X $N);
+    };
+    ([] $X:tt $N:tt) => {
+        compile_error!("rukt: can't count more than 255 tokens");
+    };
+    (@group [$K0:tt $K1:tt $K2:tt $K3:tt $($K:tt)*] [$X0:tt $X1:tt $X2:tt $X3:tt $($X:tt)*] $N:tt) => {
+        $crate::utils_count_walk!(@group [$($K)*] [$($X)*] $N);
+    };
+    (@group [$K0:tt $($K:tt)*] [$X0:tt $($X:tt)*] $N:tt) => {
+        $crate::utils_count_walk!(@group [$($K)*] [$($X)*] $N);
+    };
+    (@group [$K0:tt $($K:tt)*] [] ($F:path; $($C:tt)*)) => {
+        $F!($K0 $($C)*);
+    };
+}
+
+/// Count the top-level token trees and pass the result to the continuation.
+///
+/// The macro accepts the tokens to count, followed by a next continuation. The
+/// count is an integer literal prepended to the arguments of the continuation.
+///
+/// ```
+/// # use rukt::utils::count;
+/// macro_rules! define {
+///     ($K:literal $I:ident) => {
+///         const $I: u32 = $K;
+///     }
+/// }
+/// count!([$name:ident($($arg:expr),*)] (define; COUNT));
+/// assert_eq!(COUNT, 5);
+/// ```
+///
+/// Groups count as a single token tree regardless of their content. Like
+/// [`integers`], the count can't exceed `255`. The tokens are consumed sixteen
+/// at a time, so counting long sequences only takes a few recursive calls.
+#[doc(inline)]
+pub use utils_count as count;
+
+#[cfg(not(feature = "proc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_stringify_compact {
+    ([$($X:tt)*] $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($X)*] [] [] [] $N);
+    };
+}
+
+#[cfg(not(feature = "proc"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_stringify_compact_walk {
+    // entering a group saves the remaining tokens and the closing delimiter on the stack
+    ([($($X:tt)*) $($R:tt)*] [$($E:tt)*] [$($O:tt)*] [$($K:tt)*] $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($X)*] [] [$($O)* $($E)* "(",] [[[$($R)*] ")"] $($K)*] $N);
+    };
+    ([[$($X:tt)*] $($R:tt)*] [$($E:tt)*] [$($O:tt)*] [$($K:tt)*] $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($X)*] [] [$($O)* $($E)* "[",] [[[$($R)*] "]"] $($K)*] $N);
+    };
+    ([{$($X:tt)*} $($R:tt)*] [$($E:tt)*] [$($O:tt)*] [$($K:tt)*] $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($X)*] [] [$($O)* $($E)* "{",] [[[$($R)*] "}"] $($K)*] $N);
+    };
+    ([$X:tt $($R:tt)*] [$($E:tt)*] [$($O:tt)*] $K:tt $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($R)*] [" ",] [$($O)* $($E)* ::core::stringify!($X),] $K $N);
+    };
+    ([] $E:tt [$($O:tt)*] [[[$($R:tt)*] $Z:tt] $($K:tt)*] $N:tt) => {
+        $crate::utils_stringify_compact_walk!([$($R)*] [" ",] [$($O)* $Z,] [$($K)*] $N);
+    };
+    ([] $E:tt [$($O:tt)*] [] ($F:path; $($C:tt)*)) => {
+        $F!([::core::concat!($($O)*)] $($C)*);
+    };
+}
+
+/// Build a whitespace-normalized string from tokens and pass it to the
+/// continuation.
+///
+/// The macro accepts the tokens to stringify, followed by a next continuation.
+/// Unlike [`stringify!`], which decides where to put spaces on its own, the
+/// string follows fixed rules: token trees are separated by a single space,
+/// and delimiters are tight against their contents. Punctuation made of
+/// multiple characters like `::` or `=>` is a single token tree, so it's never
+/// split. The result is a [`concat!`] expression enclosed in brackets `[]`,
+/// prepended to the arguments of the continuation.
+///
+/// ```
+/// # use rukt::utils::stringify_compact;
+/// macro_rules! define {
+///     ([$($S:tt)*] $I:ident) => {
+///         const $I: &str = $($S)*;
+///     }
+/// }
+/// stringify_compact!([$($name:ident)::+ => { f(x, [1]) }] (define; PATTERN));
+/// assert_eq!(PATTERN, "$ ($ name : ident) :: + => {f (x , [1])}");
+/// ```
+///
+/// Comparing the strings is a reliable way to check generated tokens, since
+/// the same tokens always produce the same string. The utility walks the
+/// tokens one by one, so long sequences can require raising the
+/// `recursion_limit`. With the `proc` feature, the string is built by a
+/// procedural macro in a single step instead, and the result is the same. With
+/// the `strings` feature, the `stringify_compact` builtin produces the same
+/// string as a literal.
+#[doc(inline)]
+pub use utils_stringify_compact as stringify_compact;
+
+#[cfg(feature = "proc")]
+#[doc(hidden)]
+pub use rukt_macros::utils_stringify_compact;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_split_punct {
+    ([<<=] ($F:path; $($C:tt)*)) => {
+        $F!([< < =] $($C)*);
+    };
+    ([>>=] ($F:path; $($C:tt)*)) => {
+        $F!([> > =] $($C)*);
+    };
+    ([...] ($F:path; $($C:tt)*)) => {
+        $F!([. . .] $($C)*);
+    };
+    ([..=] ($F:path; $($C:tt)*)) => {
+        $F!([. . =] $($C)*);
+    };
+    ([::] ($F:path; $($C:tt)*)) => {
+        $F!([: :] $($C)*);
+    };
+    ([->] ($F:path; $($C:tt)*)) => {
+        $F!([- >] $($C)*);
+    };
+    ([=>] ($F:path; $($C:tt)*)) => {
+        $F!([= >] $($C)*);
+    };
+    ([==] ($F:path; $($C:tt)*)) => {
+        $F!([= =] $($C)*);
+    };
+    ([!=] ($F:path; $($C:tt)*)) => {
+        $F!([! =] $($C)*);
+    };
+    ([<=] ($F:path; $($C:tt)*)) => {
+        $F!([< =] $($C)*);
+    };
+    ([>=] ($F:path; $($C:tt)*)) => {
+        $F!([> =] $($C)*);
+    };
+    ([&&] ($F:path; $($C:tt)*)) => {
+        $F!([& &] $($C)*);
+    };
+    ([||] ($F:path; $($C:tt)*)) => {
+        $F!([| |] $($C)*);
+    };
+    ([+=] ($F:path; $($C:tt)*)) => {
+        $F!([+ =] $($C)*);
+    };
+    ([-=] ($F:path; $($C:tt)*)) => {
+        $F!([- =] $($C)*);
+    };
+    ([*=] ($F:path; $($C:tt)*)) => {
+        $F!([* =] $($C)*);
+    };
+    ([/=] ($F:path; $($C:tt)*)) => {
+        $F!([/ =] $($C)*);
+    };
+    ([%=] ($F:path; $($C:tt)*)) => {
+        $F!([% =] $($C)*);
+    };
+    ([^=] ($F:path; $($C:tt)*)) => {
+        $F!([^ =] $($C)*);
+    };
+    ([&=] ($F:path; $($C:tt)*)) => {
+        $F!([& =] $($C)*);
+    };
+    ([|=] ($F:path; $($C:tt)*)) => {
+        $F!([| =] $($C)*);
+    };
+    ([<<] ($F:path; $($C:tt)*)) => {
+        $F!([< <] $($C)*);
+    };
+    ([>>] ($F:path; $($C:tt)*)) => {
+        $F!([> >] $($C)*);
+    };
+    ([..] ($F:path; $($C:tt)*)) => {
+        $F!([. .] $($C)*);
+    };
+    ([$P:tt] $N:tt) => {
+        $crate::utils_punct_single!([$P] $N split);
+    };
+    ([$($X:tt)*] $N:tt) => {
+        compile_error!(concat!("rukt: can't split `", stringify!($($X)*), "`, expected a single punctuation token"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_join_punct {
+    ([< < =] ($F:path; $($C:tt)*)) => {
+        $F!([<<=] $($C)*);
+    };
+    ([> > =] ($F:path; $($C:tt)*)) => {
+        $F!([>>=] $($C)*);
+    };
+    ([. . .] ($F:path; $($C:tt)*)) => {
+        $F!([...] $($C)*);
+    };
+    ([. . =] ($F:path; $($C:tt)*)) => {
+        $F!([..=] $($C)*);
+    };
+    ([: :] ($F:path; $($C:tt)*)) => {
+        $F!([::] $($C)*);
+    };
+    ([- >] ($F:path; $($C:tt)*)) => {
+        $F!([->] $($C)*);
+    };
+    ([= >] ($F:path; $($C:tt)*)) => {
+        $F!([=>] $($C)*);
+    };
+    ([= =] ($F:path; $($C:tt)*)) => {
+        $F!([==] $($C)*);
+    };
+    ([! =] ($F:path; $($C:tt)*)) => {
+        $F!([!=] $($C)*);
+    };
+    ([< =] ($F:path; $($C:tt)*)) => {
+        $F!([<=] $($C)*);
+    };
+    ([> =] ($F:path; $($C:tt)*)) => {
+        $F!([>=] $($C)*);
+    };
+    ([& &] ($F:path; $($C:tt)*)) => {
+        $F!([&&] $($C)*);
+    };
+    ([| |] ($F:path; $($C:tt)*)) => {
+        $F!([||] $($C)*);
+    };
+    ([+ =] ($F:path; $($C:tt)*)) => {
+        $F!([+=] $($C)*);
+    };
+    ([- =] ($F:path; $($C:tt)*)) => {
+        $F!([-=] $($C)*);
+    };
+    ([* =] ($F:path; $($C:tt)*)) => {
+        $F!([*=] $($C)*);
+    };
+    ([/ =] ($F:path; $($C:tt)*)) => {
+        $F!([/=] $($C)*);
+    };
+    ([% =] ($F:path; $($C:tt)*)) => {
+        $F!([%=] $($C)*);
+    };
+    ([^ =] ($F:path; $($C:tt)*)) => {
+        $F!([^=] $($C)*);
+    };
+    ([& =] ($F:path; $($C:tt)*)) => {
+        $F!([&=] $($C)*);
+    };
+    ([| =] ($F:path; $($C:tt)*)) => {
+        $F!([|=] $($C)*);
+    };
+    ([< <] ($F:path; $($C:tt)*)) => {
+        $F!([<<] $($C)*);
+    };
+    ([> >] ($F:path; $($C:tt)*)) => {
+        $F!([>>] $($C)*);
+    };
+    ([. .] ($F:path; $($C:tt)*)) => {
+        $F!([..] $($C)*);
+    };
+    ([$P:tt] $N:tt) => {
+        $crate::utils_punct_single!([$P] $N join);
+    };
+    ([$($X:tt)*] $N:tt) => {
+        compile_error!(concat!("rukt: can't join `", stringify!($($X)*), "`, the characters don't form a punctuation token"));
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! utils_punct_single {
+    // single characters are their own only component
+    ([+] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([+] $($C)*);
+    };
+    ([-] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([-] $($C)*);
+    };
+    ([*] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([*] $($C)*);
+    };
+    ([/] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([/] $($C)*);
+    };
+    ([%] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([%] $($C)*);
+    };
+    ([^] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([^] $($C)*);
+    };
+    ([!] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([!] $($C)*);
+    };
+    ([&] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([&] $($C)*);
+    };
+    ([|] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([|] $($C)*);
+    };
+    ([=] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([=] $($C)*);
+    };
+    ([<] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([<] $($C)*);
+    };
+    ([>] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([>] $($C)*);
+    };
+    ([@] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([@] $($C)*);
+    };
+    ([.] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([.] $($C)*);
+    };
+    ([,] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([,] $($C)*);
+    };
+    ([;] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([;] $($C)*);
+    };
+    ([:] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([:] $($C)*);
+    };
+    ([#] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([#] $($C)*);
+    };
+    ([$] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([$] $($C)*);
+    };
+    ([?] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([?] $($C)*);
+    };
+    ([~] ($F:path; $($C:tt)*) $O:ident) => {
+        $F!([~] $($C)*);
+    };
+    ([$X:tt] $N:tt split) => {
+        compile_error!(concat!("rukt: can't split `", stringify!($X), "`, expected a single punctuation token"));
+    };
+    ([$X:tt] $N:tt join) => {
+        compile_error!(concat!("rukt: can't join `", stringify!($X), "`, the characters don't form a punctuation token"));
+    };
+}
+
+/// Split a punctuation token into its characters and pass them to the
+/// continuation.
+///
+/// The macro accepts the punctuation token enclosed in brackets `[]`, followed
+/// by a next continuation. Punctuation like `::` or `..=` is a single token
+/// tree, which makes it impossible to tell `..` and `..=` apart with a `tt`
+/// fragment without listing both. The characters are passed as separate
+/// tokens enclosed in brackets `[]`, prepended to the arguments of the
+/// continuation.
+///
+/// ```
+/// # use rukt::utils::split_punct;
+/// macro_rules! define {
+///     ([$($X:tt)*] $I:ident) => {
+///         const $I: &[&str] = &[$(stringify!($X)),*];
+///     }
+/// }
+/// split_punct!([..=] (define; RANGE));
+/// split_punct!([;] (define; SEMI));
+/// assert_eq!(RANGE, [".", ".", "="]);
+/// assert_eq!(SEMI, [";"]);
+/// ```
+///
+/// The supported tokens are the single-character punctuation tokens, and the
+/// tokens made of several characters that Rust recognizes: `<<=` `>>=` `...`
+/// `..=` `::` `->` `=>` `==` `!=` `<=` `>=` `&&` `||` `+=` `-=` `*=` `/=`
+/// `%=` `^=` `&=` `|=` `<<` `>>` `..`. Anything else is an error.
+///
+/// ```compile_fail
+/// # use rukt::utils::split_punct;
+/// macro_rules! define {
+///     ($X:tt) => {}
+/// }
+/// split_punct!([foo] (define;)); // error: rukt: can't split `foo`, expected a single punctuation token
+/// ```
+#[doc(inline)]
+pub use utils_split_punct as split_punct;
+
+/// Join punctuation characters into a single token and pass it to the
+/// continuation.
+///
+/// This is the inverse of [`split_punct`]. The macro accepts the characters
+/// enclosed in brackets `[]`, followed by a next continuation. The token is
+/// enclosed in brackets `[]` and prepended to the arguments of the
+/// continuation.
+///
+/// ```
+/// # use rukt::utils::join_punct;
+/// macro_rules! define {
+///     ([$X:tt] $I:ident) => {
+///         const $I: &str = stringify!($X);
+///     }
+/// }
+/// join_punct!([: :] (define; PATH));
+/// assert_eq!(PATH, "::");
+/// ```
+///
+/// Only the combinations supported by [`split_punct`] can be joined.
+/// Characters that don't form a single token, like `- <`, are rejected.
+///
+/// ```compile_fail
+/// # use rukt::utils::join_punct;
+/// macro_rules! define {
+///     ($X:tt) => {}
+/// }
+/// join_punct!([- <] (define;)); // error: rukt: can't join `- <`, the characters don't form a punctuation token
+/// ```
+#[doc(inline)]
+pub use utils_join_punct as join_punct;
+
+/// Create a new identifier from a base name and a counter.
+///
+/// This utility requires the `gensym` feature, because declarative macros
+/// can't put identifiers together.
+///
+/// ```toml
+/// [dependencies]
+/// rukt = { version = "*", features = ["gensym"] }
+/// ```
+///
+/// The macro accepts the base name, followed by the counter enclosed in
+/// brackets `[]`, followed by a next continuation. The identifier is the base
+/// name with the counter appended after an underscore, without the `r#` prefix
+/// of raw identifiers. It's prepended to the arguments of the continuation
+/// along with the next value of the counter, so that the continuation can
+/// thread it to the following invocation.
+///
+/// ```
+/// use rukt::utils::gensym;
+/// macro_rules! define_first {
+///     ($I:ident $K:tt) => {
+///         const $I: u32 = 1;
+///         gensym!(VALUE $K (define_second;));
+///     };
+/// }
+/// macro_rules! define_second {
+///     ($I:ident $K:tt) => {
+///         const $I: u32 = 2;
+///     };
+/// }
+/// gensym!(VALUE [] (define_first;));
+/// assert_eq!([VALUE_0, VALUE_1], [1, 2]);
+/// ```
+///
+/// An empty counter starts at `0`. The identifiers are only unique along the
+/// chain of invocations that thread the counter. There's no global state, so
+/// separate chains starting with the same base name and counter produce the
+/// same identifiers. Use different base names for unrelated chains, or keep
+/// them in separate modules. The identifier has the span of the base name, so
+/// it's visible wherever the base name would be.
+#[cfg(feature = "gensym")]
+#[doc(inline)]
+pub use rukt_macros::utils_gensym as gensym;
+
+/// Concatenate identifiers and integers into a new identifier, and pass it to
+/// the continuation.
+///
+/// Like [`gensym`], this is implemented as a procedural macro and requires the
+/// `gensym` feature. The macro accepts the parts enclosed in brackets `[]`,
+/// followed by a next continuation. The parts can be identifiers or integer
+/// literals, and the `r#` prefix of raw identifiers is dropped. The identifier
+/// is prepended to the arguments of the continuation.
+///
+/// ```
+/// use rukt::utils::concat_idents;
+/// macro_rules! define {
+///     ($I:ident $value:expr) => {
+///         const $I: u32 = $value;
+///     };
+/// }
+/// concat_idents!([MAX_ VALUE _ 2] (define; 42));
+/// assert_eq!(MAX_VALUE_2, 42);
+/// ```
+///
+/// The identifier has the span of the first part, so it's visible wherever the
+/// first part would be. Starting with an integer is an error, since the result
+/// wouldn't be a valid identifier.
+#[cfg(feature = "gensym")]
+#[doc(inline)]
+pub use rukt_macros::utils_concat_idents as concat_idents;
+
+/// Give identifiers the span of a reference token, and pass them to the
+/// continuation.
+///
+/// Like [`gensym`], this is implemented as a procedural macro and requires the
+/// `gensym` feature. The macro accepts the reference token enclosed in brackets
+/// `[]`, followed by the identifiers enclosed in brackets `[]`, followed by a
+/// next continuation. The identifiers are passed to the continuation as a
+/// bracketed `[]` group.
+///
+/// Identifiers written in the body of a `macro_rules!` definition are
+/// [hygienic](https://doc.rust-lang.org/reference/macros-by-example.html#hygiene),
+/// so local variables and metavariables named after them are invisible to the
+/// caller. Once respanned, the identifiers resolve like the reference token
+/// would, so taking the reference from the input of the macro makes them
+/// visible at the call site.
+///
+/// ```
+/// use rukt::utils::respan;
+/// macro_rules! define {
+///     ([$($I:ident)*] $value:expr) => {
+///         $(let $I = $value;)*
+///     };
+/// }
+/// macro_rules! bind {
+///     ($reference:ident) => {
+///         respan!([$reference] [first second] (define; 42));
+///     };
+/// }
+/// bind!(here);
+/// assert_eq!([first, second], [42, 42]);
+/// ```
+#[cfg(feature = "gensym")]
+#[doc(inline)]
+pub use rukt_macros::utils_respan as respan;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_repetitions() {
+        macro_rules! check {
+            ($T:tt $expected:expr) => {
+                assert_eq!(stringify!($T), $expected);
             };
         }
 
         escape_repetitions!([] [] [$REP] (check; "[]"));
         escape_repetitions!([hello world] [] [$REP] (check; "[hello world]"));
         escape_repetitions!([hello(world)] [] [$REP] (check; "[hello(world)]"));
-        escape_repetitions!([{ hello }(world)] [] [$REP] (check; "[{ hello }(world)]"));
+        escape_repetitions!([{ hello }(world)] [] [$REP] (check; "[{hello}(world)]"));
         escape_repetitions!([$($hello)* world] [] [$REP] (check; "[$REP($hello)* world]"));
         escape_repetitions!([$($hello)*(world)] [] [$REP] (check; "[$REP($hello)*(world)]"));
-        escape_repetitions!([{ $($hello)* }(world)] [] [$REP] (check; "[{ $REP($hello)* }(world)]"));
+        escape_repetitions!([{ $($hello)* }(world)] [] [$REP] (check; "[{$REP($hello)*}(world)]"));
         escape_repetitions!([$($hello)* $($world:tt, 42)+] [] [$REP] (check; "[$REP($hello)* $REP($world:tt, 42)+]"));
         escape_repetitions!([$($hello)*($($world:tt, 42)+)] [] [$REP] (check; "[$REP($hello)*($REP($world:tt, 42)+)]"));
-        escape_repetitions!([{ $($hello)* }($($world:tt, 42)+)] [] [$REP] (check; "[{ $REP($hello)* }($REP($world:tt, 42)+)]"));
+        escape_repetitions!([{ $($hello)* }($($world:tt, 42)+)] [] [$REP] (check; "[{$REP($hello)*}($REP($world:tt, 42)+)]"));
         escape_repetitions!([$($hello $(;)?)* $($world:tt, 42)+] [] [$REP] (check; "[$REP($hello $REP(;)?)* $REP($world:tt, 42)+]"));
         escape_repetitions!([$($hello $(;)?)*($($world:tt, 42)+)] [] [$REP] (check; "[$REP($hello $REP(;)?)*($REP($world:tt, 42)+)]"));
-        escape_repetitions!([{ $($hello $(;)?)* }($($world:tt, 42)+)] [] [$REP] (check; "[{ $REP($hello $REP(;)?)* }($REP($world:tt, 42)+)]"));
+        escape_repetitions!([{ $($hello $(;)?)* }($($world:tt, 42)+)] [] [$REP] (check; "[{$REP($hello $REP(;)?)*}($REP($world:tt, 42)+)]"));
+        escape_repetitions!([a b [c $(d)*] e f g h i j k l $(m)* n] [] [$REP] (check; "[a b [c $REP(d)*] e f g h i j k l $REP(m)* n]"));
+        escape_repetitions!([a { b } c d e f g h i j k l m (n $(o)?)] [] [$REP] (check; "[a {b}c d e f g h i j k l m(n $REP(o)?)]"));
+    }
+
+    #[test]
+    fn test_escape_repetitions_long() {
+        macro_rules! check {
+            ($T:tt) => {
+                let escaped = stringify!($T);
+                assert_eq!(escaped.matches("let").count(), 40);
+                assert!(escaped.ends_with("$REP($args)*]"));
+            };
+        }
+
+        macro_rules! repeat {
+            ([$($L:tt)*] $($T:tt)*) => {
+                escape_repetitions!([$($T)* $($T)* $($T)* $($T)* $($T)* $($T)* $($T)* $($T)* $($T)* $($T)* $($L)*] [] [$REP] (check;));
+            };
+        }
+
+        repeat! {
+            [$($args)*]
+            let first = one + two * three - four / five % six;
+            let second = seven + eight * nine - ten / eleven % twelve;
+            let third = first + second * first - second / first % second;
+            let fourth = first << second >> third & first | second ^ third;
+        }
+    }
+
+    #[test]
+    fn test_count() {
+        macro_rules! check {
+            ($K:literal $expected:expr) => {
+                assert_eq!($K, $expected);
+            };
+        }
+
+        count!([] (check; 0));
+        count!([$] (check; 1));
+        count!([(a b c)] (check; 1));
+        count!([$ $ [$] {} ()] (check; 5));
+        count!([a b c d e f g h i j k l m n o p] (check; 16));
+        count!([a b c d e f g h i j k l m n o p q] (check; 17));
+        count!([
+            $(a)* b c [d] e $f:ident g h i j k l m n o p
+            q r s t u v w x y z 0 1 2 3 4 5 6 7 8 9 {} () [] + - *
+        ] (check; 47));
+    }
+
+    #[test]
+    fn test_count_max() {
+        macro_rules! check {
+            ($K:literal) => {
+                assert_eq!($K, 255);
+            };
+        }
+
+        macro_rules! repeat {
+            ($($T:tt)*) => {
+                count!([$($T)* $($T)* $($T)* $($T)* $($T)* - - - - -] (check;));
+            };
+        }
+
+        repeat! {
+            a b c d e f g h i j k l m n o p q r s t u v w x y z a b c d e f g h i j k l m n o p q r s t u v w x
+        }
+    }
+
+    #[test]
+    fn test_strip_delimiters() {
+        macro_rules! check {
+            ($X:tt $K:tt $expected:expr) => {
+                assert_eq!(stringify!($X $K).replace(" ", ""), $expected);
+            };
+        }
+
+        macro_rules! error {
+            ($X:tt $expected:expr) => {
+                assert_eq!(stringify!($X).replace(" ", ""), $expected);
+            };
+        }
+
+        strip_delimiters!([()] (check; "[][parens]") (error; ""));
+        strip_delimiters!([[a b]] (check; "[ab][brackets]") (error; ""));
+        strip_delimiters!([{ $x:tt (y) }] (check; "[$x:tt(y)][braces]") (error; ""));
+        strip_delimiters!([a] (check; "") (error; "[a]"));
+        strip_delimiters!([(a) (b)] (check; "") (error; "[(a)(b)]"));
+        strip_delimiters!([] (check; "") (error; "[]"));
+    }
+
+    #[test]
+    fn test_with_delimiter() {
+        macro_rules! check {
+            ($G:tt $expected:expr) => {
+                assert_eq!(stringify!($G).replace(" ", ""), $expected);
+            };
+        }
+
+        macro_rules! round_trip {
+            ($X:tt $K:tt $expected:expr) => {
+                with_delimiter!($X $K (check; $expected));
+            };
+        }
+
+        with_delimiter!([] [parens] (check; "[()]"));
+        with_delimiter!([a b] [brackets] (check; "[[ab]]"));
+        with_delimiter!([$x:tt] [braces] (check; "[{$x:tt}]"));
+        strip_delimiters!([(a [b])] (round_trip; "[(a[b])]") (check; ""));
+        strip_delimiters!([{ a }] (round_trip; "[{a}]") (check; ""));
+    }
+
+    #[test]
+    fn test_partition() {
+        macro_rules! check {
+            ($A:tt $B:tt $expected:expr) => {
+                assert_eq!(stringify!($A $B).replace(" ", ""), $expected);
+            };
+        }
+
+        partition!([] [$x:tt] (check; "[][]"));
+        partition!([a b c] [$x:ident] (check; "[abc][]"));
+        partition!([1 2 3] [$x:ident] (check; "[][123]"));
+        partition!([a 1 b 2 c] [$x:ident] (check; "[abc][12]"));
+        partition!([(a 1) [b] (c 2) {d 3}] [($x:ident $y:literal)] (check; "[(a1)(c2)][[b]{d3}]"));
+        partition!([(a) (b c) (d e f)] [($($x:tt)*)] (check; "[(a)(bc)(def)][]"));
+        partition!([$x:tt $($y)*] [$] (check; "[$$][x:tt($y)*]"));
+        partition!([$x:tt $($y)*] [($($inner:tt)*)] (check; "[($y)][$x:tt$*]"));
+    }
+
+    #[test]
+    fn test_for_each() {
+        macro_rules! check {
+            ($T:tt $expected:expr) => {
+                assert_eq!(stringify!($T).replace(" ", ""), $expected);
+            };
+        }
+
+        macro_rules! wrap {
+            ([$X:tt] [$($A:tt)*] ($F:path; $($C:tt)*)) => {
+                $F!([$($A)* ($X)] $($C)*);
+            };
+        }
+
+        for_each!([] (wrap;) (check; "[]"));
+        for_each!([a] (wrap;) (check; "[(a)]"));
+        for_each!([a b c] (wrap;) (check; "[(a)(b)(c)]"));
+        for_each!([(a b) [c] {}] (wrap;) (check; "[((ab))([c])({})]"));
+        for_each!([$x:tt $($y)*] (wrap;) (check; "[($)(x)(:)(tt)($)(($y))(*)]"));
+    }
+
+    #[test]
+    fn test_for_each_context() {
+        macro_rules! check {
+            ($T:tt $expected:expr) => {
+                assert_eq!(stringify!($T).replace(" ", ""), $expected);
+            };
+        }
+
+        macro_rules! pair {
+            ([$X:tt] [$($A:tt)*] ($F:path; $($C:tt)*) $K:tt) => {
+                $F!([$($A)* [$K $X]] $($C)*);
+            };
+        }
+
+        macro_rules! first {
+            ([$X:tt] $A:tt $N:tt $expected:expr) => {
+                check!($X $expected);
+            };
+        }
+
+        for_each!([a b] (pair; key) (check; "[[keya][keyb]]"));
+        for_each!([a b c] (first; "a") (check; "unreachable"));
+    }
+
+    #[test]
+    fn test_split_at() {
+        macro_rules! check {
+            ($A:tt $B:tt $expected:expr) => {
+                assert_eq!(stringify!($A $B), $expected);
+            };
+        }
+
+        split_at!([] [0] (check; "[] []"));
+        split_at!([] [3] (check; "[] []"));
+        split_at!([a b c] [0] (check; "[] [a b c]"));
+        split_at!([a b c] [1] (check; "[a] [b c]"));
+        split_at!([a b c] [3] (check; "[a b c] []"));
+        split_at!([a b c] [255] (check; "[a b c] []"));
+        split_at!([a b c] [] (check; "[] [a b c]"));
+        split_at!([a b c] [_] (check; "[a] [b c]"));
+        split_at!([a b c] [_ _] (check; "[a b] [c]"));
+        split_at!([a b c] [_ _ _ _ _ _ _ _ _ _] (check; "[a b c] []"));
+        split_at!([a (b c) [d] e] [2] (check; "[a(b c)] [[d] e]"));
+        split_at!([$x:tt $($y)*] [2] (check; "[$x] [:tt $($y)*]"));
+        split_at!([$x:tt $($y)*] [_ _ _ _] (check; "[$x:tt] [$($y)*]"));
+    }
+
+    #[test]
+    fn test_split_at_long() {
+        macro_rules! check {
+            ([$($A:tt)*] [$($B:tt)*] $a:literal $b:literal) => {
+                assert_eq!(<[&str]>::len(&[$(stringify!($A)),*]), $a);
+                assert_eq!(<[&str]>::len(&[$(stringify!($B)),*]), $b);
+            };
+        }
+
+        macro_rules! repeat {
+            ($($T:tt)*) => {
+                split_at!([$($T)* $($T)* $($T)*] [16] (check; 16 62));
+                split_at!([$($T)* $($T)* $($T)*] [41] (check; 41 37));
+                split_at!([$($T)* $($T)* $($T)*] [78] (check; 78 0));
+                split_at!([$($T)* $($T)* $($T)*] [200] (check; 78 0));
+                split_at!([$($T)* $($T)* $($T)*] [$($T)*] (check; 26 52));
+            };
+        }
+
+        repeat! {
+            a b c d e f g h i j k l m n o p q r s t u v w x y z
+        }
+    }
+
+    #[test]
+    fn test_stringify_compact() {
+        macro_rules! check {
+            ([$($S:tt)*] $expected:expr) => {
+                assert_eq!($($S)*, $expected);
+            };
+        }
+
+        stringify_compact!([] (check; ""));
+        stringify_compact!([a] (check; "a"));
+        stringify_compact!([()[]{}] (check; "() [] {}"));
+        stringify_compact!([a::b::<T>] (check; "a :: b :: < T >"));
+        stringify_compact!([x=>y..=z <<= 'a &'b] (check; "x => y ..= z <<= 'a & 'b"));
+        stringify_compact!([f( a ,b )] (check; "f (a , b)"));
+        stringify_compact!([[[[a]]] {(b) [c]}] (check; "[[[a]]] {(b) [c]}"));
+        stringify_compact!([$($x:tt),* $(; $y:expr)?] (check; "$ ($ x : tt) , * $ (; $ y : expr) ?"));
+        stringify_compact!([r#type "a b" 1.0 b'c'] (check; "r#type \"a b\" 1.0 b'c'"));
+
+        macro_rules! fragments {
+            ($e:expr, $t:ty) => {
+                stringify_compact!([$e] (check; "1 + 2"));
+                stringify_compact!([f($e) -> $t {}] (check; "f (1 + 2) -> Vec<u8> {}"));
+            };
+        }
+
+        fragments!(1 + 2, Vec<u8>);
+    }
+
+    #[test]
+    fn test_split_punct() {
+        macro_rules! check {
+            ([$($X:tt)*] $expected:expr) => {
+                assert_eq!([$(stringify!($X)),*], $expected);
+            };
+        }
+
+        split_punct!([::] (check; [":", ":"]));
+        split_punct!([->] (check; ["-", ">"]));
+        split_punct!([=>] (check; ["=", ">"]));
+        split_punct!([..] (check; [".", "."]));
+        split_punct!([..=] (check; [".", ".", "="]));
+        split_punct!([<<=] (check; ["<", "<", "="]));
+        split_punct!([$] (check; ["$"]));
+        split_punct!([#] (check; ["#"]));
+    }
+
+    #[test]
+    fn test_join_punct() {
+        macro_rules! round_trip {
+            ($X:tt) => {
+                split_punct!([$X] (join_punct; (check; $X)));
+            };
+        }
+
+        macro_rules! check {
+            ([$X:tt] $Y:tt) => {
+                assert_eq!(stringify!($X), stringify!($Y));
+                tokens_equal!([[$X] [$Y]] (assert_equal;));
+            };
+        }
+
+        macro_rules! assert_equal {
+            ($R:literal) => {
+                assert!($R);
+            };
+        }
+
+        round_trip!(::);
+        round_trip!(->);
+        round_trip!(=>);
+        round_trip!(..);
+        round_trip!(..=);
+        round_trip!(...);
+        round_trip!(&&);
+        round_trip!(?);
     }
 }
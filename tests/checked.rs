@@ -0,0 +1,81 @@
+#![cfg(feature = "checked")]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+#![recursion_limit = "512"]
+
+use rukt::rukt_checked;
+
+#[test]
+fn default_limit() {
+    rukt_checked! {
+        let a = 1;
+        let b = if a == 1 { 2 } else { 3 };
+        expand {
+            assert_eq!([$a, $b], [1, 2]);
+        }
+    }
+}
+
+#[test]
+fn custom_limit() {
+    let mut names = Vec::new();
+    rukt_checked! {
+        limit = 40;
+        let opcodes = [add sub mul div];
+        for opcode in opcodes {
+            expand {
+                names.push(stringify!($opcode));
+            }
+        }
+    }
+    assert_eq!(names, ["add", "sub", "mul", "div"]);
+}
+
+#[test]
+fn nested_scopes() {
+    rukt_checked! {
+        limit = 40;
+        fn double($x:tt) {
+            [$x $x]
+        }
+        let pair = double(1);
+        let value = if true { double($pair) } else { pair };
+        expand {
+            assert_eq!(stringify!($value), "[[1 1] [1 1]]");
+        }
+    }
+}
+
+#[test]
+fn captured_block() {
+    use rukt::builtins::block;
+    rukt_checked! {
+        limit = 40;
+        let value = 1;
+        let defs = block {
+            let a = value;
+            let b = [$value 2];
+        };
+        let a = 3;
+        expand {
+            assert_eq!(stringify!($defs).replace(" ", ""), "[(a1)(b[12])]");
+            assert_eq!($a, 3);
+        }
+    }
+}
+
+#[test]
+fn recursion_limit() {
+    let mut total = 0;
+    rukt_checked! {
+        recursion_limit = 512;
+        let values = [1 2 3 4];
+        for value in values {
+            let doubled = [$value $value];
+            expand {
+                total += $value;
+                assert_eq!(stringify!($doubled), stringify!([$value $value]));
+            }
+        }
+    }
+    assert_eq!(total, 10);
+}
@@ -0,0 +1,73 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::rukt;
+
+#[test]
+fn exported_function() {
+    rukt! {
+        use upstream::quad;
+        let value = quad(1);
+        expand {
+            assert_eq!(stringify!($value), "[[1 1] [1 1]]");
+        }
+    }
+    assert_eq!(upstream::ops::LOCAL, "[[1 1] [1 1]]");
+}
+
+#[test]
+fn exported_variable() {
+    rukt! {
+        use upstream::names;
+        let [$($name:expr)*] = names;
+        expand {
+            assert_eq!([$($name),*], ["upstream"]);
+        }
+    }
+}
+
+#[test]
+fn exported_group() {
+    rukt! {
+        use upstream::settings::primary::upstream_primary_defaults as primary;
+        use upstream::settings::secondary::upstream_secondary_defaults as secondary;
+        use upstream::upstream_primary_defaults;
+        expand {
+            assert_eq!(stringify!($primary $secondary), "[1 2] [3]");
+            assert_eq!(stringify!($upstream_primary_defaults), "[1 2]");
+        }
+    }
+}
+
+#[test]
+fn exported_builtin() {
+    use upstream::builtins::swap;
+    rukt! {
+        let a = [1 2].swap();
+        let f = swap;
+        let b = f([3 4]);
+        expand {
+            assert_eq!(stringify!($a $b), "[2 1] [4 3]");
+        }
+    }
+}
+
+mod wrapped {
+    upstream::wrapped_constants!(PAIR 7 8 9);
+
+    rukt::rukt! {
+        use self::exported;
+        expand {
+            pub const EXPORTED: &str = stringify!($exported);
+        }
+    }
+
+    pub fn check() -> [&'static str; 2] {
+        [PAIR, REST]
+    }
+}
+
+#[test]
+fn wrapped_block() {
+    assert_eq!(wrapped::check(), ["[7 7]", "8 9"]);
+    assert_eq!(wrapped::EXPORTED, "[7 7]");
+}
@@ -0,0 +1,298 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::rukt;
+
+macro_rules! simple_statements {
+    ($D:tt $($I:ident)*) => {
+        rukt! {
+            use rukt::builtins::identity;
+            use rukt::builtins::not;
+            let _ = [$($I)*];
+            $(
+                let $I = "value";
+                let _ = 'ignored;
+            )*
+            let last = "value";
+            expand {
+                const LAST: &str = $D last;
+            }
+        }
+    };
+}
+
+#[test]
+fn simple_statements() {
+    simple_statements! {
+        $
+        a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15
+        b0 b1 b2 b3 b4 b5 b6 b7 b8 b9 b10 b11 b12 b13 b14 b15
+        c0 c1 c2 c3 c4 c5 c6 c7 c8 c9 c10 c11 c12 c13 c14 c15
+    }
+    assert_eq!(LAST, "value");
+}
+
+macro_rules! large_environment {
+    ($D:tt $($I:ident)*) => {
+        rukt! {
+            $(
+                let $I = 1;
+            )*
+            $(
+                expand {
+                    #[allow(non_upper_case_globals)]
+                    const $I: u32 = $D $I;
+                }
+            )*
+        }
+    };
+}
+
+#[test]
+fn large_environment() {
+    large_environment! {
+        $
+        a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15
+        b0 b1 b2 b3 b4 b5 b6 b7 b8 b9 b10 b11 b12 b13 b14 b15
+        c0 c1 c2 c3 c4 c5 c6 c7 c8 c9 c10 c11 c12 c13 c14 c15
+        d0 d1
+    }
+    assert_eq!([a0, a15, b7, c12, d1], [1; 5]);
+}
+
+#[test]
+fn deeply_nested_blocks() {
+    rukt! {
+        let a = 1; let b = 2; let c = 3; let d = 4;
+        let e = 5; let f = 6; let g = 7; let h = 8;
+        let i = 9; let j = 10; let k = 11; let l = 12;
+        let m = 13; let n = 14; let o = 15; let p = 16;
+        if true {
+            let x1 = 1;
+            if true {
+                let x2 = 2;
+                if true {
+                    let x3 = 3;
+                    if true {
+                        let x4 = 4;
+                        if true {
+                            let x5 = 5;
+                            if true {
+                                let x6 = 6;
+                                if true {
+                                    let x7 = 7;
+                                    if true {
+                                        let inner = p;
+                                        expand {
+                                            const RESULT: [u32; 4] = [$a, $x1, $x7, $inner];
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        expand {
+            const INNER: &str = stringify!($inner);
+            const OUTER: u32 = $b + $d + $f + $h + $j + $l + $n + $o;
+        }
+    }
+    assert_eq!(RESULT, [1, 1, 7, 16]);
+    assert_eq!(INNER, "$inner");
+    assert_eq!(OUTER, 71);
+}
+
+#[test]
+fn comparison_simple_tokens() {
+    rukt! {
+        let all = 1 == 1 && 'a' == 'a' && "s" == "s" && 'b' != 'c' && 2 != 3 && true == true && 'x' != 'y' && 3 == 3;
+        expand {
+            assert_eq!($all, true);
+        }
+    }
+    rukt! {
+        let any = 1 == 2 || 1 == 1.0 || 'a' == "a" || false == 0 || true != true || "s" != "s" || 'x' == 'y' || 3 != 3;
+        expand {
+            assert_eq!($any, false);
+        }
+    }
+}
+
+#[test]
+fn chained_ordering() {
+    rukt! {
+        let ascending = 1 < 2 < 3;
+        let unordered = 1 < 3 < 2;
+        let repeated = 1 == 1 == true;
+        let descending = 240 > 17 > 16;
+        expand {
+            assert_eq!([$ascending, $unordered, $repeated, $descending], [true, false, false, true]);
+        }
+    }
+}
+
+#[test]
+fn count_tokens_deep() {
+    use rukt::builtins::count_tokens_deep;
+    rukt! {
+        let count = [
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h a b c d e f g h
+            a b c d e f g h a b c d e f g
+        ]
+        .count_tokens_deep();
+        expand {
+            assert_eq!($count, 255);
+        }
+    }
+}
+
+macro_rules! long_expand_body {
+    ($D:tt $($I:ident)*) => {
+        rukt! {
+            let value = 1;
+            expand {
+                $(
+                    const $I: u32 = $D value;
+                )*
+                macro_rules! total {
+                    ($D$D($D$D name:ident)*) => {
+                        0 $D$D(+ $D$D name)*
+                    };
+                }
+            }
+        }
+    };
+}
+
+#[test]
+fn long_expand_body() {
+    long_expand_body! {
+        $
+        A0 A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14
+        B0 B1 B2 B3 B4 B5 B6 B7 B8 B9 B10 B11 B12 B13 B14
+        C0 C1 C2 C3 C4 C5 C6 C7 C8 C9 C10 C11 C12 C13 C14
+        D0 D1 D2 D3 D4 D5 D6 D7 D8 D9 D10 D11 D12 D13 D14
+        E0 E1 E2 E3 E4 E5 E6 E7 E8 E9 E10 E11 E12 E13 E14
+        F0 F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12 F13 F14
+        G0 G1 G2 G3 G4 G5 G6 G7 G8 G9 G10 G11 G12 G13 G14
+        H0 H1 H2 H3 H4 H5 H6 H7 H8 H9 H10 H11 H12 H13 H14
+    }
+    assert_eq!(total!(A0 B1 C2 D3 E4 F5 G6 H14), 8);
+}
+
+macro_rules! group_expressions {
+    ($D:tt [$($I:ident)*] [$($J:ident)*]) => {
+        rukt! {
+            $(
+                let $I = 1;
+            )*
+            $(
+                [ready $J];
+            )*
+            let last = [done];
+            expand {
+                const GROUP_EXPRESSIONS: &str = stringify!($D last);
+            }
+        }
+    };
+}
+
+#[test]
+fn group_expressions() {
+    group_expressions! {
+        $
+        [
+            a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15
+            b0 b1 b2 b3 b4 b5 b6 b7
+        ]
+        [
+        ]
+    }
+    assert_eq!(GROUP_EXPRESSIONS, "[done]");
+}
+
+macro_rules! many_functions {
+    ($D:tt [$($I:ident)*] [$($J:ident)*]) => {
+        rukt! {
+            $(
+                let $I = 1;
+            )*
+            let value = "value";
+            $(
+                fn $J() {
+                    value
+                }
+            )*
+            fn last() {
+                [$D value]
+            }
+            let result = last();
+            expand {
+                const RESULT: &str = stringify!($D result);
+                const LAST: &str = stringify!($D last);
+            }
+        }
+    };
+}
+
+#[test]
+fn many_functions() {
+    many_functions! {
+        $
+        [
+            a0 a1 a2 a3 a4 a5 a6 a7 a8 a9 a10 a11 a12 a13 a14 a15
+        ]
+        [b0 b1 b2 b3 b4 b5]
+    }
+    assert_eq!(RESULT, "[\"value\"]");
+    assert_eq!(LAST, "{ fn last () [$ value : tt] [\"value\"] { [$value] } }");
+}
+
+#[test]
+fn deeply_nested_expressions() {
+    rukt! {
+        let a = 1; let b = 2; let c = 3; let d = 4;
+        let e = 5; let f = 6; let g = 7; let h = 8;
+        let i = 9; let j = 10; let k = 11; let l = 12;
+        let m = 13; let n = 14; let o = 15; let p = 16;
+        let result = if true {
+            let x1 = a;
+            if true {
+                let x2 = c;
+                if true {
+                    let x3 = e;
+                    if true {
+                        let x4 = g;
+                        if true {
+                            let x5 = i;
+                            if true {
+                                let x6 = k;
+                                if true {
+                                    let x7 = m;
+                                    if true {
+                                        let inner = p;
+                                        [$x1, $x2, $x3, $x4, $x5, $x6, $x7, $inner]
+                                    } else { [] }
+                                } else { [] }
+                            } else { [] }
+                        } else { [] }
+                    } else { [] }
+                } else { [] }
+            } else { [] }
+        } else {
+            []
+        };
+        expand {
+            assert_eq!($result, [1, 3, 5, 7, 9, 11, 13, 16]);
+            assert_eq!(stringify!($x1), "$x1");
+            assert_eq!(stringify!($inner), "$inner");
+            assert_eq!($b + $d + $f + $h + $j + $l + $n + $o, 71);
+        }
+    }
+}
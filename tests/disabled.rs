@@ -0,0 +1,23 @@
+// the builtins of disabled features can still be imported
+#![cfg(not(any(feature = "env", feature = "strings")))]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::builtins::env;
+#[allow(unused_imports)]
+use rukt::builtins::{lines, stringify_compact, to_tokens, trim};
+use rukt::rukt;
+
+#[test]
+fn imports() {
+    rukt! {
+        let value = [1 2 3];
+        expand {
+            assert_eq!(stringify!($value), "[1 2 3]");
+        }
+    }
+}
+
+#[test]
+fn forward_env() {
+    assert_eq!(env!("CARGO_PKG_NAME"), "rukt");
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "env")]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::builtins::env;
+use rukt::rukt;
+
+#[test]
+fn defined() {
+    rukt! {
+        let name = env("CARGO_PKG_NAME");
+        let version = env("CARGO_PKG_VERSION" "0.0.0");
+        expand {
+            assert_eq!([$name, $version], ["rukt", env!("CARGO_PKG_VERSION")]);
+        }
+    }
+}
+
+#[test]
+fn fallback() {
+    rukt! {
+        let a = env("RUKT_UNDEFINED_VARIABLE" "fallback");
+        let b = env("RUKT_UNDEFINED_VARIABLE" [not a string]);
+        let c = if env("RUKT_UNDEFINED_VARIABLE" "") == "" { "empty" } else { "defined" };
+        expand {
+            assert_eq!($a, "fallback");
+            assert_eq!(stringify!($b), "[not a string]");
+            assert_eq!($c, "empty");
+        }
+    }
+}
+
+#[test]
+fn expand() {
+    rukt! {
+        let name = env("CARGO_PKG_NAME");
+        expand {
+            const NAME: &str = $name;
+        }
+    }
+    assert_eq!(NAME, "rukt");
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "gensym")]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::builtins::generate_builder;
+use rukt::rukt;
+use rukt::utils::{concat_idents, gensym};
+
+macro_rules! define_first {
+    ($I:ident $K:tt $V:literal) => {
+        const $I: u32 = $V;
+        gensym!(ITEM $K (define_second; $V));
+    };
+}
+
+macro_rules! define_second {
+    ($I:ident $K:tt $V:literal) => {
+        const $I: u32 = $V * 2;
+    };
+}
+
+gensym!(ITEM [] (define_first; 21));
+
+#[test]
+fn distinct_items() {
+    assert_eq!([ITEM_0, ITEM_1], [21, 42]);
+}
+
+#[test]
+fn counter() {
+    macro_rules! check {
+        ($I:ident $K:tt $expected:expr) => {
+            assert_eq!(stringify!($I $K), $expected);
+        };
+    }
+
+    gensym!(x [] (check; "x_0 [1]"));
+    gensym!(x [41] (check; "x_41 [42]"));
+    gensym!(r#type [2] (check; "type_2 [3]"));
+}
+
+#[test]
+fn concat() {
+    macro_rules! check {
+        ($I:ident $expected:expr) => {
+            assert_eq!(stringify!($I), $expected);
+        };
+    }
+
+    concat_idents!([a] (check; "a"));
+    concat_idents!([Foo Builder] (check; "FooBuilder"));
+    concat_idents!([set_ r#type] (check; "set_type"));
+    concat_idents!([x 1 _ 2] (check; "x1_2"));
+}
+
+#[derive(Debug, PartialEq)]
+struct Config {
+    retries: u32,
+    host: String,
+    tags: Vec<&'static str>,
+}
+
+rukt! {
+    let fields = { retries: u32, host: String, tags: Vec<&'static str> };
+    generate_builder(Config, $fields);
+}
+
+#[test]
+fn builder() {
+    let config = Config::builder().set_retries(3).set_host("localhost".to_string()).set_tags(vec!["a"]).build();
+    let expected = Config { retries: 3, host: "localhost".to_string(), tags: vec!["a"] };
+    assert_eq!(config, Ok(expected));
+    assert_eq!(ConfigBuilder::default().set_tags(vec![]).build(), Err("missing field `retries`"));
+}
+
+#[test]
+fn builder_name() {
+    struct Empty {}
+    rukt! {
+        let name = generate_builder(Empty, {});
+        expand {
+            assert_eq!(stringify!($name), "EmptyBuilder");
+            assert!($name::default().build().is_ok());
+        }
+    }
+}
+
+mod groups {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let opcodes = [add sub];
+        pub(crate) let [$($register:ident)*] = [a b c];
+        pub(crate) let registers = [$($register)*];
+        pub(crate) group tables { opcodes, registers }
+    }
+    pub mod nested {
+        use rukt::rukt;
+        rukt! {
+            pub(crate) let modes = [user kernel];
+            pub(crate) group tables { modes, }
+        }
+    }
+}
+
+#[test]
+fn use_glob() {
+    rukt! {
+        use groups::tables::*;
+        use groups::nested::tables::*;
+        expand {
+            assert_eq!(stringify!($opcodes $registers $modes), "[add sub] [a b c] [user kernel]");
+        }
+    }
+    rukt! {
+        use groups::tables::*;
+        let ops = opcodes;
+        let [$($op:ident)*] = ops;
+        expand {
+            assert_eq!(stringify!($ops $($op)*), "[add sub] add sub");
+        }
+    }
+}
+
+macro_rules! define_parts {
+    ([$($A:tt)*] [$($B:tt)*] $I:ident) => {
+        const $I: [&str; 2] = [stringify!($($A)*), stringify!($($B)*)];
+    };
+}
+
+macro_rules! define_partitions {
+    ($D:tt) => {
+        rukt::utils::partition!([a 1 b] [$D x:ident] (define_parts; IDENTS));
+        rukt::utils::partition!([a 1 b] [$D x:literal] (define_parts; LITERALS));
+    };
+}
+
+define_partitions!($);
+
+#[test]
+fn partition_in_same_scope() {
+    assert_eq!(IDENTS, ["a b", "1"]);
+    assert_eq!(LITERALS, ["1", "a b"]);
+}
+
+mod exported_first {
+    rukt::rukt! {
+        #[macro_export]
+        pub let value in exported_primary = [1 2];
+    }
+}
+
+mod exported_second {
+    rukt::rukt! {
+        #[macro_export]
+        pub let [$value:tt $($other:tt)*] in exported_secondary = [3 4];
+    }
+}
+
+#[test]
+fn macro_export_in_group() {
+    rukt! {
+        use exported_first::exported_primary::value as primary;
+        use exported_second::exported_secondary::{value, other};
+        expand {
+            assert_eq!(stringify!($primary $value $other), "[1 2] 3 [4]");
+        }
+    }
+}
+
+macro_rules! define_selected {
+    ([$($T:tt)*] $I:ident) => {
+        const $I: &str = stringify!($($T)*);
+    };
+}
+
+macro_rules! define_selections {
+    ($D:tt) => {
+        rukt::utils::select!([(1 2)] [[[($D a:tt $D b:tt)] [$D b $D a]]] (define_selected; SWAPPED) $);
+        rukt::utils::select!([[x]] [[[[$D x:ident]] [$D x]] [[$D other:tt] [()]]] (define_selected; UNWRAPPED) $);
+    };
+}
+
+define_selections!($);
+
+#[test]
+fn select_in_same_scope() {
+    assert_eq!([SWAPPED, UNWRAPPED], ["2 1", "x"]);
+}
@@ -0,0 +1,70 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+#![recursion_limit = "512"]
+
+use rukt::builtins::{identity, starts_with};
+use rukt::rukt;
+
+#[test]
+fn literal() {
+    rukt! {
+        let lifetime = 'static;
+        let method = 'a.identity();
+        let call = identity('b);
+        expand {
+            assert_eq!([stringify!($lifetime), stringify!($method), stringify!($call)], ["'static", "'a", "'b"]);
+        }
+    }
+}
+
+#[test]
+fn comparison() {
+    rukt! {
+        let lifetime = 'static;
+        let a = lifetime == 'static;
+        let b = lifetime != 'a;
+        let c = 'a == 'a == 'a;
+        let d = if lifetime == 'a { 'yes } else { 'no };
+        expand {
+            assert_eq!([$a, $b, $c], [true, true, true]);
+            assert_eq!(stringify!($d), "'no");
+        }
+    }
+}
+
+#[test]
+fn list() {
+    rukt! {
+        let list = ['a 'b];
+        let a = list.starts_with('a);
+        let b = list.starts_with('b);
+        let c = [T 'a].starts_with(T 'a);
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, true]);
+        }
+        for ($i, $lifetime) in list {
+            expand {
+                assert_eq!(stringify!($lifetime), ["'a", "'b"][$i]);
+            }
+        }
+    }
+}
+
+#[test]
+fn destructuring() {
+    rukt! {
+        let ($lifetime:lifetime) = ('x);
+        let [$($all:lifetime)*] = ['a 'b 'c];
+        let ['a $value:tt] = ['a 1];
+        let equal = [$lifetime] == ['x];
+        fn is_static($l:lifetime) {
+            [$l] == ['static]
+        }
+        let a = is_static('static);
+        let b = is_static('a);
+        expand {
+            assert_eq!([stringify!($lifetime), $(stringify!($all)),*], ["'x", "'a", "'b", "'c"]);
+            assert_eq!([$value], [1]);
+            assert_eq!([$equal, $a, $b], [true, true, false]);
+        }
+    }
+}
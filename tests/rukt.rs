@@ -1,3 +1,4 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
 #![recursion_limit = "512"]
 
 use rukt::rukt;
@@ -17,6 +18,7 @@ fn expand() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn let_bool() {
     rukt! {
         let a = true;
@@ -60,10 +62,94 @@ fn let_token_tree() {
     assert_eq!(ARBITRARY, SYNTAX);
     assert_eq!(
         SYNTAX,
-        "{ [ARBITRARY SYNTAX] in (^ here) where \"nothing\" => match }"
+        "{[ARBITRARY SYNTAX] in (^ here) where \"nothing\" => match}"
     );
 }
 
+#[test]
+fn let_long_group() {
+    rukt! {
+        let a = 0;
+        let plain = [1 2 3 4 5 6 7 8 9 (10 [11 12]) 13 14 15 16 17 18];
+        let nested = [1 2 3 4 5 6 7 8 9 (10 [11 $a]) 13 14 15 16 17 18];
+        fn last($x:tt) {
+            [1 2 3 4 5 6 7 8 9 (10 [11 $x $a])]
+        }
+        let called = last(12);
+        expand {
+            const PLAIN: &str = stringify!($plain);
+            const NESTED: &str = stringify!($nested);
+            const CALLED: &str = stringify!($called);
+        }
+    }
+    assert_eq!(PLAIN, "[1 2 3 4 5 6 7 8 9 (10 [11 12]) 13 14 15 16 17 18]");
+    assert_eq!(NESTED, "[1 2 3 4 5 6 7 8 9 (10 [11 0]) 13 14 15 16 17 18]");
+    assert_eq!(CALLED, "[1 2 3 4 5 6 7 8 9 (10 [11 12 0])]");
+}
+
+#[test]
+fn let_rest() {
+    use rukt::builtins::starts_with;
+    rukt! {
+        let [$first:tt ..$rest] = [1 2 3 4];
+        let ($a:tt $b:tt ..$tail) = (x y);
+        let {..$all} = {a {b} c};
+        let check = rest.starts_with(2 3);
+        expand {
+            assert_eq!(stringify!($first $rest $a $b $tail $all).replace(" ", ""), "1[234]xy[][a{b}c]");
+            assert_eq!($check, true);
+        }
+    }
+}
+
+#[test]
+fn let_tuple() {
+    rukt! {
+        fn minmax($a:tt $b:tt) {
+            if a < b {
+                ($a $b)
+            } else {
+                ($b $a)
+            }
+        }
+        let (min, max) = minmax(7 3);
+        let (single,) = ([x y]);
+        let (a, b, c,) = ((1) {2} $min);
+        expand {
+            assert_eq!([$min, $max], [3, 7]);
+            assert_eq!(stringify!($single), "[x y]");
+            assert_eq!([$a, $b, $c], [1, 2, 3]);
+        }
+    }
+}
+
+#[test]
+fn let_literal_pattern() {
+    rukt! {
+        let [ok $x:tt] = [ok 5];
+        let (1 $rest:tt) = (1 [2 3]);
+        let 5 = x;
+        let "done" = "done";
+        expand {
+            assert_eq!($x, 5);
+            assert_eq!(stringify!($rest).replace(" ", ""), "[23]");
+        }
+    }
+}
+
+#[test]
+fn let_assert() {
+    rukt! {
+        let count = 3 assert count > 0;
+        let (head, tail) = (1 [2 3]) assert tail == [2 3];
+        let _ = [] assert true;
+        expand {
+            assert_eq!($count + $head, 4);
+            assert_eq!(stringify!($tail), "[2 3]");
+        }
+    }
+}
+
 #[test]
 fn let_export() {
     rukt! {
@@ -85,7 +171,27 @@ fn parse_regular_macro() {
         let value = { 7 [arbitrary] stuff ... };
         pub(self) let string = rukt::builtins::parse::<expr>(stringify!($value));
     }
-    assert_eq!(string!(), "{ 7 [arbitrary] stuff ... }");
+    assert_eq!(string!(), "{7 [arbitrary] stuff ...}");
+}
+
+#[test]
+fn parse_many() {
+    use rukt::builtins::{deep_map, identity, parse_many};
+    rukt! {
+        let id = identity;
+        let types = parse_many::<ty>(u8, Vec<u16>, [u32; 2],).deep_map($id);
+        let empty = parse_many::<expr>();
+        let [$a:tt $b:tt $c:tt] = types;
+        let values = parse_many::<expr, ;>(1 + 2; <$a>::MAX as u32);
+        let [$d:tt $e:tt] = values;
+        expand {
+            let b: $b = vec![1, 2];
+            let c: $c = [3, 4];
+            assert_eq!(stringify!($empty), "[]");
+            assert_eq!((b, c), (vec![1, 2], [3, 4]));
+            assert_eq!([$d, $e], [3, 255]);
+        }
+    }
 }
 
 #[test]
@@ -175,10 +281,15 @@ fn starts_with() {
         let a = [1 2 3].starts_with(1 2);
         let b = [1 2 3].starts_with(2 2);
         let c = [1 2 3].starts_with(1 2 3 4);
+        let x = 1;
+        let d = [(1) 2 3].starts_with(($x) 2);
+        let e = [(1) 2 3].starts_with([$x]);
         expand {
             assert_eq!($a, true);
             assert_eq!($b, false);
             assert_eq!($c, false);
+            assert_eq!($d, true);
+            assert_eq!($e, false);
         }
     }
 }
@@ -254,6 +365,128 @@ fn user_function_expand() {
     assert_eq!(NINE, 9);
 }
 
+mod expression {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) fn area($w:literal $h:literal) {
+            ($w * $h)
+        }
+        pub(crate) fn scoped() {
+            expand {
+                const INNER: u32 = 5;
+            }
+            { INNER + 1 }
+        }
+    }
+}
+
+const AREA: u32 = expression::area!(@expr 6 7);
+
+#[test]
+fn exported_function_expression() {
+    assert_eq!(AREA, 42);
+    assert_eq!(expression::scoped!(@expr), 6);
+}
+
+mod attributes {
+    use rukt::rukt;
+    rukt! {
+        /// Wrap the token in brackets.
+        #[allow(unused_macros)]
+        pub(crate) fn wrap($x:tt) {
+            [$x]
+        }
+        let wrapped = wrap(1);
+        expand {
+            pub(crate) const WRAPPED: &str = stringify!($wrapped);
+        }
+    }
+}
+
+#[test]
+fn exported_function_attributes() {
+    assert_eq!(attributes::WRAPPED, "[1]");
+    rukt! {
+        use attributes::wrap;
+        let wrapped = wrap(2);
+        expand {
+            assert_eq!(stringify!($wrapped), "[2]");
+        }
+    }
+    assert_eq!(attributes::wrap!(@expr 3), [3]);
+}
+
+#[test]
+fn error_not_reached() {
+    rukt! {
+        let value = [1 2 3];
+        let [$first:tt $($rest:tt)*] = value;
+        fn check($x:tt) {
+            if x != 1 {
+                error "expected {{1}}, found {x} in {value}";
+            }
+        }
+        check($first);
+    }
+}
+
+mod interleaved {
+    use rukt::rukt;
+
+    macro_rules! two_blocks {
+        ($A:ident $B:ident) => {
+            rukt! {
+                let [$x:tt $y:tt] = [1 2];
+                expand {
+                    pub(crate) const $A: u32 = $x;
+                }
+            }
+            rukt! {
+                let [$x:tt $y:tt] = [3 4];
+                expand {
+                    pub(crate) const $B: u32 = $y;
+                }
+            }
+        };
+    }
+
+    two_blocks!(FIRST SECOND);
+}
+
+#[test]
+fn interleaved_blocks() {
+    assert_eq!([interleaved::FIRST, interleaved::SECOND], [1, 4]);
+}
+
+mod guards {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) fn pick($x:tt) where [$x] == [a] || [$x] == [b] {
+            [$x]
+        }
+        let value = pick(b);
+        expand {
+            pub(crate) const PICKED: &str = stringify!($value);
+        }
+    }
+}
+
+#[test]
+fn function_guard() {
+    assert_eq!(guards::PICKED, "[b]");
+    rukt! {
+        use guards::pick;
+        fn first($x:tt $($rest:tt)*) where [$($rest)*] != [] {
+            x
+        }
+        let a = pick(a);
+        let b = first(1 2);
+        expand {
+            assert_eq!(stringify!($a $b), "[a] 1");
+        }
+    }
+}
+
 #[test]
 fn manual_function() {
     rukt! {
@@ -274,6 +507,26 @@ fn manual_function() {
     }
 }
 
+#[test]
+#[allow(deprecated)]
+fn warn() {
+    rukt! {
+        let a = 1;
+        warn "first warning";
+        fn f($x:tt) {
+            warn "second warning";
+            x
+        }
+        let b = f(2);
+        for item in [3 4] {
+            warn "third warning";
+        }
+        expand {
+            assert_eq!([$a, $b], [1, 2]);
+        }
+    }
+}
+
 #[test]
 fn condition() {
     use rukt::builtins::starts_with;
@@ -340,6 +593,69 @@ fn condition() {
     }
 }
 
+#[test]
+fn condition_false_without_else() {
+    rukt! {
+        let before = 1;
+        if false {
+            let before = 2;
+        }
+        let after = 3;
+
+        fn skipped() {
+            if false {
+                8
+            }
+        }
+        let result = skipped();
+
+        expand {
+            assert_eq!([$before, $after], [1, 3]);
+            assert_eq!($result, ());
+        }
+    }
+}
+
+#[test]
+fn condition_statement_branches() {
+    rukt! {
+        let a = if true { let z = 1; } else { 2 };
+        let b = if false { 1 } else { let z = 1; };
+        let c = if true { 1; } else { 2 };
+        let d = if true { expand {} } else { 2 } == ();
+        let e = if false { 1 } else if true { let z = 1; } else { 3 };
+        fn f($x:tt) {
+            if x == 1 {
+                let y = 2;
+            } else {
+                x
+            }
+        }
+        let g = f(1);
+        let h = f(2);
+        expand {
+            assert_eq!([$a, $b, $c, $e, $g], [(); 5]);
+            assert_eq!([$d, $h == 2], [true; 2]);
+        }
+    }
+}
+
+#[test]
+fn empty_function() {
+    rukt! {
+        fn noop() {}
+        fn ignore($($args:tt)*) {}
+        noop();
+        let a = noop();
+        let b = ignore(1 [2] (3));
+        let f = noop;
+        let c = f();
+        expand {
+            assert_eq!([$a, $b, $c], [(), (), ()]);
+        }
+    }
+}
+
 #[test]
 fn condition_function() {
     rukt! {
@@ -388,3 +704,1287 @@ fn recursion() {
         }
     }
 }
+
+mod tables {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let opcodes = [add sub];
+        pub(crate) let registers = [a b c];
+        pub(crate) let flags = [zero carry];
+    }
+    pub mod nested {
+        use rukt::rukt;
+        rukt! {
+            pub(crate) let modes = [user kernel];
+        }
+    }
+}
+
+#[test]
+fn use_group() {
+    rukt! {
+        use tables::{opcodes, registers};
+        expand {
+            assert_eq!(stringify!($opcodes $registers), "[add sub] [a b c]");
+        }
+    }
+    rukt! {
+        use tables::{opcodes as ops, registers, flags as f,};
+        expand {
+            assert_eq!(stringify!($ops $registers $f), "[add sub] [a b c] [zero carry]");
+        }
+    }
+    rukt! {
+        use tables::{flags, nested::{modes as m}};
+        expand {
+            assert_eq!(stringify!($flags $m), "[zero carry] [user kernel]");
+        }
+    }
+}
+
+mod groups {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let opcodes = [add sub];
+        pub(crate) let registers = [a b c];
+        pub(crate) group tables { opcodes, registers }
+    }
+    pub mod nested {
+        use rukt::rukt;
+        rukt! {
+            pub(crate) let modes = [user kernel];
+            pub(crate) group tables { modes, }
+        }
+    }
+}
+
+#[test]
+fn group_export() {
+    rukt! {
+        use groups::{tables, opcodes, registers};
+        expand {
+            assert_eq!(stringify!($tables), "{opcodes, registers}");
+            assert_eq!(stringify!($opcodes $registers), "[add sub] [a b c]");
+        }
+    }
+}
+
+#[test]
+fn use_glob() {
+    rukt! {
+        use groups::tables::*;
+        use groups::nested::tables::*;
+        expand {
+            assert_eq!(stringify!($opcodes $registers $modes), "[add sub] [a b c] [user kernel]");
+        }
+    }
+    rukt! {
+        use groups::tables::*;
+        fn first() {
+            let [$op:ident $($rest:tt)*] = opcodes;
+            op
+        }
+        let op = first();
+        expand {
+            assert_eq!(stringify!($op $opcodes), "add [add sub]");
+        }
+    }
+}
+
+#[test]
+fn identity_and_const_fn() {
+    use rukt::builtins::{const_fn, identity};
+    rukt! {
+        let value = [a b c];
+        fn apply($f:tt $arg:tt) {
+            f($arg)
+        }
+        let id = identity;
+        let a = apply($id $value);
+        let b = value.identity() == value;
+        let k = const_fn($value);
+        let c = apply($k ignored);
+        let d = value.const_fn()(1 2 3);
+        let e = id($id)(7);
+        expand {
+            assert_eq!(stringify!($a), "[a b c]");
+            assert_eq!($b, true);
+            assert_eq!(stringify!($c), "[a b c]");
+            assert_eq!(stringify!($d), "[a b c]");
+            assert_eq!($e, 7);
+        }
+    }
+}
+
+#[test]
+fn expand_escape() {
+    rukt! {
+        let value = 42;
+        expand {
+            macro_rules! nested {
+                ($$x:tt) => {
+                    [$value, $$x]
+                };
+            }
+            const DOLLAR: &str = stringify!($$value $$$$ ($$));
+        }
+    }
+    assert_eq!(nested!(7), [42, 7]);
+    assert_eq!(DOLLAR.replace(" ", ""), "$value$$($)");
+}
+
+mod facade {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) use crate::tables::opcodes;
+        pub(crate) use crate::tables::nested::modes as m;
+        expand {
+            pub const LOCAL: &str = stringify!($opcodes $m);
+        }
+    }
+}
+
+#[test]
+fn use_reexport() {
+    rukt! {
+        use facade::{opcodes, m};
+        expand {
+            assert_eq!(stringify!($opcodes $m), "[add sub] [user kernel]");
+        }
+    }
+    assert_eq!(facade::LOCAL, "[add sub] [user kernel]");
+}
+
+mod function_facade {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) use crate::expression::area;
+        pub(crate) use crate::attributes::wrap as bracket;
+        let local = bracket(x);
+        expand {
+            pub const LOCAL: &str = stringify!($local);
+        }
+    }
+}
+
+#[test]
+fn use_reexport_function() {
+    rukt! {
+        use function_facade::{area, bracket};
+        let wrapped = bracket(1);
+        let value = area(2 3);
+        expand {
+            assert_eq!(stringify!($wrapped), "[1]");
+            assert_eq!($value, 6);
+        }
+    }
+    assert_eq!(function_facade::area!(@expr 6 7), 42);
+    assert_eq!(function_facade::LOCAL, "[x]");
+}
+
+mod grouped {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let value in first = [a b];
+    }
+    rukt! {
+        pub(crate) let value in second = [c];
+        pub(super) let {width: $width:tt, height: $height:tt} in size = {width: 2, height: 3};
+        pub(crate) let local as renamed in third = 4;
+    }
+}
+
+#[test]
+fn use_grouped_export() {
+    rukt! {
+        use grouped::first::value as a;
+        use grouped::second::value as b;
+        use grouped::size::{width, height};
+        use grouped::third::renamed;
+        expand {
+            assert_eq!(stringify!($a $b), "[a b] [c]");
+            assert_eq!([$width, $height, $renamed], [2, 3, 4]);
+        }
+    }
+}
+
+#[test]
+fn builtin_path() {
+    rukt! {
+        let value = 1;
+        let a = rukt::builtins::identity($value);
+        let b = [1 2].rukt::builtins::starts_with(1);
+        expand {
+            assert_eq!([$a], [1]);
+            assert_eq!($b, true);
+        }
+    }
+}
+
+#[test]
+fn not() {
+    use rukt::builtins::{not, starts_with};
+    rukt! {
+        let a = true.not();
+        let b = [x y].starts_with(x).not().not();
+        let f = not;
+        let c = f(false);
+        expand {
+            assert_eq!([$a, $b, $c], [false, true, true]);
+        }
+    }
+}
+
+#[test]
+fn debug_assert() {
+    use rukt::builtins::debug_assert;
+    rukt! {
+        let value = [1 2];
+        debug_assert(value == [1 2]);
+        let result = debug_assert(true);
+        expand {
+            assert_eq!($result, ());
+        }
+    }
+}
+
+#[cfg(not(feature = "debug"))]
+#[test]
+fn debug_assert_disabled() {
+    use rukt::builtins::debug_assert;
+    rukt! {
+        debug_assert(1 == 2);
+    }
+}
+
+#[test]
+fn deep_map() {
+    use rukt::builtins::deep_map;
+    rukt! {
+        fn rename($x:tt) {
+            let [$y:tt] = if [$x] == [a] {
+                [b]
+            } else {
+                [$x]
+            };
+            y
+        }
+        let a = [a [a c] {(a)} () c].deep_map($rename);
+        let b = [].deep_map($rename);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[b[bc]{(b)}()c]");
+            assert_eq!(stringify!($b), "[]");
+        }
+    }
+}
+
+#[test]
+fn reduce() {
+    use rukt::builtins::reduce;
+    rukt! {
+        fn max($a:tt $b:tt) {
+            if a > b { a } else { b }
+        }
+        fn pair($a:tt $b:tt) {
+            [$a $b]
+        }
+        let a = [3 9 4].reduce($max);
+        let b = [7].reduce($max);
+        let c = [[x] {y} z].reduce($pair);
+        expand {
+            assert_eq!([$a, $b], [9, 7]);
+            assert_eq!(stringify!($c).replace(" ", ""), "[[[x]{y}]z]");
+        }
+    }
+}
+
+#[test]
+fn fold_while() {
+    use rukt::builtins::fold_while;
+    rukt! {
+        fn above($found:tt $x:tt) {
+            if x > 5 { (done $x) } else { (continue $found) }
+        }
+        fn last($a:tt $x:tt) {
+            (continue $x)
+        }
+        // the groups can't be compared to integers if the fold keeps going
+        let a = [1 7 (x) (y)].fold_while(none $above);
+        let b = [1 2].fold_while(none $above);
+        let c = [].fold_while(none $above);
+        let d = (a {b} [c]).fold_while(_ $last);
+        let f = fold_while;
+        let e = f([4 9] 0 $above);
+        expand {
+            assert_eq!(stringify!([$a $b $c $e]), "[7 none none 9]");
+            assert_eq!(stringify!($d), "[c]");
+        }
+    }
+}
+
+#[test]
+fn unique_by() {
+    use rukt::builtins::{identity, unique_by};
+    rukt! {
+        let id = identity;
+        let a = [apple apricot banana apple].unique_by($id);
+        let b = [].unique_by($id);
+        expand {
+            assert_eq!(stringify!($a), "[apple apricot banana]");
+            assert_eq!(stringify!($b), "[]");
+        }
+    }
+    rukt! {
+        fn head([$first:tt $($rest:tt)*]) {
+            first
+        }
+        let a = [[a 1] [b 2] [a 3] [(c) 5] [b 4] [(c) 6]].unique_by($head);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[[a1][b2][(c)5]]");
+        }
+    }
+    rukt! {
+        fn head([$first:tt $($rest:tt)*]) {
+            first
+        }
+        let f = unique_by;
+        let a = f([[($x) 1] [($y) 2] [($x) 3]] $head);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[[($x)1][($y)2]]");
+        }
+    }
+}
+
+#[test]
+fn apply() {
+    use rukt::builtins::{apply, identity};
+    rukt! {
+        fn wrap($($x:tt)*) {
+            [$($x)*]
+        }
+        let f = wrap;
+        let a = apply($f [1 {2} (3)]);
+        let b = apply($f ());
+        expand {
+            assert_eq!(stringify!($a $b).replace(" ", ""), "[1{2}(3)][]");
+        }
+    }
+    rukt! {
+        let g = identity;
+        let c = g.apply([7]);
+        let d = apply($g [c]);
+        expand {
+            assert_eq!(stringify!($c $d), "7 c");
+        }
+    }
+}
+
+mod destructured {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let {
+            first: $first:tt,
+            ops: [$($name:ident: $op:tt),*],
+            $_:tt
+        } = {
+            first: 1,
+            ops: [add: +, sub: -],
+            ignored
+        };
+        expand {
+            pub const LOCAL: &str = stringify!($first $($name)*);
+        }
+    }
+}
+
+mod function_table {
+    use rukt::rukt;
+
+    rukt! {
+        fn double($x:tt) {
+            ($x * 2)
+        }
+        fn pair($a:tt $b:tt) {
+            ($a, $b)
+        }
+        pub(crate) let helpers = [(double $double) (pair $pair)];
+    }
+}
+
+#[test]
+fn chunk_by() {
+    use rukt::builtins::chunk_by;
+    rukt! {
+        fn is_semi($x:tt) {
+            x == ;
+        }
+        let a = [a b; c; (d;)].chunk_by($is_semi);
+        let b = [;;].chunk_by($is_semi);
+        let c = [x].chunk_by($is_semi);
+        let f = chunk_by;
+        let d = f([1; 2] $is_semi);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[[ab][c][(d;)]]");
+            assert_eq!(stringify!($b).replace(" ", ""), "[[][][]]");
+            assert_eq!(stringify!($c), "[[x]]");
+            assert_eq!(stringify!($d).replace(" ", ""), "[[1][2]]");
+        }
+    }
+}
+
+#[test]
+fn intersperse() {
+    use rukt::builtins::intersperse;
+    rukt! {
+        let a = [a b c d e f g].intersperse(,);
+        let b = [a].intersperse(,);
+        let c = [].intersperse(,);
+        let d = {x y}.intersperse(;);
+        let sep = |;
+        let e = ((a) [b] {c}).intersperse($sep);
+        let f = intersperse;
+        let g = f([1 2] +);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[a,b,c,d,e,f,g]");
+            assert_eq!(stringify!($b), "[a]");
+            assert_eq!(stringify!($c), "[]");
+            assert_eq!(stringify!($d).replace(" ", ""), "{x;y}");
+            assert_eq!(stringify!($e).replace(" ", ""), "((a)|[b]|{c})");
+            assert_eq!(stringify!($g).replace(" ", ""), "[1+2]");
+        }
+    }
+}
+
+#[test]
+fn cartesian() {
+    use rukt::builtins::cartesian;
+    rukt! {
+        let a = [a b].cartesian([1 2]);
+        let b = {x (y z)}.cartesian(([w]));
+        let c = [].cartesian([1 2]);
+        let d = (a b).cartesian({});
+        let other = [1];
+        let e = [a b c].cartesian($other);
+        let f = cartesian;
+        let g = f([a] [b c]);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(a1)(a2)(b1)(b2)]");
+            assert_eq!(stringify!($b).replace(" ", ""), "{(x[w])((yz)[w])}");
+            assert_eq!(stringify!([$c $d]), "[[] ()]");
+            assert_eq!(stringify!($e).replace(" ", ""), "[(a1)(b1)(c1)]");
+            assert_eq!(stringify!($g).replace(" ", ""), "[(ab)(ac)]");
+        }
+    }
+}
+
+#[test]
+fn generate_dispatch() {
+    use rukt::builtins::generate_dispatch;
+    use std::cell::Cell;
+
+    trait Counter {
+        fn bump(&self);
+        fn value(&self) -> u32;
+        fn label(&self) -> String;
+    }
+
+    struct Single(Cell<u32>);
+    struct Double(Cell<u32>);
+
+    impl Counter for Single {
+        fn bump(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+        fn value(&self) -> u32 {
+            self.0.get()
+        }
+        fn label(&self) -> String {
+            "single".to_string()
+        }
+    }
+
+    impl Counter for Double {
+        fn bump(&self) {
+            self.0.set(self.0.get() + 2);
+        }
+        fn value(&self) -> u32 {
+            self.0.get()
+        }
+        fn label(&self) -> String {
+            format!("double {}", self.0.get())
+        }
+    }
+
+    rukt! {
+        let variants = { One: Single, Two: Double };
+        let name = generate_dispatch(Counter, AnyCounter, $variants, [bump (value -> u32) (label -> String)]);
+        expand {
+            let counters = [$name::One(Single(Cell::new(0))), $name::Two(Double(Cell::new(0)))];
+            for counter in &counters {
+                counter.bump();
+                counter.bump();
+            }
+            assert_eq!(counters.iter().map(Counter::value).collect::<Vec<_>>(), [2, 4]);
+            assert_eq!(counters.iter().map(Counter::label).collect::<Vec<_>>(), ["single", "double 4"]);
+            assert_eq!(stringify!($name), "AnyCounter");
+        }
+    }
+}
+
+#[test]
+fn map_indexed() {
+    use rukt::builtins::map_indexed;
+    rukt! {
+        fn pair($i:tt $x:tt) {
+            ($i $x)
+        }
+        let a = [a (b c) d].map_indexed($pair);
+        let b = {}.map_indexed($pair);
+        let f = map_indexed;
+        let c = f((x y) $pair);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(0a)(1(bc))(2d)]");
+            assert_eq!(stringify!($b), "{}");
+            assert_eq!(stringify!($c).replace(" ", ""), "((0x)(1y))");
+        }
+    }
+    rukt! {
+        fn index($i:tt $x:tt) {
+            i
+        }
+        let indices = [a b c d e f g h i j k l m n o p q r].map_indexed($index);
+        expand {
+            assert_eq!(stringify!($indices), "[0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17]");
+        }
+    }
+}
+
+#[test]
+fn swap() {
+    use rukt::builtins::swap;
+    rukt! {
+        let a = [a b c d].swap(1 3);
+        let b = [a b c d].swap(3 1);
+        let c = (x [y z] {w}).swap(0 2);
+        let d = [a b].swap(1 1);
+        let index = 0;
+        let f = swap;
+        let e = f({1 2} $index 1);
+        expand {
+            assert_eq!(stringify!($a $b), "[a d c b] [a d c b]");
+            assert_eq!(stringify!($c).replace(" ", ""), "({w}[yz]x)");
+            assert_eq!(stringify!($d), "[a b]");
+            assert_eq!(stringify!($e).replace(" ", ""), "{21}");
+        }
+    }
+}
+
+#[test]
+fn zip_longest() {
+    use rukt::builtins::zip_longest;
+    rukt! {
+        let a = [a b c].zip_longest([1] X);
+        let b = [a].zip_longest([1 2 3 4 5 6] _);
+        let c = [1 2 3 4 5].zip_longest([a b c d e] _);
+        let d = [].zip_longest([] _);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(a1)(bX)(cX)]");
+            assert_eq!(stringify!($b).replace(" ", ""), "[(a1)(_2)(_3)(_4)(_5)(_6)]");
+            assert_eq!(stringify!($c).replace(" ", ""), "[(1a)(2b)(3c)(4d)(5e)]");
+            assert_eq!(stringify!($d), "[]");
+        }
+    }
+    rukt! {
+        let other = [x y];
+        let fill = [z];
+        let f = zip_longest;
+        let a = f([1 2 3] $other $fill);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(1x)(2y)(3[z])]");
+        }
+    }
+}
+
+#[test]
+fn dispatch() {
+    use function_table::helpers;
+    use rukt::builtins::dispatch;
+    rukt! {
+        use helpers;
+        let value = 5;
+        let a = helpers.dispatch(double $value);
+        let b = helpers.dispatch(pair $value [x]);
+        let f = dispatch;
+        let c = f($helpers double 21);
+        expand {
+            assert_eq!($a, 10);
+            assert_eq!(stringify!($b), "(5, [x])");
+            assert_eq!($c, 42);
+        }
+    }
+}
+
+#[test]
+fn let_export_pattern() {
+    rukt! {
+        use destructured::{first, name, op};
+        let [$($n:ident)*] = name;
+        expand {
+            assert_eq!(stringify!($first $name $op $($n)*), "1 [add sub] [+ -] add sub");
+        }
+    }
+    assert_eq!(destructured::LOCAL, "1 add sub");
+}
+
+#[test]
+fn ordering_comparisons() {
+    rukt! {
+        let a = 3 < 200;
+        let b = 200 < 3;
+        let c = 3 < 3;
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, false]);
+        }
+    }
+    rukt! {
+        let a = 200 > 3;
+        let b = 3 > 200;
+        let c = 3 > 3;
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, false]);
+        }
+    }
+    rukt! {
+        let small = 3;
+        let large = 200;
+        let a = small <= large;
+        let b = large <= small;
+        let c = small <= small;
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, true]);
+        }
+    }
+    rukt! {
+        let small = 3;
+        let large = 200;
+        let a = large >= small;
+        let b = small >= large;
+        let c = small >= small;
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, true]);
+        }
+    }
+    rukt! {
+        let mixed = 3 < 200 && 200 > 100 || false;
+        expand {
+            assert_eq!($mixed, true);
+        }
+    }
+}
+
+#[test]
+fn chained_comparisons() {
+    rukt! {
+        let increasing = 1 < 2 < 3;
+        let unordered = 1 < 3 < 2;
+        let long = 0 <= 0 < 1 < 2 <= 2 < 255;
+        expand {
+            assert_eq!($increasing, true);
+            assert_eq!($unordered, false);
+            assert_eq!($long, true);
+        }
+    }
+    rukt! {
+        let equal = 7 == 7 == 7;
+        let different = 7 == 7 != 8;
+        let not_all_equal = 7 == 7 == 8;
+        expand {
+            assert_eq!($equal, true);
+            assert_eq!($different, true);
+            assert_eq!($not_all_equal, false);
+        }
+    }
+    rukt! {
+        let combined = 1 < 2 < 3 && 3 > 2 > 1 || false;
+        let negated = !true == false;
+        expand {
+            assert_eq!($combined, true);
+            assert_eq!($negated, true);
+        }
+    }
+}
+
+#[cfg(feature = "proc")]
+#[test]
+fn suffixed_integers() {
+    rukt! {
+        let value = 42u32;
+        let a = value < 50u32;
+        let b = value > 50;
+        let c = 7u8 <= 7u8 < 8;
+        expand {
+            assert_eq!([$a, $b, $c], [true, false, true]);
+        }
+    }
+    rukt! {
+        let exclusive = 1u8..4u8;
+        let inclusive = 0..=2i64;
+        expand {
+            assert_eq!(stringify!($exclusive $inclusive), "[1u8 2u8 3u8] [0i64 1i64 2i64]");
+        }
+    }
+}
+
+mod aliases {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) let opcodes as ALIASED_OPCODES = [add sub];
+        pub(crate) fn describe as aliased_describe($name:ident) {
+            let value = [$name $opcodes];
+            value
+        }
+        let described = describe(mul);
+        expand {
+            pub const DESCRIBED: &str = stringify!($opcodes $described);
+        }
+    }
+
+    // the internal names are free to be reused
+    macro_rules! opcodes {
+        () => {};
+    }
+    macro_rules! describe {
+        () => {};
+    }
+    opcodes!();
+    describe!();
+}
+
+#[test]
+fn export_alias() {
+    assert_eq!(aliases::DESCRIBED.replace(" ", ""), "[addsub][mul[addsub]]");
+    rukt! {
+        use aliases::ALIASED_OPCODES;
+        use aliases::aliased_describe;
+        let described = aliased_describe(div);
+        expand {
+            assert_eq!(stringify!($ALIASED_OPCODES $described).replace(" ", ""), "[addsub][div[addsub]]");
+        }
+    }
+}
+
+#[test]
+fn for_loop() {
+    let mut names = Vec::new();
+    rukt! {
+        let items = [a b c];
+        for ($i, $item) in items {
+            let upper = if i == 1 { [$item B] } else { [$item] };
+            expand {
+                names.push(concat!("item_", $i, ": ", stringify!($upper)));
+            }
+        }
+        for _ in () {
+            expand {
+                names.push("unreachable");
+            }
+        }
+        for {$name:ident} in ({x} {y}) {
+            expand {
+                names.push(stringify!($name));
+            }
+        }
+        let done = true;
+        expand {
+            assert!($done);
+        }
+    }
+    assert_eq!(names, ["item_0: [a]", "item_1: [b B]", "item_2: [c]", "x", "y"]);
+}
+
+mod constants {
+    use rukt::rukt;
+    rukt! {
+        #[allow(dead_code)]
+        pub(crate) const WIDTH: u32 = 8;
+        pub(crate) const MASK: u32 = { (1 << $WIDTH) - 1 };
+        pub(crate) const NAMES: [&str; 2] = ["low", "high"];
+    }
+}
+
+#[test]
+fn const_export() {
+    assert_eq!(constants::WIDTH, 8);
+    assert_eq!(constants::MASK, 255);
+    assert_eq!(constants::NAMES, ["low", "high"]);
+    rukt! {
+        use constants::{WIDTH, MASK, NAMES};
+        expand {
+            assert_eq!(stringify!($WIDTH $MASK $NAMES).replace(" ", ""), "8{(1<<8)-1}[\"low\",\"high\"]");
+        }
+    }
+}
+
+mod statics {
+    use rukt::rukt;
+    rukt! {
+        pub(crate) static TABLE: [u32; 9] = [1 2 3 4 5 6 7 8 9];
+        pub(crate) static PAIRS: [(u8, u8); 2] = [(1, 2), (3, 4)];
+        static mut COUNTER: u32 = 0;
+        let copy = TABLE;
+        expand {
+            pub(crate) fn bump() -> u32 {
+                unsafe {
+                    COUNTER += 1;
+                    COUNTER
+                }
+            }
+            pub(crate) const COPY: &str = stringify!($copy);
+        }
+    }
+}
+
+#[test]
+fn static_item() {
+    assert_eq!(statics::TABLE, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(statics::PAIRS, [(1, 2), (3, 4)]);
+    assert_eq!(statics::bump(), 1);
+    assert_eq!(statics::COPY, "[1 2 3 4 5 6 7 8 9]");
+}
+
+mod registry {
+    pub mod base {
+        rukt::rukt! {
+            pub(crate) registry plugins;
+        }
+    }
+
+    pub mod first {
+        rukt::rukt! {
+            pub(crate) append super::base::plugins {
+                alpha => 1,
+            };
+        }
+    }
+
+    pub mod second {
+        rukt::rukt! {
+            append super::first::plugins {
+                beta => 2,
+            };
+            pub(crate) append plugins {
+                gamma => 3,
+            };
+            let local = plugins;
+            expand {
+                pub const LOCAL: &str = stringify!($local);
+            }
+        }
+    }
+}
+
+#[test]
+fn registry_append() {
+    assert_eq!(registry::second::LOCAL.replace(" ", ""), "{alpha=>1,beta=>2,gamma=>3,}");
+    rukt! {
+        use registry::base::plugins as base;
+        use registry::first::plugins as first;
+        use registry::second::plugins;
+        let {$($name:ident => $value:expr,)*} = plugins;
+        expand {
+            assert_eq!(stringify!($base), "{}");
+            assert_eq!(stringify!($first).replace(" ", ""), "{alpha=>1,}");
+            assert_eq!([$(stringify!($name)),*], ["alpha", "beta", "gamma"]);
+            assert_eq!([$($value),*], [1, 2, 3]);
+        }
+    }
+}
+
+#[test]
+fn range() {
+    rukt! {
+        let [$($small:tt)*] = 2..5;
+        let [$($large:tt)*] = 14..=33;
+        let empty = 7..7;
+        let literal = [0..3];
+        expand {
+            assert_eq!([$($small),*], [2, 3, 4]);
+            assert_eq!([$($large),*], (14..=33).collect::<Vec<u8>>().as_slice());
+            assert_eq!(stringify!($empty), "[]");
+            assert_eq!(stringify!($literal), "[0 .. 3]");
+        }
+        for ($i, $n) in 0..3 {
+            expand {
+                assert_eq!($i, $n);
+            }
+        }
+    }
+}
+
+#[test]
+fn default_operator() {
+    use rukt::builtins::starts_with;
+    rukt! {
+        fn fail($($args:tt)*) {
+            error "evaluated {args}";
+        }
+        let missing = false;
+        let first = missing ?? [1 2 3] ?? fail();
+        let second = missing ?? missing ?? [] ?? if fail() { 1 } else if !fail(1..3) { 2 } else { 3 } ?? fail.apply(4);
+        let third = missing == false && missing ?? 1 == 2;
+        let fourth = [x y].starts_with(y) ?? [x y].starts_with(x);
+        let fifth = if missing ?? true { 5 } else { fail() };
+        let sixth = 1 ?? parse::<expr>(2) ?? parse_many::<expr, ;>(3; 4);
+        expand {
+            assert_eq!(stringify!($first), "[1 2 3]");
+            assert_eq!(stringify!($second), "[]");
+            assert_eq!($third, false);
+            assert_eq!($fourth, true);
+            assert_eq!($fifth, 5);
+            assert_eq!($sixth, 1);
+        }
+    }
+}
+
+#[test]
+fn count_tokens_deep() {
+    use rukt::builtins::{count_tokens_deep, deep_map};
+    rukt! {
+        let a = [a (b c) d].count_tokens_deep();
+        let b = [1 2 3 4 5 6 7 (8 [9 10 {11}] 12) 13 14 15 16 17 18 [] 19].count_tokens_deep();
+        let c = [($x:tt) => { $x }].count_tokens_deep();
+        let count = count_tokens_deep;
+        let d = [[a b] [c d]].deep_map($count);
+        expand {
+            assert_eq!([$a, $b, $c], [4, 19, 7]);
+            assert_eq!(stringify!($d), "[[1 1] [1 1]]");
+        }
+    }
+}
+
+#[test]
+fn block() {
+    use rukt::builtins::block;
+    rukt! {
+        let outer = 0;
+        let first = block {
+            let a = outer;
+            let b = [x y];
+            fn f() {
+                a
+            }
+        };
+        let b = block {};
+        let c = block {
+            let v0 = 0; let v1 = 1; let v2 = 2; let v3 = 3; let v4 = 4; let v5 = 5;
+            let v6 = 6; let v7 = 7; let v8 = 8; let v9 = 9; let v10 = 10; let v11 = 11;
+            let v12 = 12; let v13 = 13; let v14 = 14; let v15 = 15; let v16 = 16; let v17 = 17;
+        };
+        let d = block {
+            let nested = block {
+                let inner = 1;
+            };
+        };
+        let [(a $x:tt) (b $y:tt) (f $f:tt)] = first;
+        let [$(($name:ident $value:tt))*] = c;
+        expand {
+            assert_eq!(stringify!($x $y).replace(" ", ""), "0[xy]");
+            assert!(stringify!($f).starts_with("{ fn f"));
+            assert_eq!(stringify!($b), "[]");
+            assert_eq!([$(stringify!($name)),*].len(), 18);
+            assert_eq!([$($value),*], [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17]);
+            assert_eq!(stringify!($d).replace(" ", ""), "[(nested[(inner1)])]");
+        }
+    }
+}
+
+#[test]
+fn expand_count() {
+    use rukt::builtins::expand_count;
+    rukt! {
+        let fields = [a b c];
+        let [$($field:ident)*] = fields;
+        let structs = expand_count {
+            $(
+                #[allow(non_camel_case_types, dead_code)]
+                struct $field { value: u32 }
+            )*
+        };
+        let macros = expand_count {
+            macro_rules! counted_identity {
+                ($$x:tt) => { $$x };
+            }
+            const COUNTED: u32 = counted_identity!(7);
+        };
+        let empty = expand_count {};
+        expand {
+            assert_eq!([$structs, $macros, $empty], [3, 2, 0]);
+        }
+    }
+    assert_eq!(COUNTED, 7);
+}
+
+#[test]
+fn filter_matching() {
+    use rukt::builtins::filter_matching;
+    rukt! {
+        let a = [(a 1) foo (b 2) (c) [d 4]].filter_matching(($name:ident $value:tt));
+        let b = {1 x "y" 2.0 [3]}.filter_matching($value:literal);
+        let c = [].filter_matching($value:tt);
+        let e = [[1 2] [3] [4 5 6]].filter_matching([$first:tt $($rest:tt)+]);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(a1)(b2)]");
+            assert_eq!(stringify!($b).replace(" ", ""), "{1\"y\"2.0}");
+            assert_eq!(stringify!($c), "[]");
+            assert_eq!(stringify!($e).replace(" ", ""), "[[12][456]]");
+        }
+    }
+}
+
+#[test]
+fn replace_pattern() {
+    use rukt::builtins::replace_pattern;
+    rukt! {
+        let a = [foo(1) bar foo(2) foo].replace_pattern(foo($n:tt) => call($n));
+        let b = (1 1 1 2).replace_pattern(1 1 => 3);
+        let c = {a, b, c}.replace_pattern(, =>);
+        let d = [].replace_pattern($x:tt => x);
+        let e = [(a 1 2) [b] (c)].replace_pattern(($k:ident $($v:tt)+) => [$($v)+]);
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[call(1)barcall(2)foo]");
+            assert_eq!(stringify!($b), "(3 1 2)");
+            assert_eq!(stringify!($c), "{a b c}");
+            assert_eq!(stringify!($d), "[]");
+            assert_eq!(stringify!($e).replace(" ", ""), "[[12][b](c)]");
+        }
+    }
+}
+
+#[test]
+fn assert_len() {
+    use rukt::builtins::{assert_len, assert_len_at_least, assert_len_at_most};
+    rukt! {
+        let n = 3;
+        let [$a:tt $b:tt $c:tt] = [x (y z) w].assert_len($n);
+        let d = [].assert_len(0);
+        let e = {1 2 3 4}.assert_len_at_least(2);
+        let f = (1).assert_len_at_most(1);
+        let check = assert_len_at_most;
+        let g = check([a b] 10);
+        expand {
+            assert_eq!(stringify!($a $b $c).replace(" ", ""), "x(yz)w");
+            assert_eq!(stringify!($d), "[]");
+            assert_eq!(stringify!($e).replace(" ", ""), "{1234}");
+            assert_eq!(stringify!($f), "(1)");
+            assert_eq!(stringify!($g), "[a b]");
+        }
+    }
+}
+
+#[test]
+fn default_for() {
+    use rukt::builtins::default_for;
+    rukt! {
+        let [$ty:tt] = [i64];
+        let a = default_for($ty);
+        let b = default_for(f32);
+        let c = default_for(char);
+        let d = default_for((&str));
+        let e = default_for((Option<String>));
+        let f = default_for((Vec<(u8, u8)>));
+        let g = default_for((std::time::Duration));
+        let h = default_for((std::string::String) "fallback");
+        let i = default_for;
+        let j = i(());
+        expand {
+            let a: i64 = $a;
+            let b: f32 = $b;
+            let c: char = $c;
+            let d: &str = $d;
+            let e: Option<String> = $e;
+            let f: Vec<(u8, u8)> = $f;
+            let g: std::time::Duration = $g;
+            assert_eq!((a, b, c, d, e, f, g), (0, 0.0, '\0', "", None, vec![], std::time::Duration::ZERO));
+            assert_eq!($h, "fallback");
+            assert_eq!(stringify!($j), "()");
+        }
+    }
+}
+
+macro_rules! seeded_constants {
+    ($prefix:ident $($value:tt)*) => {
+        rukt::rukt_with! {
+            { prefix = $prefix, values = [$($value)*], empty = [] }
+            let is_empty = values == empty;
+            expand {
+                const SEEDED_PREFIX: &str = stringify!($prefix);
+                const SEEDED_VALUES: &str = stringify!($values);
+                const SEEDED_EMPTY: &str = stringify!($is_empty);
+            }
+        }
+    };
+}
+
+#[test]
+fn rukt_with() {
+    seeded_constants!(item 4 5 6);
+    assert_eq!(SEEDED_PREFIX, "item");
+    assert_eq!(SEEDED_VALUES, "[4 5 6]");
+    assert_eq!(SEEDED_EMPTY, "false");
+    rukt::rukt_with! {
+        { a = 1, b = [x y], }
+        let pair = [$a $b];
+        expand {
+            assert_eq!(stringify!($pair).replace(" ", ""), "[1[xy]]");
+        }
+    }
+}
+
+macro_rules! stringify_result {
+    ($value:tt $name:ident) => {
+        const $name: &str = stringify!($value);
+    };
+}
+
+macro_rules! compare_tokens {
+    ([$($a:tt)*] [$($b:tt)*]) => {
+        rukt::eval_to!(([$($a)*] == [$($b)*]) (stringify_result; COMPARISON));
+    };
+}
+
+macro_rules! apply_function {
+    ($f:tt $x:tt) => {
+        rukt::eval_to!(($f($x)) (stringify_result; APPLIED));
+    };
+}
+
+#[test]
+fn eval_to() {
+    compare_tokens!([1 2] [1 2]);
+    rukt::eval_to!(([x y].rukt::builtins::starts_with(x).rukt::builtins::not()) (stringify_result; BUILTIN));
+    apply_function!({ fn twice($x:tt) { [$x $x] } } 1);
+    assert_eq!(COMPARISON, "true");
+    assert_eq!(BUILTIN, "false");
+    assert_eq!(APPLIED, "[1 1]");
+}
+
+rukt::builtin! {
+    first_or {
+        ([$first:tt $($_:tt)*], ($default:tt)) => { $first };
+        ([], ($default:tt)) => { $default };
+    }
+}
+
+#[test]
+fn custom_builtin() {
+    rukt! {
+        let fallback = 0;
+        let a = [1 2 3].first_or($fallback);
+        let b = [].first_or($fallback);
+        let f = first_or;
+        let c = f([] 7);
+        let d = f([4 5] $fallback);
+        expand {
+            assert_eq!([$a, $b, $c, $d], [1, 0, 7, 4]);
+        }
+    }
+}
+
+#[test]
+fn struct_fields() {
+    use rukt::builtins::struct_fields;
+    rukt! {
+        let a = {
+            #[derive(Default)]
+            pub(crate) struct A {
+                /// Documented.
+                pub a: u8,
+                b: Vec<(u16, u32)>
+            }
+        }.struct_fields();
+        let b = [struct B {}].struct_fields();
+        let f = struct_fields;
+        let c = f((struct C { c: [u8; 2], }));
+        let [$(($field:ident $ty:ty))*] = c;
+        expand {
+            assert_eq!(stringify!($a).replace(" ", ""), "[(au8)(bVec<(u16,u32)>)]");
+            assert_eq!(stringify!($b), "[]");
+            assert_eq!(stringify!($($field)*), "c");
+            let value: ($($ty,)*) = ([1, 2],);
+            assert_eq!(value.0, [1, 2]);
+        }
+    }
+}
+
+#[test]
+fn assert_matches() {
+    use rukt::builtins::{assert_matches, starts_with};
+    rukt! {
+        let [$name:ident] = [x];
+        let fields = [$name: u8, y: u16].assert_matches([$($field:ident: $type:ty),*]);
+        let result = fields.assert_matches([x: $first:ty, $($rest:tt)*]).starts_with(x);
+        expand {
+            assert_eq!(stringify!($fields).replace(" ", ""), "[x:u8,y:u16]");
+            assert_eq!($result, true);
+        }
+    }
+}
+
+#[test]
+fn transparent_fragments() {
+    use rukt::builtins::starts_with;
+    rukt! {
+        let ($name:ident $lifetime:lifetime $token:tt) = (foo 'a [1 2]);
+        let a = [$name] == [foo];
+        let b = [$lifetime] == ['a];
+        let c = token.starts_with(1);
+        expand {
+            assert_eq!([$a, $b, $c], [true, true, true]);
+        }
+    }
+}
+
+#[test]
+fn builtin_values() {
+    use rukt::builtins::{apply, reduce, starts_with};
+    rukt! {
+        fn add($a:tt $b:tt) {
+            ($a + $b)
+        }
+        let prefix = starts_with;
+        let fold = reduce;
+        let a = prefix([1 2 3] 1 2);
+        let b = fold([1 2 3] $add);
+        let c = apply($prefix [[x y] y]);
+        expand {
+            assert_eq!([$a, $c], [true, false]);
+            assert_eq!($b, 6);
+        }
+    }
+}
+
+mod do_expression {
+    use rukt::builtins::deep_map;
+    use rukt::rukt;
+
+    rukt! {
+        fn define($name:ident) {
+            do {
+                expand {
+                    pub const $name: &str = stringify!($name);
+                }
+                name
+            }
+        }
+        let result = [FIRST SECOND].deep_map($define);
+        expand {
+            pub const RESULT: &str = stringify!($result);
+        }
+    }
+}
+
+#[test]
+fn do_expression() {
+    assert_eq!([do_expression::FIRST, do_expression::SECOND], ["FIRST", "SECOND"]);
+    assert_eq!(do_expression::RESULT, "[() ()]");
+}
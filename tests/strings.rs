@@ -0,0 +1,128 @@
+#![cfg(feature = "strings")]
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+#![recursion_limit = "512"]
+
+use rukt::builtins::{deep_map, lines, parse, stringify_compact, to_tokens, trim};
+use rukt::rukt;
+
+#[test]
+fn trim() {
+    rukt! {
+        let a = "  hello  ".trim();
+        let b = "\t\n hello\r\n".trim();
+        let c = r#"  "quoted"  "#.trim();
+        let d = "".trim();
+        expand {
+            assert_eq!([$a, $b, $c, $d], ["hello", "hello", "\"quoted\"", ""]);
+        }
+    }
+}
+
+#[test]
+fn lines() {
+    rukt! {
+        let [$($a:tt)*] = "a\nb\n".lines();
+        let [$($b:tt)*] = "a\r\nb".lines();
+        let [$($c:tt)*] = "a\n\n\\n\u{62}\x63".lines();
+        let [$($d:tt)*] = r"a\nb".lines();
+        let [$($e:tt)*] = "".lines();
+        expand {
+            assert_eq!([$($a),*], ["a", "b"]);
+            assert_eq!([$($b),*], ["a", "b"]);
+            assert_eq!([$($c),*], ["a", "", "\\nbc"]);
+            assert_eq!([$($d),*], ["a\\nb"]);
+            assert_eq!(stringify!($($e)*), "");
+        }
+    }
+}
+
+#[test]
+fn fragment() {
+    rukt! {
+        let value = parse::<literal>(" a ");
+        let trimmed = value.trim();
+        expand {
+            assert_eq!($trimmed, "a");
+        }
+    }
+}
+
+#[test]
+fn loop_over_lines() {
+    let mut names = Vec::new();
+    rukt! {
+        for line in "add\nsub\nmul".lines() {
+            expand {
+                names.push($line);
+            }
+        }
+    }
+    assert_eq!(names, ["add", "sub", "mul"]);
+}
+
+#[test]
+fn function_value() {
+    rukt! {
+        let f = trim;
+        let g = lines;
+        let [$($names:tt)*] = g(" a \n b ").deep_map($f);
+        expand {
+            assert_eq!([$($names),*], ["a", "b"]);
+        }
+    }
+}
+
+#[test]
+fn to_tokens() {
+    rukt! {
+        let a = "[1 2 3]".to_tokens();
+        let b = "x + 1".to_tokens();
+        let c = "".to_tokens();
+        let d = "\"nested\"".to_tokens();
+        let [$($e:tt)*] = a;
+        expand {
+            assert_eq!(stringify!($a), "[1 2 3]");
+            assert_eq!(stringify!($b), "[x + 1]");
+            assert_eq!(stringify!($c), "[]");
+            assert_eq!($d, "nested");
+            assert_eq!([$($e),*], [1, 2, 3]);
+        }
+    }
+}
+
+#[test]
+fn stringify_compact() {
+    rukt! {
+        let a = [x::y=>'a &'b].stringify_compact();
+        let b = {f( a ,b ) [[c]]}.stringify_compact();
+        let c = [#[x] y: &str].stringify_compact();
+        let d = "a".stringify_compact();
+        let e = (a.b() ..= -1).stringify_compact() == "(a . b () ..= - 1)";
+        let f = stringify_compact;
+        let g = f([< < = <<=]);
+        expand {
+            assert_eq!($a, "[x :: y => 'a & 'b]");
+            assert_eq!($b, "{f (a , b) [[c]]}");
+            assert_eq!($c, "[# [x] y : & str]");
+            assert_eq!($d, "\"a\"");
+            assert!($e);
+            assert_eq!($g, "[< < = <<=]");
+        }
+    }
+}
+
+#[test]
+fn stringify_compact_matches_utils() {
+    macro_rules! check {
+        ([$($S:tt)*] $T:tt) => {
+            rukt! {
+                let value = $T.stringify_compact();
+                expand {
+                    assert_eq!($value, $($S)*);
+                }
+            }
+        };
+    }
+
+    rukt::utils::stringify_compact!([[a::b->c (1, 2.0) {'d} #![x] &&y]] (check; [a::b->c (1, 2.0) {'d} #![x] &&y]));
+}
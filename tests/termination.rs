@@ -0,0 +1,83 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::rukt;
+
+rukt! {}
+
+rukt! {
+    ;;
+}
+
+rukt! {
+    expand {
+        const TRAILING_EXPAND: u32 = 1;
+    }
+}
+
+rukt! {
+    let value = 2;;
+    expand {
+        const TRAILING_SEMICOLONS: u32 = $value;
+    };;
+}
+
+rukt! {
+    let condition = true;
+    if condition {
+        expand {
+            const TRAILING_IF: u32 = 3;
+        }
+    }
+}
+
+rukt! {
+    let condition = false;
+    if condition {
+    } else {
+        expand {
+            const TRAILING_ELSE: u32 = 4;
+        }
+    };
+}
+
+rukt! {
+    fn define() {
+        ;
+        expand {
+            const FUNCTION_BODY: u32 = 5;
+        };;
+    };
+    define();;
+}
+
+rukt! {
+    let value = if true { 6 } else { 0 };;
+    let [$($items:tt)*] = [7 8];
+    for item in [$($items)*] {};
+    expand {
+        const EXPRESSION_BLOCK: [u32; 3] = [$value, $($items),*];
+    }
+}
+
+#[test]
+fn termination() {
+    assert_eq!(TRAILING_EXPAND, 1);
+    assert_eq!(TRAILING_SEMICOLONS, 2);
+    assert_eq!(TRAILING_IF, 3);
+    assert_eq!(TRAILING_ELSE, 4);
+    assert_eq!(FUNCTION_BODY, 5);
+    assert_eq!(EXPRESSION_BLOCK, [6, 7, 8]);
+}
+
+#[test]
+fn block_result() {
+    rukt! {
+        let a = if true { 1 } else {};
+        let b = if true { 1; } else {};
+        let c = if true { 1;; } else {};
+        let d = if true {} else { 1 };
+        expand {
+            assert_eq!(stringify!($a $b $c $d), "1 () () ()");
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Exports used by the cross-crate tests.
+
+pub mod helpers {
+    use rukt::rukt;
+
+    rukt! {
+        #[macro_export]
+        pub fn double($x:tt) {
+            [$x $x]
+        }
+
+        #[macro_export]
+        pub let names = [crate::helpers::NAME];
+    }
+
+    pub const NAME: &str = "upstream";
+}
+
+pub mod ops {
+    use rukt::rukt;
+
+    rukt! {
+        #[macro_export]
+        pub fn quad($x:tt) {
+            let pair = crate::helpers::double($x);
+            crate::helpers::double($pair)
+        }
+    }
+
+    rukt! {
+        let value = quad(1);
+        expand {
+            pub const LOCAL: &str = stringify!($value);
+        }
+    }
+}
+
+pub mod settings {
+    use rukt::rukt;
+
+    rukt! {
+        #[macro_export]
+        pub let defaults as upstream_primary_defaults in primary = [1 2];
+    }
+
+    rukt! {
+        #[macro_export]
+        pub let defaults as upstream_secondary_defaults in secondary = [3];
+    }
+}
+
+pub mod builtins {
+    rukt::builtin! {
+        #[doc(hidden)]
+        #[macro_export]
+        builtin_swap as crate::builtins::swap {
+            ([$a:tt $b:tt], ()) => { [$b $a] };
+        }
+    }
+
+    pub use builtin_swap as swap;
+}
+
+#[doc(hidden)]
+pub use rukt as __rukt;
+
+#[macro_export]
+macro_rules! wrapped_constants {
+    ($name:ident $($value:tt)*) => {
+        $crate::wrapped_constants!(@inner [$name $($value)*] $);
+    };
+    (@inner [$name:ident $($value:tt)*] $d:tt) => {
+        $crate::__rukt::rukt_wrapped!($d {
+            fn double($d x:tt) {
+                [$d x $d x]
+            }
+            let values = [$($value)*];
+            let [$d first:tt $d($d rest:tt)*] = values;
+            let pair = double($d first);
+            pub(self) let exported = pair;
+            expand {
+                const $name: &str = stringify!($d pair);
+                const REST: &str = stringify!($d($d rest)*);
+            }
+        });
+    };
+}
@@ -0,0 +1,116 @@
+#![cfg_attr(feature = "nightly-metavar-expr", feature(macro_metavar_expr))]
+
+use rukt::utils::{
+    escape, escape_metavariables, escape_repetitions, replace_token, select, tokens_equal, unescape,
+};
+
+macro_rules! define {
+    ([$($T:tt)*] $I:ident) => {
+        const $I: &str = stringify!($($T)*);
+    };
+}
+
+macro_rules! unescape_dollar {
+    ([$($T:tt)*] $N:tt) => {
+        unescape!([$($T)*] [] [<dollar>] $N);
+    };
+}
+
+macro_rules! select_escaped {
+    ([$($T:tt)*] $N:tt) => {
+        select!([[$($T)*]] [
+            [[[__rukt_dollar x:tt]] [literal]]
+            [[[$_:tt]] [metavariable]]
+        ] $N $);
+    };
+}
+
+#[test]
+fn unescape() {
+    unescape!([a <dollar>b [<dollar>c {d <dollar>}]] [] [<dollar>] (define; A));
+    unescape!([(dollar) x ((dollar)) (dollar)(y)] [] [(dollar)] (define; B));
+    unescape!([<dollar> <dollar>] [start] [<dollar>] (define; C));
+    unescape!([<] [] [<dollar>] (define; D));
+    assert_eq!(A.replace(" ", ""), "a$b[$c{d$}]");
+    assert_eq!(B.replace(" ", ""), "$x($)$(y)");
+    assert_eq!(C.replace(" ", ""), "start$$");
+    assert_eq!(D, "<");
+}
+
+#[test]
+fn round_trip() {
+    escape!([$name:ident($($arg:expr),*)] [] [<dollar>] (unescape_dollar; (define; A)));
+    escape!([($($a:tt)*) [{$b $($c),+}] $] [] [<dollar>] (unescape_dollar; (define; B)));
+    escape_repetitions!([$($x:tt),* {$($y),*}] [] [<dollar>] (unescape_dollar; (define; C)));
+    assert_eq!(A.replace(" ", ""), "$name:ident($($arg:expr),*)");
+    assert_eq!(B.replace(" ", ""), "($($a:tt)*)[{$b$($c),+}]$");
+    assert_eq!(C.replace(" ", ""), "$($x:tt),*{$($y),*}");
+}
+
+#[test]
+fn escape_metavariables() {
+    escape_metavariables!([$name:ident($($arg:expr),*)] [] [<dollar>] (define; A));
+    escape_metavariables!([{$a [$ ($b) $c] $} $crate $] [] [#] (define; B));
+    escape_metavariables!([$x $$y $($($z)*)*] [] [<dollar>] (unescape_dollar; (define; C)));
+    assert_eq!(A.replace(" ", ""), "<dollar>name:ident($(<dollar>arg:expr),*)");
+    assert_eq!(B.replace(" ", ""), "{#a[$(#b)#c]$}#crate$");
+    assert_eq!(C.replace(" ", ""), "$x$$y$($($z)*)*");
+}
+
+#[test]
+fn replace_token() {
+    replace_token!([x (x [x {x}]) y] [] [x] [z] (define; A));
+    replace_token!([x x (x x) xx] [] [x] [1 2] (define; B));
+    replace_token!([a + b (+ c) +=] [] [+] [-] (define; C));
+    replace_token!([a, b, (c,)] [] [,] [] (define; D));
+    replace_token!([crate::f(crate)] [] [crate] [$crate] (define; E));
+    replace_token!([$a ($b)] [start] [$] [#] (define; F));
+    replace_token!([(a) [(a)] a] [] [(a)] [b] (define; G));
+    assert_eq!(A.replace(" ", ""), "z(z[z{z}])y");
+    assert_eq!(B.replace(" ", ""), "1212(1212)xx");
+    assert_eq!(C.replace(" ", ""), "a-b(-c)+=");
+    assert_eq!(D.replace(" ", ""), "ab(c)");
+    assert_eq!(E.replace(" ", ""), "$crate::f($crate)");
+    assert_eq!(F.replace(" ", ""), "start#a(#b)");
+    assert_eq!(G.replace(" ", ""), "b[b]a");
+}
+
+#[test]
+fn tokens_equal() {
+    macro_rules! result {
+        ($R:literal $I:ident) => {
+            const $I: bool = $R;
+        };
+    }
+
+    tokens_equal!([a a] (result; A));
+    tokens_equal!([a b] (result; B));
+    tokens_equal!([$ $] (result; C));
+    tokens_equal!([$ a] (result; D));
+    tokens_equal!([[$x ($($y)*)] [$x ($($y)*)]] (result; E));
+    tokens_equal!([[$x ($($y)*)] [$x [$($y)*]]] (result; F));
+    tokens_equal!([1 0x1] (result; G));
+    tokens_equal!([[::] [: :]] (result; H));
+    tokens_equal!([{} {}] (result; I));
+    assert_eq!([A, B, C, D, E, F, G, H, I], [true, false, true, false, true, false, false, false, true]);
+}
+
+#[test]
+fn select_captures() {
+    select!([(1 2 3)] [
+        [[($head:tt $($tail:tt)*)] [$head [$($tail)*]]]
+    ] (define; A) $);
+    select!([[a: u8, b: u16]] [
+        [[($($_:tt)*)] [parens]]
+        [[[$($name:ident: $ty:ty),*]] [$($name)* | $($ty)*]]
+    ] (define; B) $);
+    select!([{x}] [
+        [[[$($_:tt)*]] [brackets]]
+        [[$other:tt] [other $other]]
+    ] (define; C) $);
+    escape!([$x:tt] [] [__rukt_dollar] (select_escaped; (define; D)));
+    assert_eq!(A, "1 [2 3]");
+    assert_eq!(B, "a b | u8 u16");
+    assert_eq!(C.replace(" ", ""), "other{x}");
+    assert_eq!(D, "literal");
+}